@@ -0,0 +1,414 @@
+//! ACPI tables exported by the kernel under `/sys/firmware/acpi/tables`,
+//! their common header and a TUI to browse them
+
+pub mod tables;
+
+use super::buffer::Buffer;
+use super::devmem;
+use super::error::{Error, Result};
+use super::matrix::{self, DataType, Matrix, PAGE_SIZE};
+use super::textview::{self, TextView};
+use crossterm::event::{self, Event, KeyCode};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use tables::{BootPerformance, TableData, FBPT_HEADER_SIZE};
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::Terminal;
+
+pub const TABLES_DIR: &str = "/sys/firmware/acpi/tables";
+
+/// Tables loaded at runtime, such as SSDTs of hot-plugged CPUs
+const DYNAMIC_DIR: &str = "dynamic";
+
+/// Size of the common table header
+pub const HEADER_SIZE: usize = 36;
+
+/// Common header of the description tables
+#[derive(Debug, Clone, Serialize)]
+pub struct Header {
+    pub signature: String,
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: String,
+    pub oem_table_id: String,
+    pub oem_revision: u32,
+    pub creator_id: String,
+    pub creator_revision: u32,
+}
+
+fn ascii(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::Parse(format!(
+                "table of {} bytes is shorter than its header",
+                data.len()
+            )));
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        Ok(Self {
+            signature: ascii(&data[0..4]),
+            length: u32_at(4),
+            revision: data[8],
+            checksum: data[9],
+            oem_id: ascii(&data[10..16]),
+            oem_table_id: ascii(&data[16..24]),
+            oem_revision: u32_at(24),
+            creator_id: ascii(&data[28..32]),
+            creator_revision: u32_at(32),
+        })
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Signature:", self.signature)?;
+        writeln!(f, "{:<24}0x{:x} ({})", "Length:", self.length, self.length)?;
+        writeln!(f, "{:<24}{}", "Revision:", self.revision)?;
+        writeln!(f, "{:<24}0x{:02x}", "Checksum:", self.checksum)?;
+        writeln!(f, "{:<24}{}", "OEM ID:", self.oem_id)?;
+        writeln!(f, "{:<24}{}", "OEM Table ID:", self.oem_table_id)?;
+        writeln!(f, "{:<24}0x{:x}", "OEM Revision:", self.oem_revision)?;
+        writeln!(f, "{:<24}{}", "Creator ID:", self.creator_id)?;
+        writeln!(
+            f,
+            "{:<24}0x{:x}",
+            "Creator Revision:", self.creator_revision
+        )?;
+        Ok(())
+    }
+}
+
+/// Whether all bytes of a table including the checksum field sum up to zero
+pub fn checksum_valid(data: &[u8]) -> bool {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) == 0
+}
+
+/// A table file in sysfs
+#[derive(Debug, Clone, Serialize)]
+pub struct TableInfo {
+    /// file name relative to [`TABLES_DIR`], numbered if there are several
+    /// tables with the same signature, e.g. `SSDT2` or `dynamic/SSDT3`
+    pub name: String,
+    pub signature: String,
+    pub length: usize,
+    /// the FACS has no common header
+    pub header: Option<Header>,
+    pub checksum_valid: Option<bool>,
+}
+
+impl TableInfo {
+    fn new(name: &str, data: &[u8]) -> Self {
+        let file = name.rsplit('/').next().unwrap_or(name);
+        let signature = file.get(..4).unwrap_or(file).to_string();
+        let header = match signature.as_str() {
+            "FACS" => None,
+            _ => Header::parse(data).ok(),
+        };
+        Self {
+            name: String::from(name),
+            signature,
+            length: data.len(),
+            checksum_valid: header.as_ref().map(|_| checksum_valid(data)),
+            header,
+        }
+    }
+}
+
+/// Maps the names tables are known by to their signatures
+fn alias(name: &str) -> &str {
+    match name {
+        "FADT" => "FACP",
+        "MADT" => "APIC",
+        _ => name,
+    }
+}
+
+fn table_path(name: &str) -> String {
+    format!("{}/{}", TABLES_DIR, name)
+}
+
+fn read_file(name: &str) -> Result<Vec<u8>> {
+    fs::read(table_path(name)).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(format!("ACPI table {}", name)),
+        io::ErrorKind::PermissionDenied => {
+            Error::PermissionDenied(format!("{} requires root", table_path(name)))
+        }
+        _ => Error::Io(err),
+    })
+}
+
+/// Names of all tables, static ones first
+fn names() -> Result<Vec<String>> {
+    let mut names = vec![];
+    for dir in ["", DYNAMIC_DIR] {
+        let path = match dir {
+            "" => String::from(TABLES_DIR),
+            dir => table_path(dir),
+        };
+        let entries = match fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !dir.is_empty() => continue,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Unsupported(format!("{} is missing, no ACPI", path)))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let mut found: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            // skips the directories, `data` holds data of tables like BERT
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .map(|e| {
+                let file = e.file_name().to_string_lossy().to_string();
+                match dir {
+                    "" => file,
+                    dir => format!("{}/{}", dir, file),
+                }
+            })
+            .collect();
+        found.sort();
+        names.extend(found);
+    }
+    Ok(names)
+}
+
+/// Lists every table including the dynamically loaded ones
+pub fn list() -> Result<Vec<TableInfo>> {
+    names()?
+        .iter()
+        .map(|name| Ok(TableInfo::new(name, &read_file(name)?)))
+        .collect()
+}
+
+/// Resolves `name` to a table file, accepting the FADT and MADT names and
+/// signatures in lower case
+pub fn resolve(name: &str) -> Result<String> {
+    let upper = name.to_uppercase();
+    let wanted = alias(&upper);
+    let names = names()?;
+    names
+        .iter()
+        .find(|n| n.as_str() == name)
+        .or_else(|| names.iter().find(|n| n.to_uppercase() == wanted))
+        .cloned()
+        .ok_or_else(|| Error::NotFound(format!("ACPI table {}", name)))
+}
+
+/// Raw bytes of table `name`
+pub fn read(name: &str) -> Result<Vec<u8>> {
+    read_file(&resolve(name)?)
+}
+
+/// Reads the boot performance table the FPDT points to from physical memory
+fn boot_performance(address: u64) -> Result<BootPerformance> {
+    let bytes = |data: Vec<Option<u8>>| -> Result<Vec<u8>> {
+        data.into_iter()
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(Error::Unmapped {
+                offset: address,
+                length: FBPT_HEADER_SIZE as u64,
+            })
+    };
+    let header = bytes(devmem::read(address, FBPT_HEADER_SIZE, &DataType::Byte)?)?;
+    let length = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let fbpt = bytes(devmem::read(address, length.min(0x1000), &DataType::Byte)?)?;
+    BootPerformance::parse(&fbpt).ok_or_else(|| Error::Parse(String::from("invalid FBPT")))
+}
+
+/// A table with its decoded body
+#[derive(Debug, Clone, Serialize)]
+pub struct Table {
+    pub info: TableInfo,
+    pub data: TableData,
+}
+
+impl Table {
+    /// Reads and decodes table `name`
+    pub fn load(name: &str) -> Result<Self> {
+        let name = resolve(name)?;
+        let raw = read_file(&name)?;
+        let info = TableInfo::new(&name, &raw);
+        let mut data = tables::decode(&info.signature, &raw);
+        if let TableData::Fpdt(fpdt) = &mut data {
+            // the timestamps live in firmware memory, which /dev/mem may not expose
+            fpdt.boot = fpdt.boot_pointer().and_then(|a| boot_performance(a).ok());
+        }
+        Ok(Self { info, data })
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.info.header {
+            Some(header) => write!(f, "{}", header)?,
+            None => writeln!(f, "{:<24}{}", "Signature:", self.info.signature)?,
+        }
+        match self.info.checksum_valid {
+            Some(true) => writeln!(f, "{:<24}valid", "Checksum Status:")?,
+            Some(false) => writeln!(f, "{:<24}INVALID", "Checksum Status:")?,
+            None => {}
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.data)
+    }
+}
+
+/// List of tables, opens the decoded table or its bytes in the hex matrix
+pub struct AcpiBrowser {
+    tables: Vec<TableInfo>,
+    state: ListState,
+}
+
+impl AcpiBrowser {
+    pub fn new() -> Result<Self> {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Ok(Self {
+            tables: list()?,
+            state,
+        })
+    }
+
+    fn selected(&self) -> Option<&TableInfo> {
+        self.tables.get(self.state.selected()?)
+    }
+}
+
+fn describe(table: &TableInfo) -> String {
+    match Table::load(&table.name) {
+        Ok(table) => table.to_string(),
+        Err(err) => format!("{}\n", err),
+    }
+}
+
+/// Opens the bytes of `table` in the hex matrix
+fn hex_view<B: Backend>(terminal: &mut Terminal<B>, table: &TableInfo) -> io::Result<String> {
+    match read_file(&table.name) {
+        Ok(data) => {
+            let mut m = Matrix::new(
+                &format!("ACPI {}", table.name),
+                Buffer::new(data, PAGE_SIZE),
+            );
+            matrix::start(terminal, &mut m)?;
+            Ok(String::new())
+        }
+        Err(err) => Ok(err.to_string()),
+    }
+}
+
+/// Shows the decoded `table` until the user goes back to the list
+fn table_screen<B: Backend>(terminal: &mut Terminal<B>, table: &TableInfo) -> io::Result<()> {
+    let mut view = TextView::new(&table.name, &describe(table));
+    let mut message = String::new();
+    loop {
+        let help = format!("j/k: scroll  x: hex view  q/Esc: back  {}", message);
+        textview::draw(terminal, &view, &help)?;
+
+        if let Event::Key(key) = event::read()? {
+            message.clear();
+            if view.scroll_key(key.code) {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('x') => message = hex_view(terminal, table)?,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw<B: Backend>(
+    terminal: &mut Terminal<B>,
+    b: &mut AcpiBrowser,
+    message: &str,
+) -> io::Result<()> {
+    let items: Vec<ListItem> = b
+        .tables
+        .iter()
+        .map(|t| {
+            let (oem_id, oem_table_id) = match &t.header {
+                Some(h) => (h.oem_id.as_str(), h.oem_table_id.as_str()),
+                None => ("", ""),
+            };
+            let checksum = match t.checksum_valid {
+                Some(false) => "checksum INVALID",
+                _ => "",
+            };
+            ListItem::new(format!(
+                "{:<16}{:>8}  {:<8}{:<10}{}",
+                t.name, t.length, oem_id, oem_table_id, checksum
+            ))
+        })
+        .collect();
+    let title = format!(" ACPI tables ({}) ", b.tables.len());
+    let help = match message {
+        "" => "j/k: move  Enter: decode  x: hex view  q: quit",
+        message => message,
+    };
+
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, chunks[0], &mut b.state);
+        f.render_widget(Paragraph::new(help), chunks[1]);
+    })?;
+    Ok(())
+}
+
+/// Runs the table browser until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, b: &mut AcpiBrowser) -> io::Result<()> {
+    let mut message = String::new();
+    loop {
+        draw(terminal, b, &message)?;
+
+        if let Event::Key(key) = event::read()? {
+            message.clear();
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('j') | KeyCode::Down => {
+                    let i = b.state.selected().unwrap_or(0);
+                    b.state
+                        .select(Some((i + 1).min(b.tables.len().saturating_sub(1))));
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    let i = b.state.selected().unwrap_or(0);
+                    b.state.select(Some(i.saturating_sub(1)));
+                }
+                KeyCode::Enter => {
+                    if let Some(table) = b.selected().cloned() {
+                        table_screen(terminal, &table)?;
+                    }
+                }
+                KeyCode::Char('x') => {
+                    if let Some(table) = b.selected().cloned() {
+                        message = hex_view(terminal, &table)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,1483 @@
+//! Decoders for the structured ACPI tables, every decoder gets the whole
+//! table including the common header
+
+use serde::Serialize;
+use std::fmt;
+
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset + N)?.try_into().ok()
+}
+
+fn u8_at(data: &[u8], offset: usize) -> Option<u8> {
+    data.get(offset).copied()
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    bytes(data, offset).map(u32::from_le_bytes)
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    bytes(data, offset).map(u64::from_le_bytes)
+}
+
+/// Sub-structures of a table as `(offset, type, length)`, each starting with
+/// a type and a length field of `type_size` and `len_size` bytes
+fn structures(
+    data: &[u8],
+    start: usize,
+    type_size: usize,
+    len_size: usize,
+) -> Vec<(usize, u16, usize)> {
+    let mut found = vec![];
+    let mut offset = start;
+    while offset + type_size + len_size <= data.len() {
+        let kind = match type_size {
+            1 => data[offset] as u16,
+            _ => u16::from_le_bytes([data[offset], data[offset + 1]]),
+        };
+        let length = match len_size {
+            1 => data[offset + type_size] as usize,
+            _ => u16::from_le_bytes([data[offset + type_size], data[offset + type_size + 1]])
+                as usize,
+        };
+        // a zero length would loop forever, a long one is truncated
+        if length < type_size + len_size || offset + length > data.len() {
+            break;
+        }
+        found.push((offset, kind, length));
+        offset += length;
+    }
+    found
+}
+
+fn flag(name: &str, set: bool) -> String {
+    format!("{}{}", name, if set { "+" } else { "-" })
+}
+
+/// Names of the bits set in `value`
+fn bit_names(value: u32, names: &[(u32, &str)]) -> String {
+    let set: Vec<&str> = names
+        .iter()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    match set.is_empty() {
+        true => String::from("none"),
+        false => set.join(" "),
+    }
+}
+
+/// Generic Address Structure
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Gas {
+    pub space_id: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl Gas {
+    fn parse(data: &[u8], offset: usize) -> Option<Self> {
+        Some(Self {
+            space_id: u8_at(data, offset)?,
+            bit_width: u8_at(data, offset + 1)?,
+            bit_offset: u8_at(data, offset + 2)?,
+            access_size: u8_at(data, offset + 3)?,
+            address: u64_at(data, offset + 4)?,
+        })
+    }
+
+    pub fn space_name(&self) -> &'static str {
+        match self.space_id {
+            0x00 => "SystemMemory",
+            0x01 => "SystemIO",
+            0x02 => "PCI Config",
+            0x03 => "EmbeddedControl",
+            0x04 => "SMBus",
+            0x05 => "SystemCMOS",
+            0x06 => "PciBarTarget",
+            0x07 => "IPMI",
+            0x08 => "GeneralPurposeIO",
+            0x09 => "GenericSerialBus",
+            0x0a => "PCC",
+            0x7f => "FunctionalFixedHW",
+            _ => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for Gas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} 0x{:x} ({}-bit",
+            self.space_name(),
+            self.address,
+            self.bit_width
+        )?;
+        if self.bit_offset != 0 {
+            write!(f, " at bit {}", self.bit_offset)?;
+        }
+        write!(f, ")")
+    }
+}
+
+const FADT_MIN_SIZE: usize = 116;
+
+const FADT_FLAGS: &[(u32, &str)] = &[
+    (0, "WBINVD"),
+    (1, "WBINVD_FLUSH"),
+    (2, "PROC_C1"),
+    (3, "P_LVL2_UP"),
+    (4, "PWR_BUTTON"),
+    (5, "SLP_BUTTON"),
+    (6, "FIX_RTC"),
+    (7, "RTC_S4"),
+    (8, "TMR_VAL_EXT"),
+    (9, "DCK_CAP"),
+    (10, "RESET_REG_SUP"),
+    (11, "SEALED_CASE"),
+    (12, "HEADLESS"),
+    (13, "CPU_SW_SLP"),
+    (14, "PCI_EXP_WAK"),
+    (15, "USE_PLATFORM_CLOCK"),
+    (16, "S4_RTC_STS_VALID"),
+    (17, "REMOTE_POWER_ON_CAPABLE"),
+    (18, "FORCE_APIC_CLUSTER_MODEL"),
+    (19, "FORCE_APIC_PHYSICAL_DESTINATION_MODE"),
+    (20, "HW_REDUCED_ACPI"),
+    (21, "LOW_POWER_S0_IDLE_CAPABLE"),
+];
+
+const IAPC_BOOT_FLAGS: &[(u32, &str)] = &[
+    (0, "LEGACY_DEVICES"),
+    (1, "8042"),
+    (2, "VGA_NOT_PRESENT"),
+    (3, "MSI_NOT_SUPPORTED"),
+    (4, "PCIE_ASPM_CONTROLS"),
+    (5, "CMOS_RTC_NOT_PRESENT"),
+];
+
+const ARM_BOOT_FLAGS: &[(u32, &str)] = &[(0, "PSCI_COMPLIANT"), (1, "PSCI_USE_HVC")];
+
+/// Fixed ACPI Description Table, signature `FACP`
+#[derive(Debug, Clone, Serialize)]
+pub struct Fadt {
+    pub firmware_ctrl: u64,
+    pub dsdt: u64,
+    pub pm_profile: u8,
+    pub sci_interrupt: u16,
+    pub smi_command: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    pub pm1a_event: Option<Gas>,
+    pub pm1b_event: Option<Gas>,
+    pub pm1a_control: Option<Gas>,
+    pub pm1b_control: Option<Gas>,
+    pub pm2_control: Option<Gas>,
+    pub pm_timer: Option<Gas>,
+    pub gpe0: Option<Gas>,
+    pub gpe1: Option<Gas>,
+    pub gpe1_base: u8,
+    pub c2_latency: u16,
+    pub c3_latency: u16,
+    pub century: u8,
+    pub iapc_boot_arch: u16,
+    pub flags: u32,
+    pub reset_register: Option<Gas>,
+    pub reset_value: u8,
+    pub arm_boot_arch: u16,
+    pub minor_version: u8,
+    pub sleep_control: Option<Gas>,
+    pub sleep_status: Option<Gas>,
+    pub hypervisor_vendor: Option<u64>,
+}
+
+/// A register block given by its 32-bit I/O port and length and, since ACPI
+/// 2.0, by an extended address that takes precedence if it is set
+fn fadt_block(data: &[u8], port: usize, len: usize, extended: usize) -> Option<Gas> {
+    if let Some(gas) = Gas::parse(data, extended).filter(|gas| gas.address != 0) {
+        return Some(gas);
+    }
+    let address = u32_at(data, port)?;
+    (address != 0).then(|| Gas {
+        space_id: 1,
+        bit_width: data[len].saturating_mul(8),
+        bit_offset: 0,
+        access_size: 0,
+        address: address as u64,
+    })
+}
+
+impl Fadt {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < FADT_MIN_SIZE {
+            return None;
+        }
+        let wide = |offset: usize, legacy: usize| match u64_at(data, offset) {
+            Some(address) if address != 0 => address,
+            _ => u32_at(data, legacy).unwrap_or(0) as u64,
+        };
+        let gas = |offset: usize| Gas::parse(data, offset).filter(|gas| gas.address != 0);
+        Some(Self {
+            firmware_ctrl: wide(132, 36),
+            dsdt: wide(140, 40),
+            pm_profile: data[45],
+            sci_interrupt: u16_at(data, 46)?,
+            smi_command: u32_at(data, 48)?,
+            acpi_enable: data[52],
+            acpi_disable: data[53],
+            pm1a_event: fadt_block(data, 56, 88, 148),
+            pm1b_event: fadt_block(data, 60, 88, 160),
+            pm1a_control: fadt_block(data, 64, 89, 172),
+            pm1b_control: fadt_block(data, 68, 89, 184),
+            pm2_control: fadt_block(data, 72, 90, 196),
+            pm_timer: fadt_block(data, 76, 91, 208),
+            gpe0: fadt_block(data, 80, 92, 220),
+            gpe1: fadt_block(data, 84, 93, 232),
+            gpe1_base: data[94],
+            c2_latency: u16_at(data, 96)?,
+            c3_latency: u16_at(data, 98)?,
+            century: data[108],
+            iapc_boot_arch: u16_at(data, 109)?,
+            flags: u32_at(data, 112)?,
+            reset_register: gas(116),
+            reset_value: u8_at(data, 128).unwrap_or(0),
+            arm_boot_arch: u16_at(data, 129).unwrap_or(0),
+            minor_version: u8_at(data, 131).unwrap_or(0),
+            sleep_control: gas(244),
+            sleep_status: gas(256),
+            hypervisor_vendor: u64_at(data, 268).filter(|id| *id != 0),
+        })
+    }
+
+    pub fn pm_profile_name(&self) -> &'static str {
+        match self.pm_profile {
+            0 => "Unspecified",
+            1 => "Desktop",
+            2 => "Mobile",
+            3 => "Workstation",
+            4 => "Enterprise Server",
+            5 => "SOHO Server",
+            6 => "Appliance PC",
+            7 => "Performance Server",
+            8 => "Tablet",
+            _ => "Reserved",
+        }
+    }
+}
+
+impl fmt::Display for Fadt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}0x{:x}", "Firmware Control:", self.firmware_ctrl)?;
+        writeln!(f, "{:<24}0x{:x}", "DSDT:", self.dsdt)?;
+        writeln!(
+            f,
+            "{:<24}{} ({})",
+            "PM Profile:",
+            self.pm_profile_name(),
+            self.pm_profile
+        )?;
+        writeln!(f, "{:<24}{}", "SCI Interrupt:", self.sci_interrupt)?;
+        writeln!(
+            f,
+            "{:<24}0x{:x} enable 0x{:02x} disable 0x{:02x}",
+            "SMI Command:", self.smi_command, self.acpi_enable, self.acpi_disable
+        )?;
+        let blocks = [
+            ("PM1a Event Block:", &self.pm1a_event),
+            ("PM1b Event Block:", &self.pm1b_event),
+            ("PM1a Control Block:", &self.pm1a_control),
+            ("PM1b Control Block:", &self.pm1b_control),
+            ("PM2 Control Block:", &self.pm2_control),
+            ("PM Timer Block:", &self.pm_timer),
+            ("GPE0 Block:", &self.gpe0),
+            ("GPE1 Block:", &self.gpe1),
+            ("Reset Register:", &self.reset_register),
+            ("Sleep Control:", &self.sleep_control),
+            ("Sleep Status:", &self.sleep_status),
+        ];
+        for (name, block) in blocks {
+            if let Some(gas) = block {
+                writeln!(f, "{:<24}{}", name, gas)?;
+            }
+        }
+        if self.reset_register.is_some() {
+            writeln!(f, "{:<24}0x{:02x}", "Reset Value:", self.reset_value)?;
+        }
+        if self.gpe1.is_some() {
+            writeln!(f, "{:<24}{}", "GPE1 Base:", self.gpe1_base)?;
+        }
+        writeln!(
+            f,
+            "{:<24}C2 {} us, C3 {} us",
+            "Latency:", self.c2_latency, self.c3_latency
+        )?;
+        writeln!(f, "{:<24}{}", "RTC Century Index:", self.century)?;
+        writeln!(
+            f,
+            "{:<24}{}",
+            "IA-PC Boot Flags:",
+            bit_names(self.iapc_boot_arch as u32, IAPC_BOOT_FLAGS)
+        )?;
+        if self.arm_boot_arch != 0 {
+            writeln!(
+                f,
+                "{:<24}{}",
+                "ARM Boot Flags:",
+                bit_names(self.arm_boot_arch as u32, ARM_BOOT_FLAGS)
+            )?;
+        }
+        writeln!(f, "{:<24}{}", "Flags:", bit_names(self.flags, FADT_FLAGS))?;
+        writeln!(f, "{:<24}{}", "Minor Version:", self.minor_version)?;
+        if let Some(id) = self.hypervisor_vendor {
+            writeln!(f, "{:<24}0x{:016x}", "Hypervisor Vendor:", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Interrupt controller structure of the MADT
+#[derive(Debug, Clone, Serialize)]
+pub enum MadtEntry {
+    LocalApic {
+        processor_uid: u8,
+        apic_id: u8,
+        flags: u32,
+    },
+    IoApic {
+        id: u8,
+        address: u32,
+        gsi_base: u32,
+    },
+    InterruptOverride {
+        bus: u8,
+        source: u8,
+        gsi: u32,
+        flags: u16,
+    },
+    NmiSource {
+        flags: u16,
+        gsi: u32,
+    },
+    LocalApicNmi {
+        processor_uid: u8,
+        flags: u16,
+        lint: u8,
+    },
+    LocalApicOverride {
+        address: u64,
+    },
+    LocalX2Apic {
+        x2apic_id: u32,
+        flags: u32,
+        processor_uid: u32,
+    },
+    LocalX2ApicNmi {
+        processor_uid: u32,
+        flags: u16,
+        lint: u8,
+    },
+    /// a structure without decoder
+    Raw {
+        kind: u8,
+        length: u8,
+    },
+}
+
+impl MadtEntry {
+    fn parse(data: &[u8], kind: u8, length: u8) -> Option<Self> {
+        Some(match kind {
+            0 => Self::LocalApic {
+                processor_uid: u8_at(data, 2)?,
+                apic_id: u8_at(data, 3)?,
+                flags: u32_at(data, 4)?,
+            },
+            1 => Self::IoApic {
+                id: u8_at(data, 2)?,
+                address: u32_at(data, 4)?,
+                gsi_base: u32_at(data, 8)?,
+            },
+            2 => Self::InterruptOverride {
+                bus: u8_at(data, 2)?,
+                source: u8_at(data, 3)?,
+                gsi: u32_at(data, 4)?,
+                flags: u16_at(data, 8)?,
+            },
+            3 => Self::NmiSource {
+                flags: u16_at(data, 2)?,
+                gsi: u32_at(data, 4)?,
+            },
+            4 => Self::LocalApicNmi {
+                processor_uid: u8_at(data, 2)?,
+                flags: u16_at(data, 3)?,
+                lint: u8_at(data, 5)?,
+            },
+            5 => Self::LocalApicOverride {
+                address: u64_at(data, 4)?,
+            },
+            9 => Self::LocalX2Apic {
+                x2apic_id: u32_at(data, 4)?,
+                flags: u32_at(data, 8)?,
+                processor_uid: u32_at(data, 12)?,
+            },
+            10 => Self::LocalX2ApicNmi {
+                flags: u16_at(data, 2)?,
+                processor_uid: u32_at(data, 4)?,
+                lint: u8_at(data, 8)?,
+            },
+            _ => Self::Raw { kind, length },
+        })
+    }
+}
+
+/// Polarity and trigger mode of MPS INTI flags
+fn inti_flags(flags: u16) -> String {
+    let polarity = match flags & 0x3 {
+        0 => "Conforming",
+        1 => "ActiveHigh",
+        3 => "ActiveLow",
+        _ => "Reserved",
+    };
+    let trigger = match (flags >> 2) & 0x3 {
+        0 => "Conforming",
+        1 => "Edge",
+        3 => "Level",
+        _ => "Reserved",
+    };
+    format!("Polarity {} Trigger {}", polarity, trigger)
+}
+
+/// Whether a local APIC flags value marks the processor usable
+fn lapic_flags(flags: u32) -> String {
+    format!(
+        "{} {}",
+        flag("Enabled", flags & 0x1 != 0),
+        flag("OnlineCapable", flags & 0x2 != 0)
+    )
+}
+
+impl fmt::Display for MadtEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LocalApic {
+                processor_uid,
+                apic_id,
+                flags,
+            } => write!(
+                f,
+                "Local APIC: UID {} APIC ID {} {}",
+                processor_uid,
+                apic_id,
+                lapic_flags(*flags)
+            ),
+            Self::IoApic {
+                id,
+                address,
+                gsi_base,
+            } => write!(
+                f,
+                "I/O APIC: ID {} Address 0x{:x} GSI Base {}",
+                id, address, gsi_base
+            ),
+            Self::InterruptOverride {
+                bus,
+                source,
+                gsi,
+                flags,
+            } => write!(
+                f,
+                "Interrupt Override: Bus {} IRQ {} -> GSI {} {}",
+                bus,
+                source,
+                gsi,
+                inti_flags(*flags)
+            ),
+            Self::NmiSource { flags, gsi } => {
+                write!(f, "NMI Source: GSI {} {}", gsi, inti_flags(*flags))
+            }
+            Self::LocalApicNmi {
+                processor_uid,
+                flags,
+                lint,
+            } => write!(
+                f,
+                "Local APIC NMI: UID {} LINT{} {}",
+                match processor_uid {
+                    0xff => String::from("all"),
+                    uid => uid.to_string(),
+                },
+                lint,
+                inti_flags(*flags)
+            ),
+            Self::LocalApicOverride { address } => {
+                write!(f, "Local APIC Address Override: 0x{:x}", address)
+            }
+            Self::LocalX2Apic {
+                x2apic_id,
+                flags,
+                processor_uid,
+            } => write!(
+                f,
+                "Local x2APIC: UID {} x2APIC ID {} {}",
+                processor_uid,
+                x2apic_id,
+                lapic_flags(*flags)
+            ),
+            Self::LocalX2ApicNmi {
+                processor_uid,
+                flags,
+                lint,
+            } => write!(
+                f,
+                "Local x2APIC NMI: UID {} LINT{} {}",
+                match processor_uid {
+                    0xffff_ffff => String::from("all"),
+                    uid => uid.to_string(),
+                },
+                lint,
+                inti_flags(*flags)
+            ),
+            Self::Raw { kind, length } => {
+                write!(f, "Structure type 0x{:02x}, {} bytes", kind, length)
+            }
+        }
+    }
+}
+
+/// Multiple APIC Description Table, signature `APIC`
+#[derive(Debug, Clone, Serialize)]
+pub struct Madt {
+    pub local_apic_address: u32,
+    pub flags: u32,
+    pub entries: Vec<MadtEntry>,
+}
+
+impl Madt {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            local_apic_address: u32_at(data, 36)?,
+            flags: u32_at(data, 40)?,
+            entries: structures(data, 44, 1, 1)
+                .into_iter()
+                .filter_map(|(offset, kind, length)| {
+                    MadtEntry::parse(&data[offset..offset + length], kind as u8, length as u8)
+                })
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Madt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24}0x{:x}",
+            "Local APIC Address:", self.local_apic_address
+        )?;
+        writeln!(
+            f,
+            "{:<24}{}",
+            "Flags:",
+            flag("PCAT_COMPAT", self.flags & 0x1 != 0)
+        )?;
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// ECAM region of a PCI segment
+#[derive(Debug, Clone, Serialize)]
+pub struct McfgEntry {
+    pub base: u64,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+/// PCI Express memory mapped configuration space table, signature `MCFG`
+#[derive(Debug, Clone, Serialize)]
+pub struct Mcfg {
+    pub entries: Vec<McfgEntry>,
+}
+
+impl Mcfg {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let entries = data
+            .get(44..)?
+            .chunks_exact(16)
+            .map(|e| McfgEntry {
+                base: u64::from_le_bytes(e[0..8].try_into().unwrap()),
+                segment: u16::from_le_bytes([e[8], e[9]]),
+                start_bus: e[10],
+                end_bus: e[11],
+            })
+            .collect();
+        Some(Self { entries })
+    }
+}
+
+impl fmt::Display for Mcfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.entries {
+            writeln!(
+                f,
+                "Segment {:04x} Buses {:02x}-{:02x} ECAM 0x{:x}",
+                e.segment, e.start_bus, e.end_bus, e.base
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// High Precision Event Timer table, signature `HPET`
+#[derive(Debug, Clone, Serialize)]
+pub struct Hpet {
+    pub hardware_revision: u8,
+    pub comparators: u8,
+    pub counter_64bit: bool,
+    pub legacy_replacement: bool,
+    pub vendor_id: u16,
+    pub base: Gas,
+    pub number: u8,
+    pub min_tick: u16,
+    pub page_protection: u8,
+}
+
+impl Hpet {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let id = u32_at(data, 36)?;
+        Some(Self {
+            hardware_revision: id as u8,
+            comparators: ((id >> 8) & 0x1f) as u8 + 1,
+            counter_64bit: id & (1 << 13) != 0,
+            legacy_replacement: id & (1 << 15) != 0,
+            vendor_id: (id >> 16) as u16,
+            base: Gas::parse(data, 40)?,
+            number: u8_at(data, 52)?,
+            min_tick: u16_at(data, 53)?,
+            page_protection: u8_at(data, 55)?,
+        })
+    }
+}
+
+impl fmt::Display for Hpet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Base Address:", self.base)?;
+        writeln!(f, "{:<24}{}", "HPET Number:", self.number)?;
+        writeln!(
+            f,
+            "{:<24}vendor {:04x} revision {}",
+            "Hardware ID:", self.vendor_id, self.hardware_revision
+        )?;
+        writeln!(
+            f,
+            "{:<24}{} {} {}",
+            "Timer Block:",
+            format_args!("Comparators={}", self.comparators),
+            flag("64bit", self.counter_64bit),
+            flag("LegacyReplacement", self.legacy_replacement)
+        )?;
+        writeln!(f, "{:<24}{} ticks", "Minimum Tick:", self.min_tick)?;
+        writeln!(
+            f,
+            "{:<24}{}",
+            "Page Protection:",
+            match self.page_protection & 0xf {
+                0 => "none",
+                1 => "4 KiB",
+                2 => "64 KiB",
+                _ => "reserved",
+            }
+        )?;
+        Ok(())
+    }
+}
+
+/// Device the DMA remapping structures apply to
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceScope {
+    pub kind: u8,
+    pub enumeration_id: u8,
+    pub start_bus: u8,
+    /// device and function numbers from the start bus down the hierarchy
+    pub path: Vec<(u8, u8)>,
+}
+
+impl DeviceScope {
+    fn parse_all(data: &[u8], start: usize) -> Vec<Self> {
+        structures(data, start, 1, 1)
+            .into_iter()
+            .filter(|(_, _, length)| *length >= 6)
+            .map(|(offset, kind, length)| Self {
+                kind: kind as u8,
+                enumeration_id: data[offset + 4],
+                start_bus: data[offset + 5],
+                path: data[offset + 6..offset + length]
+                    .chunks_exact(2)
+                    .map(|p| (p[0], p[1]))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            1 => "PCI Endpoint",
+            2 => "PCI Sub-hierarchy",
+            3 => "IOAPIC",
+            4 => "HPET",
+            5 => "ACPI Namespace Device",
+            _ => "Reserved",
+        }
+    }
+}
+
+impl fmt::Display for DeviceScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bus {:02x} path", self.kind_name(), self.start_bus)?;
+        for (device, function) in &self.path {
+            write!(f, " {:02x}.{}", device, function)?;
+        }
+        if matches!(self.kind, 3..=5) {
+            write!(f, " id {}", self.enumeration_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remapping structure of the DMAR
+#[derive(Debug, Clone, Serialize)]
+pub enum DmarEntry {
+    /// DMA remapping hardware unit
+    Drhd {
+        flags: u8,
+        size: u8,
+        segment: u16,
+        register_base: u64,
+        scopes: Vec<DeviceScope>,
+    },
+    /// reserved memory region
+    Rmrr {
+        segment: u16,
+        base: u64,
+        limit: u64,
+        scopes: Vec<DeviceScope>,
+    },
+    /// root port ATS capability
+    Atsr {
+        flags: u8,
+        segment: u16,
+        scopes: Vec<DeviceScope>,
+    },
+    /// remapping hardware static affinity
+    Rhsa {
+        register_base: u64,
+        proximity_domain: u32,
+    },
+    /// ACPI namespace device declaration
+    Andd { device_number: u8, name: String },
+    /// SoC integrated address translation cache
+    Satc {
+        flags: u8,
+        segment: u16,
+        scopes: Vec<DeviceScope>,
+    },
+    /// a structure without decoder
+    Raw { kind: u16, length: u16 },
+}
+
+impl DmarEntry {
+    fn parse(data: &[u8], kind: u16, length: u16) -> Option<Self> {
+        Some(match kind {
+            0 => Self::Drhd {
+                flags: u8_at(data, 4)?,
+                size: u8_at(data, 5)?,
+                segment: u16_at(data, 6)?,
+                register_base: u64_at(data, 8)?,
+                scopes: DeviceScope::parse_all(data, 16),
+            },
+            1 => Self::Rmrr {
+                segment: u16_at(data, 6)?,
+                base: u64_at(data, 8)?,
+                limit: u64_at(data, 16)?,
+                scopes: DeviceScope::parse_all(data, 24),
+            },
+            2 => Self::Atsr {
+                flags: u8_at(data, 4)?,
+                segment: u16_at(data, 6)?,
+                scopes: DeviceScope::parse_all(data, 8),
+            },
+            3 => Self::Rhsa {
+                register_base: u64_at(data, 8)?,
+                proximity_domain: u32_at(data, 16)?,
+            },
+            4 => Self::Andd {
+                device_number: u8_at(data, 7)?,
+                name: String::from_utf8_lossy(data.get(8..)?)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            },
+            5 => Self::Satc {
+                flags: u8_at(data, 4)?,
+                segment: u16_at(data, 6)?,
+                scopes: DeviceScope::parse_all(data, 8),
+            },
+            _ => Self::Raw { kind, length },
+        })
+    }
+}
+
+fn write_scopes(f: &mut fmt::Formatter<'_>, scopes: &[DeviceScope]) -> fmt::Result {
+    for scope in scopes {
+        writeln!(f, "\tScope: {}", scope)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for DmarEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Drhd {
+                flags,
+                size,
+                segment,
+                register_base,
+                scopes,
+            } => {
+                writeln!(
+                    f,
+                    "DRHD: Segment {:04x} Registers 0x{:x} ({} KiB) {}",
+                    segment,
+                    register_base,
+                    4u64 << size,
+                    flag("IncludePciAll", flags & 0x1 != 0)
+                )?;
+                write_scopes(f, scopes)
+            }
+            Self::Rmrr {
+                segment,
+                base,
+                limit,
+                scopes,
+            } => {
+                writeln!(
+                    f,
+                    "RMRR: Segment {:04x} Range 0x{:x}-0x{:x}",
+                    segment, base, limit
+                )?;
+                write_scopes(f, scopes)
+            }
+            Self::Atsr {
+                flags,
+                segment,
+                scopes,
+            } => {
+                writeln!(
+                    f,
+                    "ATSR: Segment {:04x} {}",
+                    segment,
+                    flag("AllPorts", flags & 0x1 != 0)
+                )?;
+                write_scopes(f, scopes)
+            }
+            Self::Rhsa {
+                register_base,
+                proximity_domain,
+            } => writeln!(
+                f,
+                "RHSA: Registers 0x{:x} Proximity Domain {}",
+                register_base, proximity_domain
+            ),
+            Self::Andd {
+                device_number,
+                name,
+            } => writeln!(f, "ANDD: Device {} {}", device_number, name),
+            Self::Satc {
+                flags,
+                segment,
+                scopes,
+            } => {
+                writeln!(
+                    f,
+                    "SATC: Segment {:04x} {}",
+                    segment,
+                    flag("ATCRequired", flags & 0x1 != 0)
+                )?;
+                write_scopes(f, scopes)
+            }
+            Self::Raw { kind, length } => {
+                writeln!(f, "Structure type 0x{:04x}, {} bytes", kind, length)
+            }
+        }
+    }
+}
+
+/// DMA Remapping table of Intel VT-d, signature `DMAR`
+#[derive(Debug, Clone, Serialize)]
+pub struct Dmar {
+    /// DMA address width in bits
+    pub host_address_width: u8,
+    pub flags: u8,
+    pub entries: Vec<DmarEntry>,
+}
+
+impl Dmar {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            host_address_width: u8_at(data, 36)?.saturating_add(1),
+            flags: u8_at(data, 37)?,
+            entries: structures(data, 48, 2, 2)
+                .into_iter()
+                .filter_map(|(offset, kind, length)| {
+                    DmarEntry::parse(&data[offset..offset + length], kind, length as u16)
+                })
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Dmar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24}{} bits",
+            "Host Address Width:", self.host_address_width
+        )?;
+        writeln!(
+            f,
+            "{:<24}{} {} {}",
+            "Flags:",
+            flag("INTR_REMAP", self.flags & 0x1 != 0),
+            flag("X2APIC_OPT_OUT", self.flags & 0x2 != 0),
+            flag("DMA_CTRL_PLATFORM_OPT_IN", self.flags & 0x4 != 0)
+        )?;
+        for entry in &self.entries {
+            write!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Static resource affinity structure of the SRAT
+#[derive(Debug, Clone, Serialize)]
+pub enum SratEntry {
+    ProcessorAffinity {
+        proximity_domain: u32,
+        apic_id: u8,
+        flags: u32,
+        clock_domain: u32,
+    },
+    MemoryAffinity {
+        proximity_domain: u32,
+        base: u64,
+        length: u64,
+        flags: u32,
+    },
+    X2ApicAffinity {
+        proximity_domain: u32,
+        x2apic_id: u32,
+        flags: u32,
+        clock_domain: u32,
+    },
+    GiccAffinity {
+        proximity_domain: u32,
+        processor_uid: u32,
+        flags: u32,
+        clock_domain: u32,
+    },
+    /// a structure without decoder
+    Raw { kind: u8, length: u8 },
+}
+
+impl SratEntry {
+    fn parse(data: &[u8], kind: u8, length: u8) -> Option<Self> {
+        Some(match kind {
+            0 => {
+                let high = bytes::<3>(data, 9)?;
+                Self::ProcessorAffinity {
+                    proximity_domain: u32::from_le_bytes([
+                        u8_at(data, 2)?,
+                        high[0],
+                        high[1],
+                        high[2],
+                    ]),
+                    apic_id: u8_at(data, 3)?,
+                    flags: u32_at(data, 4)?,
+                    clock_domain: u32_at(data, 12)?,
+                }
+            }
+            1 => Self::MemoryAffinity {
+                proximity_domain: u32_at(data, 2)?,
+                base: u64_at(data, 8)?,
+                length: u64_at(data, 16)?,
+                flags: u32_at(data, 28)?,
+            },
+            2 => Self::X2ApicAffinity {
+                proximity_domain: u32_at(data, 4)?,
+                x2apic_id: u32_at(data, 8)?,
+                flags: u32_at(data, 12)?,
+                clock_domain: u32_at(data, 16)?,
+            },
+            3 => Self::GiccAffinity {
+                proximity_domain: u32_at(data, 2)?,
+                processor_uid: u32_at(data, 6)?,
+                flags: u32_at(data, 10)?,
+                clock_domain: u32_at(data, 14)?,
+            },
+            _ => Self::Raw { kind, length },
+        })
+    }
+}
+
+impl fmt::Display for SratEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProcessorAffinity {
+                proximity_domain,
+                apic_id,
+                flags,
+                clock_domain,
+            } => write!(
+                f,
+                "Processor: APIC ID {} Domain {} Clock Domain {} {}",
+                apic_id,
+                proximity_domain,
+                clock_domain,
+                flag("Enabled", flags & 0x1 != 0)
+            ),
+            Self::MemoryAffinity {
+                proximity_domain,
+                base,
+                length,
+                flags,
+            } => write!(
+                f,
+                "Memory: 0x{:x}-0x{:x} Domain {} {} {} {}",
+                base,
+                (base + length).saturating_sub(1),
+                proximity_domain,
+                flag("Enabled", flags & 0x1 != 0),
+                flag("HotPluggable", flags & 0x2 != 0),
+                flag("NonVolatile", flags & 0x4 != 0)
+            ),
+            Self::X2ApicAffinity {
+                proximity_domain,
+                x2apic_id,
+                flags,
+                clock_domain,
+            } => write!(
+                f,
+                "Processor: x2APIC ID {} Domain {} Clock Domain {} {}",
+                x2apic_id,
+                proximity_domain,
+                clock_domain,
+                flag("Enabled", flags & 0x1 != 0)
+            ),
+            Self::GiccAffinity {
+                proximity_domain,
+                processor_uid,
+                flags,
+                clock_domain,
+            } => write!(
+                f,
+                "GICC: UID {} Domain {} Clock Domain {} {}",
+                processor_uid,
+                proximity_domain,
+                clock_domain,
+                flag("Enabled", flags & 0x1 != 0)
+            ),
+            Self::Raw { kind, length } => {
+                write!(f, "Structure type 0x{:02x}, {} bytes", kind, length)
+            }
+        }
+    }
+}
+
+/// System Resource Affinity Table, signature `SRAT`
+#[derive(Debug, Clone, Serialize)]
+pub struct Srat {
+    pub entries: Vec<SratEntry>,
+}
+
+impl Srat {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 48 {
+            return None;
+        }
+        Some(Self {
+            entries: structures(data, 48, 1, 1)
+                .into_iter()
+                .filter_map(|(offset, kind, length)| {
+                    SratEntry::parse(&data[offset..offset + length], kind as u8, length as u8)
+                })
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Srat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// System Locality Information Table, signature `SLIT`
+#[derive(Debug, Clone, Serialize)]
+pub struct Slit {
+    /// relative distances, `distances[i][j]` from locality `i` to `j`
+    pub distances: Vec<Vec<u8>>,
+}
+
+impl Slit {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let count = usize::try_from(u64_at(data, 36)?).ok()?;
+        let matrix = data.get(44..44 + count.checked_mul(count)?)?;
+        Some(Self {
+            distances: matrix
+                .chunks(count.max(1))
+                .map(|row| row.to_vec())
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Slit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Localities:", self.distances.len())?;
+        write!(f, "    ")?;
+        for j in 0..self.distances.len() {
+            write!(f, "{:>4}", j)?;
+        }
+        writeln!(f)?;
+        for (i, row) in self.distances.iter().enumerate() {
+            write!(f, "{:>4}", i)?;
+            for distance in row {
+                write!(f, "{:>4}", distance)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Boot Graphics Resource Table, signature `BGRT`
+#[derive(Debug, Clone, Serialize)]
+pub struct Bgrt {
+    pub version: u16,
+    pub status: u8,
+    pub image_type: u8,
+    pub image_address: u64,
+    pub offset_x: u32,
+    pub offset_y: u32,
+}
+
+impl Bgrt {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            version: u16_at(data, 36)?,
+            status: u8_at(data, 38)?,
+            image_type: u8_at(data, 39)?,
+            image_address: u64_at(data, 40)?,
+            offset_x: u32_at(data, 48)?,
+            offset_y: u32_at(data, 52)?,
+        })
+    }
+}
+
+impl fmt::Display for Bgrt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Version:", self.version)?;
+        writeln!(
+            f,
+            "{:<24}{} Orientation {} degrees",
+            "Status:",
+            flag("Displayed", self.status & 0x1 != 0),
+            ((self.status >> 1) & 0x3) as u32 * 90
+        )?;
+        writeln!(
+            f,
+            "{:<24}{}",
+            "Image Type:",
+            match self.image_type {
+                0 => "Bitmap",
+                _ => "Reserved",
+            }
+        )?;
+        writeln!(f, "{:<24}0x{:x}", "Image Address:", self.image_address)?;
+        writeln!(
+            f,
+            "{:<24}{}, {}",
+            "Image Offset:", self.offset_x, self.offset_y
+        )?;
+        Ok(())
+    }
+}
+
+/// Performance record of the FPDT
+#[derive(Debug, Clone, Serialize)]
+pub enum FpdtRecord {
+    /// physical address of the firmware basic boot performance table
+    BootPointer { address: u64 },
+    /// physical address of the S3 performance table
+    S3Pointer { address: u64 },
+    /// a record without decoder
+    Raw { kind: u16, length: u8 },
+}
+
+/// Timestamps of the firmware basic boot performance data record in ns
+#[derive(Debug, Clone, Serialize)]
+pub struct BootPerformance {
+    pub reset_end: u64,
+    pub loader_load_image_start: u64,
+    pub loader_start_image_start: u64,
+    pub exit_boot_services_entry: u64,
+    pub exit_boot_services_exit: u64,
+}
+
+/// Size of the header of the tables the FPDT points to
+pub const FBPT_HEADER_SIZE: usize = 8;
+
+impl BootPerformance {
+    /// Parses a firmware basic boot performance table including its header
+    pub fn parse(fbpt: &[u8]) -> Option<Self> {
+        if fbpt.get(..4)? != b"FBPT" {
+            return None;
+        }
+        let (offset, _, _) = structures(fbpt, FBPT_HEADER_SIZE, 2, 1)
+            .into_iter()
+            .find(|(_, kind, _)| *kind == 2)?;
+        let record = &fbpt[offset..];
+        Some(Self {
+            reset_end: u64_at(record, 8)?,
+            loader_load_image_start: u64_at(record, 16)?,
+            loader_start_image_start: u64_at(record, 24)?,
+            exit_boot_services_entry: u64_at(record, 32)?,
+            exit_boot_services_exit: u64_at(record, 40)?,
+        })
+    }
+}
+
+/// Firmware Performance Data Table, signature `FPDT`
+#[derive(Debug, Clone, Serialize)]
+pub struct Fpdt {
+    pub records: Vec<FpdtRecord>,
+    /// boot timestamps, only present if the table behind the pointer could be read
+    pub boot: Option<BootPerformance>,
+}
+
+impl Fpdt {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let records = structures(data, 36, 2, 1)
+            .into_iter()
+            .map(|(offset, kind, length)| match kind {
+                0 | 1 if length >= 16 => {
+                    let address = u64_at(data, offset + 8).unwrap_or(0);
+                    match kind {
+                        0 => FpdtRecord::BootPointer { address },
+                        _ => FpdtRecord::S3Pointer { address },
+                    }
+                }
+                _ => FpdtRecord::Raw {
+                    kind,
+                    length: length as u8,
+                },
+            })
+            .collect();
+        Some(Self {
+            records,
+            boot: None,
+        })
+    }
+
+    /// Address of the firmware basic boot performance table
+    pub fn boot_pointer(&self) -> Option<u64> {
+        self.records.iter().find_map(|r| match r {
+            FpdtRecord::BootPointer { address } => Some(*address),
+            _ => None,
+        })
+    }
+}
+
+fn ms(ns: u64) -> String {
+    format!("{}.{:03} ms", ns / 1_000_000, ns / 1_000 % 1_000)
+}
+
+impl fmt::Display for Fpdt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in &self.records {
+            match record {
+                FpdtRecord::BootPointer { address } => {
+                    writeln!(f, "{:<24}0x{:x}", "Boot Performance:", address)?
+                }
+                FpdtRecord::S3Pointer { address } => {
+                    writeln!(f, "{:<24}0x{:x}", "S3 Performance:", address)?
+                }
+                FpdtRecord::Raw { kind, length } => {
+                    writeln!(f, "Record type 0x{:04x}, {} bytes", kind, length)?
+                }
+            }
+        }
+        if let Some(boot) = &self.boot {
+            writeln!(f, "\t{:<32}{}", "Reset End:", ms(boot.reset_end))?;
+            writeln!(
+                f,
+                "\t{:<32}{}",
+                "OS Loader LoadImage Start:",
+                ms(boot.loader_load_image_start)
+            )?;
+            writeln!(
+                f,
+                "\t{:<32}{}",
+                "OS Loader StartImage Start:",
+                ms(boot.loader_start_image_start)
+            )?;
+            writeln!(
+                f,
+                "\t{:<32}{}",
+                "ExitBootServices Entry:",
+                ms(boot.exit_boot_services_entry)
+            )?;
+            writeln!(
+                f,
+                "\t{:<32}{}",
+                "ExitBootServices Exit:",
+                ms(boot.exit_boot_services_exit)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Decoded body of a table
+#[derive(Debug, Clone, Serialize)]
+pub enum TableData {
+    Fadt(Box<Fadt>),
+    Madt(Madt),
+    Mcfg(Mcfg),
+    Hpet(Hpet),
+    Dmar(Dmar),
+    Srat(Srat),
+    Slit(Slit),
+    Bgrt(Bgrt),
+    Fpdt(Fpdt),
+    /// a table without decoder, such as AML code, or one that is too short
+    Raw,
+}
+
+/// Decodes the table `data` with `signature`
+pub fn decode(signature: &str, data: &[u8]) -> TableData {
+    let decoded = match signature {
+        "FACP" => Fadt::parse(data).map(|t| TableData::Fadt(Box::new(t))),
+        "APIC" => Madt::parse(data).map(TableData::Madt),
+        "MCFG" => Mcfg::parse(data).map(TableData::Mcfg),
+        "HPET" => Hpet::parse(data).map(TableData::Hpet),
+        "DMAR" => Dmar::parse(data).map(TableData::Dmar),
+        "SRAT" => Srat::parse(data).map(TableData::Srat),
+        "SLIT" => Slit::parse(data).map(TableData::Slit),
+        "BGRT" => Bgrt::parse(data).map(TableData::Bgrt),
+        "FPDT" => Fpdt::parse(data).map(TableData::Fpdt),
+        _ => None,
+    };
+    decoded.unwrap_or(TableData::Raw)
+}
+
+impl fmt::Display for TableData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableData::Fadt(t) => write!(f, "{}", t),
+            TableData::Madt(t) => write!(f, "{}", t),
+            TableData::Mcfg(t) => write!(f, "{}", t),
+            TableData::Hpet(t) => write!(f, "{}", t),
+            TableData::Dmar(t) => write!(f, "{}", t),
+            TableData::Srat(t) => write!(f, "{}", t),
+            TableData::Slit(t) => write!(f, "{}", t),
+            TableData::Bgrt(t) => write!(f, "{}", t),
+            TableData::Fpdt(t) => write!(f, "{}", t),
+            TableData::Raw => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A table with an empty header followed by `body`
+    fn table(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 36];
+        data[..4].copy_from_slice(signature);
+        data.extend_from_slice(body);
+        let len = data.len() as u32;
+        data[4..8].copy_from_slice(&len.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_madt() {
+        let mut body = vec![];
+        body.extend_from_slice(&0xfee0_0000u32.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        // local APIC 1, enabled
+        body.extend_from_slice(&[0, 8, 0, 1, 1, 0, 0, 0]);
+        // I/O APIC at 0xfec00000
+        body.extend_from_slice(&[1, 12, 2, 0, 0, 0, 0xc0, 0xfe, 0, 0, 0, 0]);
+        // IRQ 0 to GSI 2, edge triggered active high
+        body.extend_from_slice(&[2, 10, 0, 0, 2, 0, 0, 0, 5, 0]);
+        // zero length structure ends the walk
+        body.extend_from_slice(&[0x7f, 0]);
+
+        let madt = match decode("APIC", &table(b"APIC", &body)) {
+            TableData::Madt(madt) => madt,
+            data => panic!("unexpected {:?}", data),
+        };
+        assert_eq!(madt.local_apic_address, 0xfee0_0000);
+        assert_eq!(madt.entries.len(), 3);
+        assert!(matches!(
+            madt.entries[0],
+            MadtEntry::LocalApic {
+                apic_id: 1,
+                flags: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            madt.entries[1],
+            MadtEntry::IoApic {
+                id: 2,
+                address: 0xfec0_0000,
+                gsi_base: 0
+            }
+        ));
+        assert_eq!(
+            madt.entries[2].to_string(),
+            "Interrupt Override: Bus 0 IRQ 0 -> GSI 2 Polarity ActiveHigh Trigger Edge"
+        );
+    }
+
+    #[test]
+    fn test_decode_slit() {
+        let mut body = 2u64.to_le_bytes().to_vec();
+        body.extend_from_slice(&[10, 21, 21, 10]);
+        match decode("SLIT", &table(b"SLIT", &body)) {
+            TableData::Slit(slit) => assert_eq!(slit.distances, vec![vec![10, 21], vec![21, 10]]),
+            data => panic!("unexpected {:?}", data),
+        }
+        // the matrix extends past the table
+        body[0] = 3;
+        assert!(matches!(
+            decode("SLIT", &table(b"SLIT", &body)),
+            TableData::Raw
+        ));
+    }
+}
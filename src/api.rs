@@ -1,27 +1,86 @@
+use super::acpi::{self, Table};
+use super::cpuid::{self, CpuInfo};
 use super::devmem;
+use super::error::{self, Error};
+use super::guard;
+use super::ioport;
+use super::matrix::{data_size, DataType};
+use super::msr;
 use super::pci;
+use super::smbios;
 use actix_web::{get, put, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 
+/// Maps a backend error to the matching HTTP response
+fn error_response(err: Error) -> HttpResponse {
+    let mut response = match err {
+        Error::PermissionDenied(_) | Error::Refused(_) => HttpResponse::Forbidden(),
+        Error::NotFound(_) | Error::Unmapped { .. } => HttpResponse::NotFound(),
+        Error::Alignment { .. } | Error::Parse(_) => HttpResponse::BadRequest(),
+        Error::Unsupported(_) => HttpResponse::NotImplemented(),
+        Error::Io(_) => HttpResponse::InternalServerError(),
+    };
+    response.body(err.to_string())
+}
+
+/// Largest number of bytes a single read request returns
+const MAX_READ_LENGTH: u64 = 4 << 20;
+
+/// Fails for reads longer than [`MAX_READ_LENGTH`], the buffer is
+/// allocated up front
+fn check_length(length: u64) -> error::Result<usize> {
+    if length > MAX_READ_LENGTH {
+        return Err(Error::Parse(format!(
+            "length 0x{:x} exceeds the maximum of 0x{:x}",
+            length, MAX_READ_LENGTH
+        )));
+    }
+    Ok(length as usize)
+}
+
+/// Body of the bytes read at `offset`, a read that left bytes unreadable
+/// fails with the range between the first and the last of them instead of
+/// passing placeholders off as register contents
+fn read_response(offset: u64, bytes: Vec<Option<u8>>) -> HttpResponse {
+    match bytes.iter().position(|b| b.is_none()) {
+        Some(first) => {
+            let last = bytes.iter().rposition(|b| b.is_none()).unwrap_or(first);
+            error_response(Error::Unmapped {
+                offset: offset + first as u64,
+                length: (last - first + 1) as u64,
+            })
+        }
+        None => HttpResponse::Ok().body(bytes.into_iter().flatten().collect::<Vec<u8>>()),
+    }
+}
+
 #[derive(Deserialize)]
 struct ReadDevmemArgs {
     offset: u64,
     length: u64,
+    data_type: Option<String>,
 }
 
 #[get("/devmem")]
 async fn read_devmem(args: web::Query<ReadDevmemArgs>) -> HttpResponse {
     let args = args.into_inner();
-    let mut data: Vec<u8> = vec![];
-    let start = args.offset;
-    let stop = args.offset + args.length;
-
-    for i in start..stop {
-        match devmem::read_byte(i) {
-            Some(b) => data.push(b),
-            None => data.push(0),
-        }
-    }
+    let data_type = match args
+        .data_type
+        .as_deref()
+        .unwrap_or("byte")
+        .parse::<DataType>()
+    {
+        Ok(data_type) => data_type,
+        Err(err) => return error_response(err),
+    };
+    let length = match check_length(args.length) {
+        Ok(length) => length,
+        Err(err) => return error_response(err),
+    };
+    let data: Vec<u8> = match devmem::read(args.offset, length, &data_type) {
+        Ok(bytes) => bytes.into_iter().map(|b| b.unwrap_or(0)).collect(),
+        Err(err) => return error_response(err),
+    };
 
     HttpResponse::Ok().body(data)
 }
@@ -31,59 +90,233 @@ struct WriteDevmemMeta {
     offset: u64,
     data_type: String,
     data: String,
+    /// write to System RAM, the kernel image or unclaimed ranges as well
+    #[serde(default)]
+    force: bool,
 }
 
 #[put("/devmem")]
 async fn write_devmem(meta: web::Json<WriteDevmemMeta>) -> HttpResponse {
     let meta = meta.into_inner();
-    let bytes: Option<Vec<u8>> = match meta.data_type.as_str() {
-        "byte" => {
-            if let Ok(byte) = u8::from_str_radix(&meta.data, 16) {
-                Some(vec![byte])
-            } else {
-                None
-            }
-        }
-        "word" => {
-            if let Ok(data) = u16::from_str_radix(&meta.data, 16) {
-                Some(data.to_ne_bytes().to_vec())
-            } else {
-                None
-            }
-        }
-        "dword" => {
-            if let Ok(data) = u32::from_str_radix(&meta.data, 16) {
-                Some(data.to_ne_bytes().to_vec())
-            } else {
-                None
-            }
-        }
-        "qword" => {
-            if let Ok(data) = u64::from_str_radix(&meta.data, 16) {
-                Some(data.to_ne_bytes().to_vec())
-            } else {
-                None
-            }
-        }
-        "dqword" => {
-            if let Ok(data) = u128::from_str_radix(&meta.data, 16) {
-                Some(data.to_ne_bytes().to_vec())
-            } else {
-                None
-            }
-        }
-        _ => None,
+    let data_type = meta.data_type.parse::<DataType>();
+    let bytes: Option<Vec<u8>> = match data_type {
+        Ok(DataType::Byte) => u8::from_str_radix(&meta.data, 16)
+            .ok()
+            .map(|data| vec![data]),
+        Ok(DataType::Word) => u16::from_str_radix(&meta.data, 16)
+            .ok()
+            .map(|data| data.to_ne_bytes().to_vec()),
+        Ok(DataType::DoubleWord) => u32::from_str_radix(&meta.data, 16)
+            .ok()
+            .map(|data| data.to_ne_bytes().to_vec()),
+        Ok(DataType::QuadWord) => u64::from_str_radix(&meta.data, 16)
+            .ok()
+            .map(|data| data.to_ne_bytes().to_vec()),
+        Ok(DataType::DoubleQuadWord) => u128::from_str_radix(&meta.data, 16)
+            .ok()
+            .map(|data| data.to_ne_bytes().to_vec()),
+        Err(_) => None,
     };
 
     if bytes.is_none() {
-        return HttpResponse::NotAcceptable().body(format!(
+        return error_response(Error::Parse(format!(
             "invalid data, type: {}, length: {}",
             meta.data_type,
             meta.data.len()
-        ));
+        )));
+    }
+
+    let bytes = bytes.unwrap();
+    if let Err(err) = guard::check_phys_write(meta.offset, bytes.len() as u64, meta.force) {
+        return error_response(err);
+    }
+    if let Err(err) = devmem::write(meta.offset, &data_type.unwrap(), bytes) {
+        return error_response(err);
+    }
+
+    HttpResponse::Accepted().body("")
+}
+
+#[derive(Deserialize)]
+struct ReadIoArgs {
+    port: u64,
+    length: u64,
+    data_type: Option<String>,
+}
+
+#[get("/io")]
+async fn read_io(args: web::Query<ReadIoArgs>) -> HttpResponse {
+    let args = args.into_inner();
+    let data_type = match args
+        .data_type
+        .as_deref()
+        .unwrap_or("byte")
+        .parse::<DataType>()
+    {
+        Ok(data_type) => data_type,
+        Err(err) => return error_response(err),
+    };
+    let length = match check_length(args.length) {
+        Ok(length) => length,
+        Err(err) => return error_response(err),
+    };
+    match ioport::read(args.port, length, &data_type) {
+        Ok(bytes) => read_response(args.port, bytes),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct WriteIoMeta {
+    port: u64,
+    data_type: String,
+    data: String,
+}
+
+#[put("/io")]
+async fn write_io(meta: web::Json<WriteIoMeta>) -> HttpResponse {
+    let meta = meta.into_inner();
+    let data_type = match meta.data_type.parse::<DataType>() {
+        Ok(data_type) => data_type,
+        Err(err) => return error_response(err),
+    };
+    let size = data_size(&data_type) as usize;
+    let value = match u64::from_str_radix(&meta.data, 16) {
+        Ok(value) if size >= 8 || value >> (size * 8) == 0 => value,
+        _ => {
+            return error_response(Error::Parse(format!(
+                "invalid data, type: {}, length: {}",
+                meta.data_type,
+                meta.data.len()
+            )))
+        }
+    };
+
+    if let Err(err) = ioport::write_value(meta.port, &data_type, value) {
+        return error_response(err);
+    }
+
+    HttpResponse::Accepted().body("")
+}
+
+/// Header and checksum status of every ACPI table
+#[get("/acpi/tables")]
+async fn get_acpi_tables() -> HttpResponse {
+    match acpi::list() {
+        Ok(tables) => HttpResponse::Ok().json(tables),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct AcpiTableArgs {
+    name: String,
+}
+
+/// Header and decoded body of an ACPI table
+#[get("/acpi/table")]
+async fn get_acpi_table(args: web::Query<AcpiTableArgs>) -> HttpResponse {
+    match Table::load(&args.name) {
+        Ok(table) => HttpResponse::Ok().json(table),
+        Err(err) => error_response(err),
+    }
+}
+
+/// Raw bytes of an ACPI table
+#[get("/acpi/table/raw")]
+async fn get_acpi_table_raw(args: web::Query<AcpiTableArgs>) -> HttpResponse {
+    match acpi::read(&args.name) {
+        Ok(data) => HttpResponse::Ok().body(data),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct CpuidArgs {
+    cpu: Option<usize>,
+}
+
+/// Decoded and raw CPUID leaves of a CPU, cpu 0 by default
+#[get("/cpuid")]
+async fn get_cpuid(args: web::Query<CpuidArgs>) -> HttpResponse {
+    let cpu = args.cpu.unwrap_or(0);
+    match cpuid::dump(cpu) {
+        Ok(entries) => HttpResponse::Ok().json(serde_json::json!({
+            "cpu": cpu,
+            "info": CpuInfo::decode(&entries),
+            "leaves": entries,
+        })),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct SmbiosArgs {
+    #[serde(rename = "type")]
+    kind: Option<u8>,
+}
+
+/// SMBIOS entry point and decoded structures, only those of `type` if given
+#[get("/smbios")]
+async fn get_smbios(args: web::Query<SmbiosArgs>) -> HttpResponse {
+    match smbios::read() {
+        Ok(mut smbios) => {
+            if let Some(kind) = args.kind {
+                smbios.structures.retain(|s| s.kind == kind);
+            }
+            HttpResponse::Ok().json(smbios)
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct ReadMsrArgs {
+    reg: u32,
+    cpu: Option<usize>,
+}
+
+/// Values of an MSR on one or, without `cpu`, all online CPUs
+#[get("/msr")]
+async fn read_msr(args: web::Query<ReadMsrArgs>) -> HttpResponse {
+    let args = args.into_inner();
+    let values = match args.cpu {
+        Some(cpu) => msr::read(cpu, args.reg).map(|value| {
+            vec![msr::MsrValue {
+                cpu,
+                value: Some(value),
+                error: None,
+            }]
+        }),
+        None => msr::read_all(args.reg),
+    };
+    match values {
+        Ok(values) => HttpResponse::Ok().json(values),
+        Err(err) => error_response(err),
     }
+}
 
-    devmem::write(meta.offset, bytes.unwrap());
+#[derive(Deserialize)]
+struct WriteMsrMeta {
+    reg: u32,
+    cpu: Option<usize>,
+    data: String,
+}
+
+#[put("/msr")]
+async fn write_msr(meta: web::Json<WriteMsrMeta>) -> HttpResponse {
+    let meta = meta.into_inner();
+    let value = match u64::from_str_radix(&meta.data, 16) {
+        Ok(value) => value,
+        Err(_) => return error_response(Error::Parse(format!("invalid data: {}", meta.data))),
+    };
+    let res = match meta.cpu {
+        Some(cpu) => msr::write(cpu, meta.reg, value),
+        None => msr::write_all(meta.reg, value),
+    };
+    if let Err(err) = res {
+        return error_response(err);
+    }
 
     HttpResponse::Accepted().body("")
 }
@@ -103,25 +336,23 @@ async fn get_pci_devices() -> HttpResponse {
         .iter()
         .map(|d| {
             let (device, info) = d;
-            let desc = format!(
-                "{}: {} {} (rev {})",
-                info.class.1.as_ref().unwrap_or(&info.class.0),
-                info.vendor.1.as_ref().unwrap_or(&info.vendor.0),
-                info.device.1.as_ref().unwrap_or(&info.device.0),
-                info.revision,
-            );
             PciDevice {
                 domain: device.domain,
                 bus: device.bus,
                 device: device.device,
                 function: device.function,
-                description: desc,
+                description: info.description(),
             }
         })
         .collect::<Vec<PciDevice>>();
     HttpResponse::Ok().json(devices)
 }
 
+#[get("/pci/links")]
+async fn get_pci_links() -> HttpResponse {
+    HttpResponse::Ok().json(pci::link::links())
+}
+
 #[derive(Deserialize)]
 struct PciDevQueryArgs {
     domain: u8,
@@ -146,3 +377,141 @@ async fn get_pci_dev_config(args: web::Query<PciDevQueryArgs>) -> HttpResponse {
         None => HttpResponse::NotFound().body("device not found"),
     }
 }
+
+#[derive(Deserialize)]
+struct WritePciConfigMeta {
+    domain: u8,
+    bus: u8,
+    device: u8,
+    function: u8,
+    offset: u64,
+    data_type: String,
+    data: String,
+}
+
+#[put("/pci/device/config")]
+async fn write_pci_dev_config(meta: web::Json<WritePciConfigMeta>) -> HttpResponse {
+    let meta = meta.into_inner();
+    let data_type = match meta.data_type.parse::<DataType>() {
+        Ok(data_type) => data_type,
+        Err(err) => return error_response(err),
+    };
+    let value = match u64::from_str_radix(&meta.data, 16) {
+        Ok(value) => value,
+        Err(_) => {
+            return error_response(Error::Parse(format!(
+                "invalid data, type: {}, length: {}",
+                meta.data_type,
+                meta.data.len()
+            )))
+        }
+    };
+
+    let dev = pci::PciDevice::new(meta.domain, meta.bus, meta.device, meta.function);
+    if let Err(err) = dev.write_config(meta.offset, &data_type, value) {
+        return error_response(err);
+    }
+
+    HttpResponse::Accepted().body("")
+}
+
+#[derive(Deserialize)]
+struct ReadPciBarArgs {
+    domain: u8,
+    bus: u8,
+    device: u8,
+    function: u8,
+    bar: usize,
+    offset: u64,
+    length: u64,
+    data_type: Option<String>,
+}
+
+#[get("/pci/device/bar")]
+async fn read_pci_bar(args: web::Query<ReadPciBarArgs>) -> HttpResponse {
+    let args = args.into_inner();
+    let data_type = match args
+        .data_type
+        .as_deref()
+        .unwrap_or("byte")
+        .parse::<DataType>()
+    {
+        Ok(data_type) => data_type,
+        Err(err) => return error_response(err),
+    };
+    let length = match check_length(args.length) {
+        Ok(length) => length,
+        Err(err) => return error_response(err),
+    };
+    let dev = pci::PciDevice::new(args.domain, args.bus, args.device, args.function);
+    match pci::bar::read(&dev, args.bar, args.offset, length, &data_type) {
+        Ok(bytes) => read_response(args.offset, bytes),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Deserialize)]
+struct WritePciBarMeta {
+    domain: u8,
+    bus: u8,
+    device: u8,
+    function: u8,
+    bar: usize,
+    offset: u64,
+    data_type: String,
+    data: String,
+}
+
+#[put("/pci/device/bar")]
+async fn write_pci_bar(meta: web::Json<WritePciBarMeta>) -> HttpResponse {
+    let meta = meta.into_inner();
+    let data_type = match meta.data_type.parse::<DataType>() {
+        Ok(data_type) if pci::bar::supports(&data_type) => data_type,
+        Ok(_) => {
+            return error_response(Error::Unsupported(format!(
+                "{} accesses to a BAR",
+                meta.data_type
+            )))
+        }
+        Err(err) => return error_response(err),
+    };
+    let size = data_size(&data_type) as usize;
+    let value = match u64::from_str_radix(&meta.data, 16) {
+        Ok(value) if size >= 8 || value >> (size * 8) == 0 => value,
+        _ => {
+            return error_response(Error::Parse(format!(
+                "invalid data, type: {}, length: {}",
+                meta.data_type,
+                meta.data.len()
+            )))
+        }
+    };
+
+    let dev = pci::PciDevice::new(meta.domain, meta.bus, meta.device, meta.function);
+    let bytes = &value.to_ne_bytes()[..size];
+    if let Err(err) = pci::bar::write(&dev, meta.bar, meta.offset, &data_type, bytes) {
+        return error_response(err);
+    }
+
+    HttpResponse::Accepted().body("")
+}
+
+#[get("/pci/device/config/decoded")]
+async fn get_pci_dev_config_decoded(args: web::Query<PciDevQueryArgs>) -> HttpResponse {
+    let args = args.into_inner();
+    let dev = pci::PciDevice::new(args.domain, args.bus, args.device, args.function);
+    match dev.decoded_config() {
+        Ok(header) => HttpResponse::Ok().json(header),
+        Err(err) => error_response(err),
+    }
+}
+
+#[get("/pci/device/capabilities")]
+async fn get_pci_dev_capabilities(args: web::Query<PciDevQueryArgs>) -> HttpResponse {
+    let args = args.into_inner();
+    let dev = pci::PciDevice::new(args.domain, args.bus, args.device, args.function);
+    match dev.capabilities() {
+        Ok(caps) => HttpResponse::Ok().json(caps),
+        Err(err) => error_response(err),
+    }
+}
@@ -1,11 +1,37 @@
+use clap::Parser;
 use rwlinux::{
     devmem::Devmem,
-    matrix::{init_terminal, reset_terminal, start, Matrix, Result},
+    guard,
+    matrix::{init_terminal, reset_terminal, start, Matrix, Result, PAGE_SIZE},
+    regmap::RegisterMap,
+    utils::parse_u64,
 };
 
+#[derive(Parser)]
+#[clap(about("Browse and edit physical memory via /dev/mem"))]
+struct DevmemApp {
+    /// Refuse every write
+    #[clap(long)]
+    read_only: bool,
+    /// Register map (JSON, or CMSIS-SVD with the .svd extension) that names
+    /// the registers and bitfields under the cursor
+    #[clap(long)]
+    regs: Option<String>,
+    /// Base address of the register map, overrides the one in the file
+    #[clap(long, value_parser = parse_u64, requires = "regs")]
+    base: Option<u64>,
+}
+
 fn main() -> Result<()> {
+    let app = DevmemApp::parse();
+    guard::set_read_only(app.read_only);
+    let mut devmem = Matrix::new("/dev/mem", Devmem::new(PAGE_SIZE)?);
+    if let Some(path) = &app.regs {
+        let mut map = RegisterMap::load(path)?;
+        map.base = app.base.unwrap_or(map.base);
+        devmem.registers = Some(map);
+    }
     let mut terminal = init_terminal()?;
-    let mut devmem: Matrix<Devmem> = Matrix::new("/dev/mem");
     let res = start(&mut terminal, &mut devmem);
     reset_terminal()?;
     if let Err(err) = res {
@@ -1,8 +1,29 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use rwlinux::{
-    devmem::Devmem,
-    matrix::{init_terminal, reset_terminal, start, Matrix, Result},
+    acpi::{self, AcpiBrowser, Table},
+    cpuid::{self, CpuInfo, CpuidView},
+    devmem::{self, Devmem},
+    guard,
+    ioport::{self, IoPort},
+    matrix::{
+        data_size, init_terminal, reset_terminal, start, DataType, Matrix, MatrixData, Result,
+        PAGE_SIZE,
+    },
+    msr::{self, MsrTable},
+    pci::{
+        self,
+        bar::{self, PciBar},
+        caps::link_speed_name,
+        config, link, PciConfig, PciDevice, PciTreeNode,
+    },
+    picker::{self, Picker},
+    regmap::RegisterMap,
+    smbios::{self, SmbiosBrowser},
+    utils::{encode_hex, hexdump, parse_u64},
+    Error,
 };
+use std::io::Write;
+use std::process;
 
 #[derive(Parser)]
 #[clap(
@@ -11,33 +32,924 @@ use rwlinux::{
     about("Read Write on Linux")
 )]
 pub struct RwApp {
+    /// Refuse every write, in the TUI as well
+    #[clap(long, global = true)]
+    read_only: bool,
     #[clap(subcommand)]
     command: Command,
 }
 
 #[derive(Subcommand)]
 enum Command {
+    /// Inspect the ACPI tables in /sys/firmware/acpi/tables, opens a table
+    /// browser without a subcommand
+    Acpi {
+        #[clap(subcommand)]
+        command: Option<AcpiCommand>,
+    },
+    /// Dump and decode the CPUID leaves of a CPU
+    Cpuid {
+        /// CPU to run cpuid on
+        #[clap(long, default_value = "0")]
+        cpu: usize,
+        /// Print the raw leaves instead of the decoded ones
+        #[clap(long, conflicts_with = "json")]
+        raw: bool,
+        /// Print the decoded and raw leaves as JSON
+        #[clap(long)]
+        json: bool,
+        /// Browse the leaves of all CPUs in the TUI
+        #[clap(long, conflicts_with_all = &["raw", "json"])]
+        tui: bool,
+    },
     /// Access physical memory via /dev/mem node
-    Devmem,
+    Devmem {
+        /// Register map (JSON, or CMSIS-SVD with the .svd extension) that
+        /// names the registers and bitfields under the cursor
+        #[clap(long)]
+        regs: Option<String>,
+        /// Base address of the register map, overrides the one in the file
+        #[clap(long, value_parser = parse_u64, requires = "regs")]
+        base: Option<u64>,
+    },
+    /// Read and write physical memory without the TUI
+    Mem {
+        #[clap(subcommand)]
+        command: MemCommand,
+    },
+    /// Access I/O ports via /dev/port, opens the hex viewer without a subcommand
+    Io {
+        /// Issue word and double word accesses with the in/out instructions
+        /// after iopl(3) instead of byte by byte through /dev/port
+        #[clap(long)]
+        iopl: bool,
+        #[clap(subcommand)]
+        command: Option<IoCommand>,
+    },
+    /// Access model-specific registers via /dev/cpu/<n>/msr, opens a table of
+    /// one MSR across all CPUs without a subcommand
+    Msr {
+        #[clap(subcommand)]
+        command: Option<MsrCommand>,
+    },
+    /// List and inspect PCI devices
+    Pci {
+        #[clap(subcommand)]
+        command: PciCommand,
+    },
+    /// Decode the SMBIOS structures from /sys/firmware/dmi/tables
+    Smbios {
+        /// Only print structures of this type, can be repeated
+        #[clap(short = 't', long = "type")]
+        types: Vec<u8>,
+        /// Print the entry point and structures as JSON
+        #[clap(long)]
+        json: bool,
+        /// Browse the structures in the TUI
+        #[clap(long, conflicts_with_all = &["types", "json"])]
+        tui: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemCommand {
+    /// Read values from physical memory
+    Read {
+        /// Physical address, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        addr: u64,
+        /// Access width: b, w, d or q
+        #[clap(short, long, default_value = "b")]
+        width: DataType,
+        /// Number of consecutive values to read
+        #[clap(short, long, default_value = "1")]
+        count: u64,
+    },
+    /// Write a value to physical memory
+    Write {
+        /// Physical address, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        addr: u64,
+        /// Value to write, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        value: u64,
+        /// Access width: b, w, d or q
+        #[clap(short, long)]
+        width: DataType,
+        /// Write to System RAM, the kernel image or unclaimed ranges as well
+        #[clap(long)]
+        force: bool,
+    },
+    /// Dump a range of physical memory
+    Dump {
+        /// Physical address, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        addr: u64,
+        /// Number of bytes to dump
+        #[clap(value_parser = parse_u64)]
+        len: u64,
+        /// Output format
+        #[clap(short, long, value_enum, default_value = "hex")]
+        format: DumpFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum BarCommand {
+    /// Write a value to a register of the BAR
+    Write {
+        /// Offset in the BAR, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        offset: u64,
+        /// Value to write, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        value: u64,
+        /// Access width: b, w, d or q
+        #[clap(short, long)]
+        width: DataType,
+    },
+}
+
+#[derive(Subcommand)]
+enum IoCommand {
+    /// Read values from I/O ports
+    Read {
+        /// Port number, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        port: u64,
+        /// Access width: b, w or d
+        #[clap(short, long, default_value = "b")]
+        width: DataType,
+        /// Number of consecutive values to read
+        #[clap(short, long, default_value = "1")]
+        count: u64,
+    },
+    /// Write a value to an I/O port
+    Write {
+        /// Port number, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        port: u64,
+        /// Value to write, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        value: u64,
+        /// Access width: b, w or d
+        #[clap(short, long)]
+        width: DataType,
+    },
+}
+
+#[derive(Subcommand)]
+enum AcpiCommand {
+    /// List all tables with their header and checksum status
+    List {
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Decode a table
+    Show {
+        /// Table file such as APIC, SSDT2 or dynamic/SSDT3, FADT and MADT are accepted as well
+        name: String,
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Dump the raw bytes of a table
+    Dump {
+        /// Table file such as APIC, SSDT2 or dynamic/SSDT3
+        name: String,
+        /// Output format
+        #[clap(long, value_enum, default_value = "hex")]
+        format: DumpFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum MsrCommand {
+    /// Read an MSR
+    Read {
+        /// Register number, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_reg)]
+        reg: u32,
+        /// CPU to read on
+        #[clap(long, default_value = "0", conflicts_with = "all")]
+        cpu: usize,
+        /// Read on every online CPU
+        #[clap(long)]
+        all: bool,
+    },
+    /// Write an MSR
+    Write {
+        /// Register number, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_reg)]
+        reg: u32,
+        /// Value to write, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        value: u64,
+        /// CPU to write on
+        #[clap(long, default_value = "0", conflicts_with = "all")]
+        cpu: usize,
+        /// Write on every online CPU
+        #[clap(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PciCommand {
+    /// List all PCI devices
+    List {
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Show the PCI bridge hierarchy
+    Tree {
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Show details of a PCI device
+    Show {
+        /// Device address, dddd:bb:dd.f or bb:dd.f
+        #[clap(value_parser = parse_bdf)]
+        bdf: PciDevice,
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Pick a device from an interactive list and inspect it
+    Browse,
+    /// Browse and edit the configuration space of a PCI device in the hex viewer
+    Edit {
+        /// Device address, dddd:bb:dd.f or bb:dd.f
+        #[clap(value_parser = parse_bdf)]
+        bdf: PciDevice,
+        /// Register map (JSON, or CMSIS-SVD with the .svd extension) that
+        /// names the registers and bitfields under the cursor
+        #[clap(long)]
+        regs: Option<String>,
+        /// Offset of the register map in configuration space, 0 by default
+        #[clap(long, value_parser = parse_u64, requires = "regs")]
+        base: Option<u64>,
+    },
+    /// Browse and edit the registers behind a memory BAR in the hex viewer,
+    /// or write one of them with the write subcommand
+    Bar {
+        /// Device address, dddd:bb:dd.f or bb:dd.f
+        #[clap(value_parser = parse_bdf)]
+        bdf: PciDevice,
+        /// BAR number, 0 to 5
+        bar: usize,
+        /// Register map (JSON, or CMSIS-SVD with the .svd extension) that
+        /// names the registers and bitfields under the cursor
+        #[clap(long)]
+        regs: Option<String>,
+        /// Offset of the register map in the BAR, 0 by default
+        #[clap(long, value_parser = parse_u64, requires = "regs")]
+        base: Option<u64>,
+        #[clap(subcommand)]
+        command: Option<BarCommand>,
+    },
+    /// Write a value to the configuration space of a PCI device
+    Write {
+        /// Device address, dddd:bb:dd.f or bb:dd.f
+        #[clap(value_parser = parse_bdf)]
+        bdf: PciDevice,
+        /// Offset in configuration space, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        offset: u64,
+        /// Value to write, hex with 0x prefix or decimal
+        #[clap(value_parser = parse_u64)]
+        value: u64,
+        /// Access width: b, w or d
+        #[clap(short, long)]
+        width: DataType,
+    },
+    /// Compare negotiated and maximum speed and width of PCIe links
+    Link {
+        /// Only show links that trained below their capability
+        #[clap(long)]
+        degraded: bool,
+        /// Print as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+enum DumpFormat {
+    Hex,
+    Bin,
+    Json,
+}
+
+/// Checks that `value` fits in an access of `data_type`
+fn check_value(value: u64, data_type: &DataType) -> Result<()> {
+    let bits = data_size(data_type) * 8;
+    if bits < 64 && value >> bits != 0 {
+        return Err(Box::new(Error::Parse(format!(
+            "0x{:x} does not fit in {} bits",
+            value, bits
+        ))));
+    }
+    Ok(())
+}
+
+fn parse_bdf(s: &str) -> std::result::Result<PciDevice, String> {
+    PciDevice::from_bdf(s).ok_or_else(|| format!("invalid device address: {}", s))
+}
+
+fn parse_reg(s: &str) -> std::result::Result<u32, String> {
+    parse_u64(s)
+        .ok()
+        .and_then(|reg| u32::try_from(reg).ok())
+        .ok_or_else(|| format!("invalid register: {}", s))
+}
+
+fn print_tree(nodes: &[PciTreeNode], prefix: &str) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let bus_range = match node.bus_range {
+            Some((secondary, subordinate)) => format!(" [{:02x}-{:02x}]", secondary, subordinate),
+            None => String::new(),
+        };
+        println!(
+            "{}{}{}{} {}",
+            prefix,
+            if last { "\\-" } else { "+-" },
+            node.device,
+            bus_range,
+            node.info.description()
+        );
+        print_tree(
+            &node.children,
+            &format!("{}{}", prefix, if last { "  " } else { "| " }),
+        );
+    }
+}
+
+fn print_links(reports: &[link::LinkReport]) {
+    let state = |speed: u8, width: u8, max_speed: u8, max_width: u8| {
+        format!(
+            "{} x{} (max {} x{})",
+            link_speed_name(speed),
+            width,
+            link_speed_name(max_speed),
+            max_width
+        )
+    };
+    println!(
+        "{:<14}{:<18}{:<34}{:<14}UPSTREAM LINK",
+        "DEVICE", "TYPE", "LINK", "UPSTREAM"
+    );
+    for report in reports {
+        let l = &report.link;
+        let (upstream, upstream_state) = match &report.upstream {
+            Some(u) => (
+                u.device.to_string(),
+                state(u.speed, u.width, u.max_speed, u.max_width),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        let mut flags = vec![];
+        if report.speed_degraded {
+            flags.push("speed");
+        }
+        if report.width_degraded {
+            flags.push("width");
+        }
+        let line = format!(
+            "{:<14}{:<18}{:<34}{:<14}{:<34}{}",
+            l.device.to_string(),
+            l.port_type,
+            state(l.speed, l.width, l.max_speed, l.max_width),
+            upstream,
+            upstream_state,
+            if flags.is_empty() {
+                String::new()
+            } else {
+                format!("DEGRADED {}", flags.join(","))
+            }
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+fn run_pci(command: PciCommand) -> Result<()> {
+    match command {
+        PciCommand::List { json } => {
+            let devices = pci::devices();
+            if json {
+                let devices: Vec<_> = devices
+                    .iter()
+                    .map(|(device, info)| {
+                        serde_json::json!({
+                            "slot": device.to_string(),
+                            "info": info,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&devices)?);
+            } else {
+                for (device, info) in devices {
+                    println!("{} {}", device, info.description());
+                }
+            }
+        }
+        PciCommand::Tree { json } => {
+            let tree = pci::tree();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tree)?);
+            } else {
+                print_tree(&tree, "");
+            }
+        }
+        PciCommand::Show { bdf, json } => {
+            let info = bdf
+                .basic_info()
+                .ok_or_else(|| Error::NotFound(format!("device {}", bdf)))?;
+            let config = bdf.config_data().unwrap_or_default();
+            if json {
+                let value = serde_json::json!({
+                    "slot": bdf.to_string(),
+                    "info": info,
+                    "driver": bdf.driver(),
+                    "parent": bdf.parent().map(|p| p.to_string()),
+                    "bus_range": bdf.bus_range(),
+                    "config": encode_hex(&config),
+                    "header": bdf.decoded_config().ok(),
+                    "capabilities": bdf.capabilities().unwrap_or_default(),
+                });
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            } else {
+                let name = |id: &(String, Option<String>)| match &id.1 {
+                    Some(name) => format!("{} {}", id.0, name),
+                    None => id.0.to_owned(),
+                };
+                println!("{} {}", bdf, info.description());
+                println!("  Vendor:    {}", name(&info.vendor));
+                println!("  Device:    {}", name(&info.device));
+                println!("  Class:     {}", name(&info.class));
+                println!("  Subclass:  {}", name(&info.sub_class));
+                println!("  Prog-if:   {}", name(&info.prog_if));
+                println!("  Revision:  {}", info.revision);
+                if let Some(driver) = bdf.driver() {
+                    println!("  Driver:    {}", driver);
+                }
+                if let Some(parent) = bdf.parent() {
+                    println!("  Upstream:  {}", parent);
+                }
+                if let Some((secondary, subordinate)) = bdf.bus_range() {
+                    println!("  Bus range: [{:02x}-{:02x}]", secondary, subordinate);
+                }
+                match bdf.decoded_config() {
+                    Ok(header) => {
+                        println!();
+                        for line in header.to_string().lines() {
+                            println!("  {}", line);
+                        }
+                    }
+                    Err(err) => println!("  Header:    {}", err),
+                }
+                for cap in bdf.capabilities().unwrap_or_default() {
+                    for line in cap.to_string().lines() {
+                        println!("  {}", line);
+                    }
+                }
+                if config.len() <= config::HEADER_SIZE {
+                    println!("  Capabilities: <access denied>");
+                }
+                println!();
+                let config: Vec<Option<u8>> = config.into_iter().map(Some).collect();
+                print!("{}", hexdump(0, &config));
+            }
+        }
+        PciCommand::Browse => {
+            let mut terminal = init_terminal()?;
+            let res = picker::start(&mut terminal, &mut Picker::new());
+            reset_terminal()?;
+            if let Err(err) = res {
+                println!("{:?}", err);
+            }
+        }
+        PciCommand::Edit { bdf, regs, base } => {
+            let name = format!("{} config", bdf);
+            let mut matrix = Matrix::new(&name, PciConfig::new(bdf, PAGE_SIZE)?);
+            matrix.registers = load_registers(regs, Some(base.unwrap_or(0)))?;
+            run_matrix(matrix)?;
+        }
+        PciCommand::Bar {
+            bdf,
+            bar,
+            command:
+                Some(BarCommand::Write {
+                    offset,
+                    value,
+                    width,
+                }),
+            ..
+        } => {
+            check_value(value, &width)?;
+            if !bar::supports(&width) {
+                return Err(Box::new(Error::Unsupported(format!(
+                    "{}-bit accesses to a BAR",
+                    data_size(&width) * 8
+                ))));
+            }
+            let size = data_size(&width) as usize;
+            bar::write(&bdf, bar, offset, &width, &value.to_ne_bytes()[..size])?;
+        }
+        PciCommand::Bar {
+            bdf,
+            bar,
+            regs,
+            base,
+            command: None,
+        } => {
+            let name = format!("{} BAR{}", bdf, bar);
+            let mut matrix = Matrix::new(&name, PciBar::new(bdf, bar, PAGE_SIZE)?);
+            // the viewer shows offsets into the BAR, not physical addresses
+            matrix.registers = load_registers(regs, Some(base.unwrap_or(0)))?;
+            run_matrix(matrix)?;
+        }
+        PciCommand::Write {
+            bdf,
+            offset,
+            value,
+            width,
+        } => {
+            check_value(value, &width)?;
+            bdf.write_config(offset, &width, value)?;
+        }
+        PciCommand::Link { degraded, json } => {
+            let mut reports = link::links();
+            if degraded {
+                reports.retain(|r| r.degraded());
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+            } else {
+                print_links(&reports);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints values read from consecutive addresses, a single value without its address
+fn print_values(addr: u64, width: &DataType, values: &[u64], addr_digits: usize) {
+    let digits = data_size(width) as usize * 2;
+    if values.len() == 1 {
+        println!("0x{:0digits$X}", values[0], digits = digits);
+    } else {
+        for (i, value) in values.iter().enumerate() {
+            println!(
+                "0x{:0addr_digits$X}: 0x{:0digits$X}",
+                addr + i as u64 * data_size(width),
+                value,
+                addr_digits = addr_digits,
+                digits = digits
+            );
+        }
+    }
+}
+
+fn run_acpi(command: AcpiCommand) -> Result<()> {
+    match command {
+        AcpiCommand::List { json } => {
+            let tables = acpi::list()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tables)?);
+                return Ok(());
+            }
+            println!(
+                "{:<16}{:>8}  {:<4}{:<8}{:<10}{:<10}Checksum",
+                "Table", "Length", "Rev", "OEM", "OEM Table", "Creator"
+            );
+            for t in tables {
+                let (revision, oem_id, oem_table_id, creator_id) = match &t.header {
+                    Some(h) => (
+                        h.revision.to_string(),
+                        h.oem_id.as_str(),
+                        h.oem_table_id.as_str(),
+                        h.creator_id.as_str(),
+                    ),
+                    None => (String::new(), "", "", ""),
+                };
+                println!(
+                    "{:<16}{:>8}  {:<4}{:<8}{:<10}{:<10}{}",
+                    t.name,
+                    t.length,
+                    revision,
+                    oem_id,
+                    oem_table_id,
+                    creator_id,
+                    match t.checksum_valid {
+                        Some(true) => "ok",
+                        Some(false) => "INVALID",
+                        None => "-",
+                    }
+                );
+            }
+        }
+        AcpiCommand::Show { name, json } => {
+            let table = Table::load(&name)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&table)?);
+            } else {
+                print!("{}", table);
+            }
+        }
+        AcpiCommand::Dump { name, format } => {
+            let data = acpi::read(&name)?;
+            match format {
+                DumpFormat::Hex => {
+                    let data: Vec<Option<u8>> = data.into_iter().map(Some).collect();
+                    print!("{}", hexdump(0, &data));
+                }
+                DumpFormat::Bin => std::io::stdout().write_all(&data)?,
+                DumpFormat::Json => println!(
+                    "{}",
+                    serde_json::json!({ "name": name, "length": data.len(), "data": data })
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_msr(command: MsrCommand) -> Result<()> {
+    match command {
+        MsrCommand::Read { reg, cpu, all } => {
+            if all {
+                for v in msr::read_all(reg)? {
+                    match (v.value, v.error) {
+                        (Some(value), _) => println!("cpu {}: 0x{:016X}", v.cpu, value),
+                        (None, error) => println!("cpu {}: {}", v.cpu, error.unwrap_or_default()),
+                    }
+                }
+            } else {
+                println!("0x{:016X}", msr::read(cpu, reg)?);
+            }
+        }
+        MsrCommand::Write {
+            reg,
+            value,
+            cpu,
+            all,
+        } => {
+            if all {
+                msr::write_all(reg, value)?;
+            } else {
+                msr::write(cpu, reg, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_cpuid(cpu: usize, raw: bool, json: bool) -> Result<()> {
+    let entries = cpuid::dump(cpu)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "cpu": cpu,
+                "info": CpuInfo::decode(&entries),
+                "leaves": entries,
+            })
+        );
+    } else if raw {
+        for entry in &entries {
+            println!("{}", entry);
+        }
+    } else {
+        print!("{}", CpuInfo::decode(&entries));
+    }
+    Ok(())
+}
+
+fn run_smbios(types: Vec<u8>, json: bool) -> Result<()> {
+    let mut smbios = smbios::read()?;
+    if !types.is_empty() {
+        smbios.structures.retain(|s| types.contains(&s.kind));
+    }
+    if json {
+        println!("{}", serde_json::json!(smbios));
+    } else {
+        println!("{}", smbios.entry_point);
+        for structure in &smbios.structures {
+            println!("{}", structure);
+        }
+    }
+    Ok(())
+}
+
+fn run_io(command: IoCommand) -> Result<()> {
+    match command {
+        IoCommand::Read { port, width, count } => {
+            let values = ioport::read_values(port, &width, count)?;
+            print_values(port, &width, &values, 4);
+        }
+        IoCommand::Write { port, value, width } => {
+            check_value(value, &width)?;
+            ioport::write_value(port, &width, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_mem(command: MemCommand) -> Result<()> {
+    match command {
+        MemCommand::Read { addr, width, count } => {
+            let values = devmem::read_values(addr, &width, count)?;
+            print_values(addr, &width, &values, 16);
+        }
+        MemCommand::Write {
+            addr,
+            value,
+            width,
+            force,
+        } => {
+            check_value(value, &width)?;
+            guard::check_phys_write(addr, data_size(&width), force)?;
+            devmem::write_value(addr, &width, value)?;
+        }
+        MemCommand::Dump { addr, len, format } => dump_mem(addr, len, format)?,
+    }
+    Ok(())
+}
+
+/// Dumps `len` bytes of physical memory at `addr` a page at a time, so the
+/// output starts before the whole range is read
+fn dump_mem(addr: u64, len: u64, format: DumpFormat) -> Result<()> {
+    if addr.checked_add(len).is_none() {
+        return Err(Box::new(Error::Unmapped {
+            offset: addr,
+            length: len,
+        }));
+    }
+    let mut mapper = devmem::PhysMapper::new()?;
+    let page_size = mapper.page_size();
+    let mut stdout = std::io::stdout().lock();
+    // first and last unreadable byte and the number of unreadable bytes
+    let mut unread: Option<(u64, u64)> = None;
+    let mut unread_count = 0;
+
+    if let DumpFormat::Json = format {
+        write!(stdout, "{{\"data\":[")?;
+    }
+    let mut cur = addr;
+    while cur < addr + len {
+        // chunks stay a multiple of 16 bytes from addr to keep hex lines whole
+        let chunk = page_size.min(addr + len - cur);
+        let data = match mapper.read_lossy(cur, chunk as usize, &DataType::Byte) {
+            Ok(data) => data,
+            Err(Error::Unmapped { .. }) | Err(Error::PermissionDenied(_)) => {
+                vec![None; chunk as usize]
+            }
+            Err(err) => return Err(Box::new(err)),
+        };
+        for (i, _) in data.iter().enumerate().filter(|(_, b)| b.is_none()) {
+            let byte = cur + i as u64;
+            unread = Some((unread.map_or(byte, |(first, _)| first), byte));
+            unread_count += 1;
+        }
+        match format {
+            DumpFormat::Hex => write!(stdout, "{}", hexdump(cur, &data))?,
+            DumpFormat::Bin => {
+                let bytes: Vec<u8> = data.iter().map(|b| b.unwrap_or(0)).collect();
+                stdout.write_all(&bytes)?;
+            }
+            DumpFormat::Json => {
+                for (i, b) in data.iter().enumerate() {
+                    let sep = if cur == addr && i == 0 { "" } else { "," };
+                    write!(stdout, "{}{}", sep, serde_json::json!(b))?;
+                }
+            }
+        }
+        cur += chunk;
+    }
+    if let DumpFormat::Json = format {
+        writeln!(stdout, "],\"length\":{},\"offset\":{}}}", len, addr)?;
+    }
+    stdout.flush()?;
+
+    // the dump is still printed, but a script must not take the
+    // placeholders of unreadable bytes for memory contents
+    if let Some((first, last)) = unread {
+        eprintln!(
+            "rw: warning: {} of {} bytes could not be read",
+            unread_count, len
+        );
+        return Err(Box::new(Error::Unmapped {
+            offset: first,
+            length: last - first + 1,
+        }));
+    }
+    Ok(())
+}
+
+/// Loads the register map at `path`, `base` replaces the base address given
+/// by the file
+fn load_registers(path: Option<String>, base: Option<u64>) -> Result<Option<RegisterMap>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let mut map = RegisterMap::load(&path)?;
+    if let Some(base) = base {
+        map.base = base;
+    }
+    Ok(Some(map))
+}
+
+/// Runs the hex viewer on `matrix` until the user quits
+fn run_matrix<T: MatrixData>(mut matrix: Matrix<T>) -> Result<()> {
+    let mut terminal = init_terminal()?;
+    let res = start(&mut terminal, &mut matrix);
+    reset_terminal()?;
+    if let Err(err) = res {
+        println!("{:?}", err);
+    }
+    Ok(())
 }
 
 pub fn run() -> Result<()> {
     let app = RwApp::parse();
+    guard::set_read_only(app.read_only);
 
     match app.command {
-        Command::Devmem => {
+        Command::Acpi { command: None } => {
+            let mut browser = AcpiBrowser::new()?;
+            let mut terminal = init_terminal()?;
+            let res = acpi::start(&mut terminal, &mut browser);
+            reset_terminal()?;
+            if let Err(err) = res {
+                println!("{:?}", err);
+            }
+            Ok(())
+        }
+        Command::Acpi {
+            command: Some(command),
+        } => run_acpi(command),
+        Command::Cpuid { cpu, tui: true, .. } => {
+            let mut view = CpuidView::new(cpu)?;
             let mut terminal = init_terminal()?;
-            let mut dm: Matrix<Devmem> = Matrix::new("/dev/mem");
-            let res = start(&mut terminal, &mut dm);
+            let res = cpuid::start(&mut terminal, &mut view);
             reset_terminal()?;
             if let Err(err) = res {
                 println!("{:?}", err);
             }
             Ok(())
         }
+        Command::Cpuid { cpu, raw, json, .. } => run_cpuid(cpu, raw, json),
+        Command::Devmem { regs, base } => {
+            let mut matrix = Matrix::new("/dev/mem", Devmem::new(PAGE_SIZE)?);
+            matrix.registers = load_registers(regs, base)?;
+            run_matrix(matrix)
+        }
+        Command::Io { iopl, command } => {
+            ioport::set_port_instructions(iopl);
+            match command {
+                Some(command) => run_io(command),
+                None => run_matrix(Matrix::new("/dev/port", IoPort::new(PAGE_SIZE)?)),
+            }
+        }
+        Command::Mem { command } => run_mem(command),
+        Command::Msr { command: None } => {
+            // start at the time stamp counter
+            let mut table = MsrTable::new(0x10)?;
+            let mut terminal = init_terminal()?;
+            let res = msr::start(&mut terminal, &mut table);
+            reset_terminal()?;
+            if let Err(err) = res {
+                println!("{:?}", err);
+            }
+            Ok(())
+        }
+        Command::Msr {
+            command: Some(command),
+        } => run_msr(command),
+        Command::Pci { command } => run_pci(command),
+        Command::Smbios { tui: true, .. } => {
+            let mut browser = SmbiosBrowser::new()?;
+            let mut terminal = init_terminal()?;
+            let res = smbios::start(&mut terminal, &mut browser);
+            reset_terminal()?;
+            if let Err(err) = res {
+                println!("{:?}", err);
+            }
+            Ok(())
+        }
+        Command::Smbios { types, json, .. } => run_smbios(types, json),
     }
 }
 
 fn main() {
-    run().unwrap();
+    if let Err(err) = run() {
+        eprintln!("rw: {}", err);
+        process::exit(1);
+    }
 }
@@ -1,10 +1,20 @@
 use actix_cors::Cors;
 use actix_web::{self, middleware::Logger, App, HttpServer};
+use clap::Parser;
 use env_logger;
-use rwlinux::api;
+use rwlinux::{api, guard};
+
+#[derive(Parser)]
+#[clap(about("REST API of rwlinux"))]
+struct RwApiApp {
+    /// Refuse every write request
+    #[clap(long)]
+    read_only: bool,
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    guard::set_read_only(RwApiApp::parse().read_only);
     std::env::set_var("RUST_LOG", "acti_web=info");
     env_logger::init();
 
@@ -19,8 +29,23 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .service(api::read_devmem)
             .service(api::write_devmem)
+            .service(api::read_io)
+            .service(api::write_io)
+            .service(api::get_acpi_tables)
+            .service(api::get_acpi_table)
+            .service(api::get_acpi_table_raw)
+            .service(api::get_cpuid)
+            .service(api::get_smbios)
+            .service(api::read_msr)
+            .service(api::write_msr)
             .service(api::get_pci_devices)
             .service(api::get_pci_dev_config)
+            .service(api::write_pci_dev_config)
+            .service(api::get_pci_dev_config_decoded)
+            .service(api::get_pci_dev_capabilities)
+            .service(api::get_pci_links)
+            .service(api::read_pci_bar)
+            .service(api::write_pci_bar)
     })
     .bind(("0.0.0.0", 8000))?
     .run()
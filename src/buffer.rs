@@ -0,0 +1,55 @@
+//! Read-only [`MatrixData`] backend over bytes that were read up front, such
+//! as firmware tables
+
+use super::error::{Error, Result};
+use super::matrix::{Cell, DataType, MatrixData};
+
+pub struct Buffer {
+    pub data: Vec<u8>,
+    pub inner: Vec<Option<u8>>,
+    pub size: u16,
+}
+
+impl Buffer {
+    pub fn new(data: Vec<u8>, size: u16) -> Self {
+        let mut buffer = Self {
+            data,
+            inner: vec![None; size as usize],
+            size,
+        };
+        // copying from memory can not fail
+        let _ = buffer.update(0, &DataType::Byte);
+        buffer
+    }
+}
+
+impl MatrixData for Buffer {
+    fn write(&mut self, _offset: u64, _data_type: &DataType, _bytes: Vec<u8>) -> Result<()> {
+        Err(Error::Unsupported(String::from(
+            "writing a read-only buffer",
+        )))
+    }
+
+    fn update(&mut self, start: u64, _data_type: &DataType) -> Result<()> {
+        self.inner = vec![None; self.size as usize];
+        let start = (start as usize).min(self.data.len());
+        let end = (start + self.size as usize).min(self.data.len());
+        for (cell, byte) in self.inner.iter_mut().zip(&self.data[start..end]) {
+            *cell = Some(*byte);
+        }
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cell> {
+        if index < self.size as usize {
+            return Some(Cell {
+                inner: self.inner[index],
+            });
+        }
+        None
+    }
+
+    fn limit(&self) -> Option<u64> {
+        Some(self.data.len() as u64)
+    }
+}
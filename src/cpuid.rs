@@ -0,0 +1,741 @@
+//! CPUID dumps through the `/dev/cpu/<n>/cpuid` nodes of the cpuid kernel
+//! module, or the `cpuid` instruction on the target CPU when the module is not
+//! loaded, and decoding of the common leaves
+
+use super::error::{Error, Result};
+use super::msr;
+use super::textview::{self, TextView};
+use crossterm::event::{self, Event, KeyCode};
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use tui::backend::Backend;
+use tui::Terminal;
+
+const BASIC: u32 = 0;
+const HYPERVISOR: u32 = 0x4000_0000;
+const EXTENDED: u32 = 0x8000_0000;
+
+/// Leaves above the base of a range are capped, some hypervisors report
+/// garbage as the maximum leaf
+const MAX_LEAVES: u32 = 0x100;
+
+/// Upper bound of the subleaves enumerated for a single leaf
+const MAX_SUBLEAVES: u32 = 64;
+
+/// Registers returned by the `cpuid` instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CpuidResult {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+impl CpuidResult {
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Result of one leaf and subleaf
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuidEntry {
+    pub leaf: u32,
+    pub subleaf: u32,
+    #[serde(flatten)]
+    pub regs: CpuidResult,
+}
+
+impl fmt::Display for CpuidEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:08x} 0x{:02x}: eax=0x{:08x} ebx=0x{:08x} ecx=0x{:08x} edx=0x{:08x}",
+            self.leaf, self.subleaf, self.regs.eax, self.regs.ebx, self.regs.ecx, self.regs.edx
+        )
+    }
+}
+
+fn cpuid_path(cpu: usize) -> String {
+    format!("/dev/cpu/{}/cpuid", cpu)
+}
+
+/// Reads a leaf from a cpuid node, which takes the leaf in the low and the
+/// subleaf in the high half of the file offset
+fn read_node(file: &File, leaf: u32, subleaf: u32) -> Result<CpuidResult> {
+    let mut buf = [0u8; 16];
+    file.read_exact_at(&mut buf, leaf as u64 | (subleaf as u64) << 32)?;
+    let reg = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+    Ok(CpuidResult {
+        eax: reg(0),
+        ebx: reg(4),
+        ecx: reg(8),
+        edx: reg(12),
+    })
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn native(leaf: u32, subleaf: u32) -> Result<CpuidResult> {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::__cpuid_count;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::__cpuid_count;
+
+    // the intrinsic is only safe to call on newer toolchains
+    #[allow(unused_unsafe)]
+    let r = unsafe { __cpuid_count(leaf, subleaf) };
+    Ok(CpuidResult {
+        eax: r.eax,
+        ebx: r.ebx,
+        ecx: r.ecx,
+        edx: r.edx,
+    })
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn native(_leaf: u32, _subleaf: u32) -> Result<CpuidResult> {
+    Err(Error::Unsupported(String::from(
+        "cpuid on this architecture",
+    )))
+}
+
+/// Runs `f` with the calling thread pinned to `cpu`
+fn on_cpu<T>(cpu: usize, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    // CPU_SET indexes a fixed size bit array
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(Error::NotFound(format!("cpu {}", cpu)));
+    }
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    unsafe {
+        let mut old: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, size, &mut old) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, size, &set) != 0 {
+            return Err(Error::NotFound(format!("cpu {}", cpu)));
+        }
+        let res = f();
+        libc::sched_setaffinity(0, size, &old);
+        res
+    }
+}
+
+/// How the subleaves of a leaf are enumerated
+enum Subleaves {
+    /// the leaf has no subleaves
+    None,
+    /// subleaves up to the maximum reported in eax of subleaf 0
+    MaxInEax,
+    /// subleaves until `valid` fails
+    While(fn(u32, &CpuidResult) -> bool),
+    /// the first `n` subleaves that are not all zero
+    NonZero(u32),
+}
+
+fn subleaves(leaf: u32) -> Subleaves {
+    match leaf {
+        // deterministic cache parameters, until the null cache type
+        0x4 | 0x8000_001d => Subleaves::While(|_, r| r.eax & 0x1f != 0),
+        // extended topology, until the invalid level type
+        0xb | 0x1f | 0x8000_0026 => Subleaves::While(|_, r| (r.ecx >> 8) & 0xff != 0),
+        0x7 | 0x14 | 0x17 | 0x18 | 0x1d | 0x20 | 0x23 => Subleaves::MaxInEax,
+        // XSAVE state components
+        0xd => Subleaves::NonZero(MAX_SUBLEAVES),
+        // RDT monitoring and allocation resources
+        0xf | 0x10 => Subleaves::NonZero(4),
+        // SGX, the EPC sections start at subleaf 2
+        0x12 => Subleaves::While(|subleaf, r| subleaf < 2 || r.eax & 0xf != 0),
+        _ => Subleaves::None,
+    }
+}
+
+/// Enumerates every leaf and subleaf with `query`
+fn enumerate(mut query: impl FnMut(u32, u32) -> Result<CpuidResult>) -> Result<Vec<CpuidEntry>> {
+    let mut entries = vec![];
+    let hypervisor = query(1, 0)?.ecx & (1 << 31) != 0;
+    for base in [BASIC, HYPERVISOR, EXTENDED] {
+        if base == HYPERVISOR && !hypervisor {
+            continue;
+        }
+        let max = query(base, 0)?.eax;
+        if max < base || (base != BASIC && max >= base + MAX_LEAVES) {
+            continue;
+        }
+        for leaf in base..=max.min(base + MAX_LEAVES - 1) {
+            let first = query(leaf, 0)?;
+            entries.push(CpuidEntry {
+                leaf,
+                subleaf: 0,
+                regs: first,
+            });
+            let (last, valid): (u32, fn(u32, &CpuidResult) -> bool) = match subleaves(leaf) {
+                Subleaves::None => continue,
+                Subleaves::MaxInEax => (first.eax.min(MAX_SUBLEAVES - 1), |_, _| true),
+                Subleaves::While(valid) => (MAX_SUBLEAVES - 1, valid),
+                Subleaves::NonZero(n) => (n - 1, |_, _| true),
+            };
+            // subleaf 0 of a cache or topology leaf may already be the terminator
+            if !valid(0, &first) {
+                continue;
+            }
+            for subleaf in 1..=last {
+                let regs = query(leaf, subleaf)?;
+                if !valid(subleaf, &regs) {
+                    break;
+                }
+                if matches!(subleaves(leaf), Subleaves::NonZero(_)) && regs.is_zero() {
+                    continue;
+                }
+                entries.push(CpuidEntry {
+                    leaf,
+                    subleaf,
+                    regs,
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Reads `leaf` and `subleaf` on `cpu`
+pub fn cpuid(cpu: usize, leaf: u32, subleaf: u32) -> Result<CpuidResult> {
+    match File::open(cpuid_path(cpu)) {
+        Ok(file) => read_node(&file, leaf, subleaf),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => on_cpu(cpu, || native(leaf, subleaf)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Dumps all basic, hypervisor and extended leaves with their subleaves on `cpu`
+pub fn dump(cpu: usize) -> Result<Vec<CpuidEntry>> {
+    match File::open(cpuid_path(cpu)) {
+        Ok(file) => enumerate(|leaf, subleaf| read_node(&file, leaf, subleaf)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => on_cpu(cpu, || enumerate(native)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+const LEAF1_EDX: &[(u32, &str)] = &[
+    (0, "fpu"),
+    (1, "vme"),
+    (2, "de"),
+    (3, "pse"),
+    (4, "tsc"),
+    (5, "msr"),
+    (6, "pae"),
+    (7, "mce"),
+    (8, "cx8"),
+    (9, "apic"),
+    (11, "sep"),
+    (12, "mtrr"),
+    (13, "pge"),
+    (14, "mca"),
+    (15, "cmov"),
+    (16, "pat"),
+    (17, "pse36"),
+    (18, "pn"),
+    (19, "clflush"),
+    (21, "dts"),
+    (22, "acpi"),
+    (23, "mmx"),
+    (24, "fxsr"),
+    (25, "sse"),
+    (26, "sse2"),
+    (27, "ss"),
+    (28, "ht"),
+    (29, "tm"),
+    (30, "ia64"),
+    (31, "pbe"),
+];
+
+const LEAF1_ECX: &[(u32, &str)] = &[
+    (0, "pni"),
+    (1, "pclmulqdq"),
+    (2, "dtes64"),
+    (3, "monitor"),
+    (4, "ds_cpl"),
+    (5, "vmx"),
+    (6, "smx"),
+    (7, "est"),
+    (8, "tm2"),
+    (9, "ssse3"),
+    (10, "cid"),
+    (11, "sdbg"),
+    (12, "fma"),
+    (13, "cx16"),
+    (14, "xtpr"),
+    (15, "pdcm"),
+    (17, "pcid"),
+    (18, "dca"),
+    (19, "sse4_1"),
+    (20, "sse4_2"),
+    (21, "x2apic"),
+    (22, "movbe"),
+    (23, "popcnt"),
+    (24, "tsc_deadline_timer"),
+    (25, "aes"),
+    (26, "xsave"),
+    (27, "osxsave"),
+    (28, "avx"),
+    (29, "f16c"),
+    (30, "rdrand"),
+    (31, "hypervisor"),
+];
+
+const LEAF7_EBX: &[(u32, &str)] = &[
+    (0, "fsgsbase"),
+    (1, "tsc_adjust"),
+    (2, "sgx"),
+    (3, "bmi1"),
+    (4, "hle"),
+    (5, "avx2"),
+    (7, "smep"),
+    (8, "bmi2"),
+    (9, "erms"),
+    (10, "invpcid"),
+    (11, "rtm"),
+    (12, "cqm"),
+    (14, "mpx"),
+    (15, "rdt_a"),
+    (16, "avx512f"),
+    (17, "avx512dq"),
+    (18, "rdseed"),
+    (19, "adx"),
+    (20, "smap"),
+    (21, "avx512ifma"),
+    (23, "clflushopt"),
+    (24, "clwb"),
+    (25, "intel_pt"),
+    (26, "avx512pf"),
+    (27, "avx512er"),
+    (28, "avx512cd"),
+    (29, "sha_ni"),
+    (30, "avx512bw"),
+    (31, "avx512vl"),
+];
+
+const LEAF7_ECX: &[(u32, &str)] = &[
+    (1, "avx512vbmi"),
+    (2, "umip"),
+    (3, "pku"),
+    (4, "ospke"),
+    (5, "waitpkg"),
+    (6, "avx512_vbmi2"),
+    (7, "shstk"),
+    (8, "gfni"),
+    (9, "vaes"),
+    (10, "vpclmulqdq"),
+    (11, "avx512_vnni"),
+    (12, "avx512_bitalg"),
+    (13, "tme"),
+    (14, "avx512_vpopcntdq"),
+    (16, "la57"),
+    (22, "rdpid"),
+    (25, "cldemote"),
+    (27, "movdiri"),
+    (28, "movdir64b"),
+    (29, "enqcmd"),
+    (30, "sgx_lc"),
+];
+
+const LEAF7_EDX: &[(u32, &str)] = &[
+    (2, "avx512_4vnniw"),
+    (3, "avx512_4fmaps"),
+    (4, "fsrm"),
+    (8, "avx512_vp2intersect"),
+    (10, "md_clear"),
+    (14, "serialize"),
+    (16, "tsxldtrk"),
+    (18, "pconfig"),
+    (19, "arch_lbr"),
+    (20, "ibt"),
+    (22, "amx_bf16"),
+    (23, "avx512_fp16"),
+    (24, "amx_tile"),
+    (25, "amx_int8"),
+    (26, "spec_ctrl"),
+    (27, "intel_stibp"),
+    (28, "flush_l1d"),
+    (29, "arch_capabilities"),
+    (30, "core_capabilities"),
+    (31, "spec_ctrl_ssbd"),
+];
+
+const EXT1_EDX: &[(u32, &str)] = &[
+    (11, "syscall"),
+    (19, "mp"),
+    (20, "nx"),
+    (22, "mmxext"),
+    (25, "fxsr_opt"),
+    (26, "pdpe1gb"),
+    (27, "rdtscp"),
+    (29, "lm"),
+    (30, "3dnowext"),
+    (31, "3dnow"),
+];
+
+const EXT1_ECX: &[(u32, &str)] = &[
+    (0, "lahf_lm"),
+    (1, "cmp_legacy"),
+    (2, "svm"),
+    (3, "extapic"),
+    (4, "cr8_legacy"),
+    (5, "abm"),
+    (6, "sse4a"),
+    (7, "misalignsse"),
+    (8, "3dnowprefetch"),
+    (9, "osvw"),
+    (10, "ibs"),
+    (11, "xop"),
+    (12, "skinit"),
+    (13, "wdt"),
+    (15, "lwp"),
+    (16, "fma4"),
+    (17, "tce"),
+    (19, "nodeid_msr"),
+    (21, "tbm"),
+    (22, "topoext"),
+    (23, "perfctr_core"),
+    (24, "perfctr_nb"),
+    (26, "bpext"),
+    (27, "ptsc"),
+    (28, "perfctr_llc"),
+    (29, "mwaitx"),
+];
+
+/// A cache described by leaf 4 or 0x8000001d
+#[derive(Debug, Clone, Serialize)]
+pub struct Cache {
+    pub level: u8,
+    pub kind: &'static str,
+    /// size in bytes
+    pub size: u64,
+    pub ways: u32,
+    pub line_size: u32,
+    pub sets: u32,
+    /// logical processors sharing the cache
+    pub shared_by: u32,
+}
+
+impl Cache {
+    fn parse(r: &CpuidResult) -> Option<Self> {
+        let kind = match r.eax & 0x1f {
+            1 => "Data",
+            2 => "Instruction",
+            3 => "Unified",
+            _ => return None,
+        };
+        let line_size = (r.ebx & 0xfff) + 1;
+        let partitions = ((r.ebx >> 12) & 0x3ff) + 1;
+        let ways = ((r.ebx >> 22) & 0x3ff) + 1;
+        let sets = r.ecx.wrapping_add(1);
+        Some(Self {
+            level: ((r.eax >> 5) & 0x7) as u8,
+            kind,
+            size: ways as u64 * partitions as u64 * line_size as u64 * sets as u64,
+            ways,
+            line_size,
+            sets,
+            shared_by: ((r.eax >> 14) & 0xfff) + 1,
+        })
+    }
+}
+
+impl fmt::Display for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = if self.size >= 1 << 20 {
+            format!("{} MiB", self.size >> 20)
+        } else {
+            format!("{} KiB", self.size >> 10)
+        };
+        write!(
+            f,
+            "L{} {:<12}{:>8}, {}-way, {} byte lines, shared by {} threads",
+            self.level, self.kind, size, self.ways, self.line_size, self.shared_by
+        )
+    }
+}
+
+/// Decoded view of the common leaves of a CPUID dump
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuInfo {
+    pub vendor: String,
+    pub brand: Option<String>,
+    pub hypervisor: Option<String>,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    pub features: Vec<&'static str>,
+    pub caches: Vec<Cache>,
+    pub physical_address_bits: Option<u8>,
+    pub linear_address_bits: Option<u8>,
+}
+
+/// Characters of the registers in `regs`, in that order
+fn ascii(regs: &[u32]) -> String {
+    let bytes: Vec<u8> = regs.iter().flat_map(|r| r.to_le_bytes()).collect();
+    String::from_utf8_lossy(&bytes)
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string()
+}
+
+fn flags(reg: u32, table: &[(u32, &'static str)], features: &mut Vec<&'static str>) {
+    features.extend(
+        table
+            .iter()
+            .filter(|(bit, _)| reg & (1 << bit) != 0)
+            .map(|(_, name)| *name),
+    );
+}
+
+impl CpuInfo {
+    /// Decodes the entries returned by [`dump`]
+    pub fn decode(entries: &[CpuidEntry]) -> Self {
+        let get = |leaf: u32, subleaf: u32| {
+            entries
+                .iter()
+                .find(|e| e.leaf == leaf && e.subleaf == subleaf)
+                .map(|e| e.regs)
+        };
+        let leaf0 = get(0, 0).unwrap_or_default();
+        let leaf1 = get(1, 0).unwrap_or_default();
+
+        // the extended family and model only apply to some base families
+        let base_family = (leaf1.eax >> 8) & 0xf;
+        let mut family = base_family;
+        let mut model = (leaf1.eax >> 4) & 0xf;
+        if base_family == 0xf {
+            family += (leaf1.eax >> 20) & 0xff;
+        }
+        if base_family == 0x6 || base_family == 0xf {
+            model |= ((leaf1.eax >> 16) & 0xf) << 4;
+        }
+
+        let mut features = vec![];
+        flags(leaf1.edx, LEAF1_EDX, &mut features);
+        flags(leaf1.ecx, LEAF1_ECX, &mut features);
+        if let Some(leaf7) = get(7, 0) {
+            flags(leaf7.ebx, LEAF7_EBX, &mut features);
+            flags(leaf7.ecx, LEAF7_ECX, &mut features);
+            flags(leaf7.edx, LEAF7_EDX, &mut features);
+        }
+        if let Some(ext1) = get(0x8000_0001, 0) {
+            flags(ext1.edx, EXT1_EDX, &mut features);
+            flags(ext1.ecx, EXT1_ECX, &mut features);
+        }
+
+        // AMD reports the cache topology in 0x8000001d instead of leaf 4
+        let caches_of = |leaf: u32| -> Vec<Cache> {
+            entries
+                .iter()
+                .filter(|e| e.leaf == leaf)
+                .filter_map(|e| Cache::parse(&e.regs))
+                .collect()
+        };
+        let mut caches = caches_of(4);
+        if caches.is_empty() {
+            caches = caches_of(0x8000_001d);
+        }
+
+        let brand = match (
+            get(0x8000_0002, 0),
+            get(0x8000_0003, 0),
+            get(0x8000_0004, 0),
+        ) {
+            (Some(a), Some(b), Some(c)) => Some(ascii(&[
+                a.eax, a.ebx, a.ecx, a.edx, b.eax, b.ebx, b.ecx, b.edx, c.eax, c.ebx, c.ecx, c.edx,
+            ])),
+            _ => None,
+        };
+        let hypervisor = get(HYPERVISOR, 0)
+            .filter(|_| leaf1.ecx & (1 << 31) != 0)
+            .map(|r| ascii(&[r.ebx, r.ecx, r.edx]));
+        let sizes = get(0x8000_0008, 0);
+
+        Self {
+            vendor: ascii(&[leaf0.ebx, leaf0.edx, leaf0.ecx]),
+            brand,
+            hypervisor,
+            family,
+            model,
+            stepping: leaf1.eax & 0xf,
+            features,
+            caches,
+            physical_address_bits: sizes.map(|r| (r.eax & 0xff) as u8),
+            linear_address_bits: sizes.map(|r| ((r.eax >> 8) & 0xff) as u8),
+        }
+    }
+}
+
+impl fmt::Display for CpuInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Vendor:", self.vendor)?;
+        if let Some(brand) = &self.brand {
+            writeln!(f, "{:<24}{}", "Brand:", brand)?;
+        }
+        if let Some(hypervisor) = &self.hypervisor {
+            writeln!(f, "{:<24}{}", "Hypervisor:", hypervisor)?;
+        }
+        writeln!(f, "{:<24}0x{:x} ({})", "Family:", self.family, self.family)?;
+        writeln!(f, "{:<24}0x{:x} ({})", "Model:", self.model, self.model)?;
+        writeln!(f, "{:<24}{}", "Stepping:", self.stepping)?;
+        if let (Some(physical), Some(linear)) =
+            (self.physical_address_bits, self.linear_address_bits)
+        {
+            writeln!(
+                f,
+                "{:<24}{} bits physical, {} bits virtual",
+                "Address sizes:", physical, linear
+            )?;
+        }
+        writeln!(f, "Caches:")?;
+        for cache in &self.caches {
+            writeln!(f, "\t{}", cache)?;
+        }
+        writeln!(f, "Features:")?;
+        let mut line = String::new();
+        for feature in &self.features {
+            if !line.is_empty() && line.len() + feature.len() >= 72 {
+                writeln!(f, "\t{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(feature);
+        }
+        if !line.is_empty() {
+            writeln!(f, "\t{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decoded leaves followed by the raw dump of `cpu` as text
+fn describe(cpu: usize) -> String {
+    match dump(cpu) {
+        Ok(entries) => {
+            let mut text = CpuInfo::decode(&entries).to_string();
+            text.push_str("\nLeaves:\n");
+            for entry in &entries {
+                text.push_str(&format!("\t{}\n", entry));
+            }
+            text
+        }
+        Err(err) => format!("{}\n", err),
+    }
+}
+
+/// Decoded CPUID of one CPU at a time
+pub struct CpuidView {
+    cpus: Vec<usize>,
+    index: usize,
+    view: TextView,
+}
+
+impl CpuidView {
+    pub fn new(cpu: usize) -> Result<Self> {
+        let cpus = msr::cpus()?;
+        let index = cpus
+            .iter()
+            .position(|c| *c == cpu)
+            .ok_or_else(|| Error::NotFound(format!("cpu {}", cpu)))?;
+        let mut view = Self {
+            cpus,
+            index,
+            view: TextView::new("", ""),
+        };
+        view.load();
+        Ok(view)
+    }
+
+    fn load(&mut self) {
+        let cpu = self.cpus[self.index];
+        let scroll = self.view.scroll;
+        self.view = TextView::new(&format!("CPUID of cpu {}", cpu), &describe(cpu));
+        // stay at the same leaf when switching between CPUs
+        self.view.scroll = scroll.min(self.view.lines.len().saturating_sub(1) as u16);
+    }
+}
+
+/// Runs the CPUID view until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, v: &mut CpuidView) -> io::Result<()> {
+    loop {
+        textview::draw(
+            terminal,
+            &v.view,
+            "j/k: scroll  n/p: page  h/l: previous/next cpu  q: quit",
+        )?;
+
+        if let Event::Key(key) = event::read()? {
+            if v.view.scroll_key(key.code) {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('h') | KeyCode::Left if v.index > 0 => {
+                    v.index -= 1;
+                    v.load();
+                }
+                KeyCode::Char('l') | KeyCode::Right if v.index + 1 < v.cpus.len() => {
+                    v.index += 1;
+                    v.load();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(leaf: u32, subleaf: u32, eax: u32, ebx: u32, ecx: u32, edx: u32) -> CpuidEntry {
+        CpuidEntry {
+            leaf,
+            subleaf,
+            regs: CpuidResult { eax, ebx, ecx, edx },
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let entries = vec![
+            // "GenuineIntel"
+            entry(0, 0, 0x16, 0x756e_6547, 0x6c65_746e, 0x4965_6e69),
+            entry(1, 0, 0x0009_06ea, 0, 1 << 28, 1 << 25 | 1),
+            // 32 KiB 8-way L1 data cache shared by 2 threads
+            entry(4, 0, 0x0000_4121, 0x01c0_003f, 0x3f, 0),
+            entry(4, 1, 0x0000_4122, 0x01c0_003f, 0x3f, 0),
+            entry(0x8000_0008, 0, 0x3027, 0, 0, 0),
+        ];
+        let info = CpuInfo::decode(&entries);
+        assert_eq!(info.vendor, "GenuineIntel");
+        assert_eq!((info.family, info.model, info.stepping), (6, 0x9e, 0xa));
+        assert_eq!(info.features, vec!["fpu", "sse", "avx"]);
+        assert_eq!(info.caches.len(), 2);
+        assert_eq!(info.caches[0].level, 1);
+        assert_eq!(info.caches[0].kind, "Data");
+        assert_eq!(info.caches[0].size, 32 << 10);
+        assert_eq!(info.caches[0].shared_by, 2);
+        assert_eq!(info.physical_address_bits, Some(39));
+        assert_eq!(info.linear_address_bits, Some(48));
+
+        // the extended family is added for base family 0xf
+        let info = CpuInfo::decode(&[entry(1, 0, 0x00a2_0f10, 0, 0, 0)]);
+        assert_eq!((info.family, info.model, info.stepping), (0x19, 0x21, 0));
+    }
+
+    #[test]
+    fn test_cpu_out_of_range() {
+        let res = on_cpu(5000, || Ok(()));
+        assert!(matches!(res, Err(Error::NotFound(cpu)) if cpu == "cpu 5000"));
+        assert!(matches!(dump(5000), Err(Error::NotFound(_))));
+    }
+}
@@ -1,93 +1,371 @@
-use super::matrix::{Cell, MatrixData};
+use super::error::{Error, Result};
+use super::guard;
+use super::matrix::{data_size, Cell, DataType, MatrixData};
+use super::memmap::MemoryMap;
 use libc::{O_RDWR, O_SYNC};
-use memmap::MmapOptions;
-use std::fs::OpenOptions;
+use memmap::{Mmap, MmapMut, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::io;
 use std::os::unix::prelude::OpenOptionsExt;
+use std::ptr;
 
 const MEMDEV: &str = "/dev/mem";
 
-pub fn read_byte(offset: u64) -> Option<u8> {
-    if let Ok(file) = OpenOptions::new().read(true).open(MEMDEV) {
-        if let Ok(mmap) = unsafe { MmapOptions::new().offset(offset).len(1).map(&file) } {
-            return Some(mmap[0]);
-        } else {
-            return None;
+/// Maximum number of page windows kept mapped by a [`PhysMapper`]
+const WINDOW_CACHE_SIZE: usize = 64;
+
+enum Mapping {
+    ReadOnly(Mmap),
+    ReadWrite(MmapMut),
+}
+
+/// A single page-aligned mapping of the underlying device
+struct Window {
+    base: u64,
+    mapping: Mapping,
+}
+
+impl Window {
+    fn as_ptr(&self) -> *const u8 {
+        match &self.mapping {
+            Mapping::ReadOnly(m) => m.as_ptr(),
+            Mapping::ReadWrite(m) => m.as_ptr(),
         }
     }
-    None
-}
 
-pub fn write(offset: u64, bytes: Vec<u8>) {
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .custom_flags(O_RDWR | O_SYNC)
-        .open(MEMDEV);
-    if file.is_err() {
-        println!("fail to open /dev/mem");
-        return;
-    }
-
-    let mmap = unsafe {
-        MmapOptions::new()
-            .offset(offset)
-            .len(bytes.len())
-            .map_mut(&file.unwrap())
-    };
-    if mmap.is_err() {
-        println!("fail to map /dev/mem");
-        return;
-    }
-    let mut mmap = mmap.unwrap();
-
-    mmap.copy_from_slice(&bytes)
+    fn as_mut_ptr(&mut self) -> Option<*mut u8> {
+        match &mut self.mapping {
+            Mapping::ReadOnly(_) => None,
+            Mapping::ReadWrite(m) => Some(m.as_mut_ptr()),
+        }
+    }
 }
 
-pub struct Devmem {
-    pub inner: Vec<Option<u8>>,
-    pub size: u16,
+/// Maps physical memory in page-aligned windows and serves reads and writes
+/// of arbitrary length from them.
+///
+/// Recently used windows are kept in a small LRU cache, so refreshing a page
+/// of the hex matrix costs at most one `mmap` instead of one per byte.
+pub struct PhysMapper {
+    path: String,
+    file: File,
+    writable: bool,
+    page_size: u64,
+    /// mapped windows, least recently used first
+    windows: Vec<Window>,
 }
 
-impl MatrixData for Devmem {
-    fn new(size: u16) -> Self {
-        let mut dm = Self {
-            inner: vec![],
-            size,
-        };
-        dm.update(0);
-        dm
+impl PhysMapper {
+    /// Opens `/dev/mem`
+    pub fn new() -> Result<Self> {
+        Self::open(MEMDEV)
     }
 
-    fn write(&self, offset: u64, bytes: Vec<u8>) {
-        let file = OpenOptions::new()
+    /// Opens `path` for read and write, falling back to read-only access
+    /// if the caller is not allowed to write it.
+    pub fn open(path: &str) -> Result<Self> {
+        let (file, writable) = match OpenOptions::new()
             .read(true)
             .write(true)
             .custom_flags(O_RDWR | O_SYNC)
-            .open(MEMDEV);
-        if file.is_err() {
-            println!("fail to open /dev/mem");
-            return;
+            .open(path)
+        {
+            Ok(file) => (file, true),
+            Err(_) => match OpenOptions::new().read(true).open(path) {
+                Ok(file) => (file, false),
+                Err(err) => {
+                    return Err(match err.kind() {
+                        io::ErrorKind::PermissionDenied => {
+                            Error::PermissionDenied(format!("fail to open {}", path))
+                        }
+                        _ => Error::Io(io::Error::new(err.kind(), format!("{}: {}", path, err))),
+                    })
+                }
+            },
+        };
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let page_size = if page_size > 0 {
+            page_size as u64
+        } else {
+            4096
+        };
+
+        Ok(Self {
+            path: String::from(path),
+            file,
+            writable,
+            page_size,
+            windows: Vec::with_capacity(WINDOW_CACHE_SIZE),
+        })
+    }
+
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    /// Size of the windows mappings are made in
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// Returns the window containing `addr`, mapping it if necessary
+    fn window(&mut self, addr: u64) -> Result<&mut Window> {
+        let base = addr - addr % self.page_size;
+
+        if let Some(pos) = self.windows.iter().position(|w| w.base == base) {
+            // move to the most recently used end
+            let window = self.windows.remove(pos);
+            self.windows.push(window);
+        } else {
+            let mut options = MmapOptions::new();
+            options.offset(base).len(self.page_size as usize);
+            let mapping = if self.writable {
+                unsafe { options.map_mut(&self.file) }.map(Mapping::ReadWrite)
+            } else {
+                unsafe { options.map(&self.file) }.map(Mapping::ReadOnly)
+            };
+            let mapping = mapping.map_err(|err| match err.kind() {
+                io::ErrorKind::PermissionDenied => {
+                    Error::PermissionDenied(format!("fail to map {} at 0x{:x}", self.path, base))
+                }
+                _ => Error::Unmapped {
+                    offset: base,
+                    length: self.page_size,
+                },
+            })?;
+
+            if self.windows.len() >= WINDOW_CACHE_SIZE {
+                self.windows.remove(0);
+            }
+            self.windows.push(Window { base, mapping });
+        }
+
+        Ok(self.windows.last_mut().unwrap())
+    }
+
+    /// Checks that an access of `data_type` at `addr` can be issued as a
+    /// single naturally aligned load or store
+    fn check_access(&self, addr: u64, data_type: &DataType) -> Result<()> {
+        if !supports(data_type) {
+            return Err(Error::Unsupported(format!(
+                "{}-bit accesses to {}",
+                data_size(data_type) * 8,
+                self.path
+            )));
+        }
+
+        if addr & (data_size(data_type) - 1) != 0 {
+            return Err(Error::Alignment {
+                offset: addr,
+                width: data_size(data_type),
+            });
         }
+        Ok(())
+    }
 
-        let mmap = unsafe {
-            MmapOptions::new()
-                .offset(offset)
-                .len(bytes.len())
-                .map_mut(&file.unwrap())
+    /// Reads a single `data_type` sized value at `addr` with one volatile load
+    pub fn read_sized(&mut self, addr: u64, data_type: &DataType) -> Result<u64> {
+        self.check_access(addr, data_type)?;
+        let page_offset = (addr % self.page_size) as usize;
+        let window = self.window(addr)?;
+        let src = unsafe { window.as_ptr().add(page_offset) };
+        let value = unsafe {
+            match data_type {
+                DataType::Byte => ptr::read_volatile(src) as u64,
+                DataType::Word => ptr::read_volatile(src as *const u16) as u64,
+                DataType::DoubleWord => ptr::read_volatile(src as *const u32) as u64,
+                _ => ptr::read_volatile(src as *const u64),
+            }
         };
-        if mmap.is_err() {
-            println!("fail to map /dev/mem");
-            return;
+        Ok(value)
+    }
+
+    /// Writes a single `data_type` sized value at `addr` with one volatile store
+    pub fn write_sized(&mut self, addr: u64, data_type: &DataType, value: u64) -> Result<()> {
+        guard::check_writable()?;
+        self.check_access(addr, data_type)?;
+        if !self.writable {
+            return Err(Error::PermissionDenied(format!(
+                "{} is opened read-only",
+                self.path
+            )));
+        }
+        let page_offset = (addr % self.page_size) as usize;
+        let window = self.window(addr)?;
+        let dst = unsafe { window.as_mut_ptr().unwrap().add(page_offset) };
+        unsafe {
+            match data_type {
+                DataType::Byte => ptr::write_volatile(dst, value as u8),
+                DataType::Word => ptr::write_volatile(dst as *mut u16, value as u16),
+                DataType::DoubleWord => ptr::write_volatile(dst as *mut u32, value as u32),
+                _ => ptr::write_volatile(dst as *mut u64, value),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads `buf.len()` bytes starting at physical address `addr`
+    pub fn read(&mut self, addr: u64, buf: &mut [u8]) -> Result<()> {
+        check_range(addr, buf.len() as u64)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let cur = addr + done as u64;
+            let page_offset = (cur % self.page_size) as usize;
+            let chunk = (self.page_size as usize - page_offset).min(buf.len() - done);
+            let window = self.window(cur)?;
+            let src = window.as_ptr();
+            for (i, b) in buf[done..done + chunk].iter_mut().enumerate() {
+                *b = unsafe { ptr::read_volatile(src.add(page_offset + i)) };
+            }
+            done += chunk;
+        }
+        Ok(())
+    }
+
+    /// Reads `len` bytes starting at `addr` using `data_type` sized accesses,
+    /// bytes that cannot be read are returned as `None`.
+    ///
+    /// Fails only if none of the bytes could be read.
+    pub fn read_lossy(
+        &mut self,
+        addr: u64,
+        len: usize,
+        data_type: &DataType,
+    ) -> Result<Vec<Option<u8>>> {
+        let end = check_range(addr, len as u64)?;
+        let size = data_size(data_type) as usize;
+        let mut data = Vec::with_capacity(len);
+        let mut error = None;
+        while data.len() < len {
+            let cur = addr + data.len() as u64;
+            let chunk = size.min(len - data.len());
+            match self.read_sized(cur, data_type) {
+                Ok(value) => data.extend(value.to_ne_bytes()[..chunk].iter().map(|b| Some(*b))),
+                // a page that cannot be mapped is skipped as a whole instead
+                // of retrying the mmap for every access in it
+                Err(err @ (Error::Unmapped { .. } | Error::PermissionDenied(_))) => {
+                    let next = (cur - cur % self.page_size).saturating_add(self.page_size);
+                    data.resize(data.len() + (next.min(end) - cur) as usize, None);
+                    error = Some(err);
+                }
+                Err(err) => {
+                    data.resize(data.len() + chunk, None);
+                    error = Some(err);
+                }
+            }
+        }
+
+        match error {
+            Some(err) if data.iter().all(|b| b.is_none()) => Err(err),
+            _ => Ok(data),
         }
-        let mut mmap = mmap.unwrap();
+    }
 
-        mmap.copy_from_slice(&bytes)
+    /// Writes `bytes` starting at `addr` as a sequence of `data_type` sized stores
+    pub fn write_bytes(&mut self, addr: u64, data_type: &DataType, bytes: &[u8]) -> Result<()> {
+        let size = data_size(data_type) as usize;
+        if !bytes.len().is_multiple_of(size) {
+            return Err(Error::Parse(format!(
+                "{} bytes can not be written with {:?} accesses",
+                bytes.len(),
+                data_type
+            )));
+        }
+        self.check_access(addr, data_type)?;
+        for (i, chunk) in bytes.chunks(size).enumerate() {
+            let mut value = [0u8; 8];
+            value[..size].copy_from_slice(chunk);
+            self.write_sized(
+                addr + (i * size) as u64,
+                data_type,
+                u64::from_ne_bytes(value),
+            )?;
+        }
+        Ok(())
     }
+}
+
+/// Returns the end of the `len` bytes at `addr`, failing if they wrap around
+/// the end of the address space
+fn check_range(addr: u64, len: u64) -> Result<u64> {
+    addr.checked_add(len).ok_or(Error::Unmapped {
+        offset: addr,
+        length: len,
+    })
+}
+
+/// Whether `/dev/mem` can be accessed with a single load or store of `data_type`
+pub fn supports(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::DoubleQuadWord => false,
+        DataType::QuadWord => cfg!(target_pointer_width = "64"),
+        _ => true,
+    }
+}
+
+/// Reads `length` bytes of physical memory starting at `offset`
+pub fn read(offset: u64, length: usize, data_type: &DataType) -> Result<Vec<Option<u8>>> {
+    PhysMapper::new()?.read_lossy(offset, length, data_type)
+}
+
+/// Reads `count` consecutive `data_type` sized values starting at `offset`
+pub fn read_values(offset: u64, data_type: &DataType, count: u64) -> Result<Vec<u64>> {
+    let size = data_size(data_type);
+    check_range(offset, count.saturating_mul(size))?;
+    let mut mapper = PhysMapper::new()?;
+    (0..count)
+        .map(|i| mapper.read_sized(offset + i * size, data_type))
+        .collect()
+}
 
-    fn update(&mut self, start: u64) {
-        self.inner.clear();
-        for i in start..start + self.size as u64 {
-            self.inner.push(read_byte(i));
+/// Writes a single `data_type` sized value to physical memory at `offset`
+pub fn write_value(offset: u64, data_type: &DataType, value: u64) -> Result<()> {
+    PhysMapper::new()?.write_sized(offset, data_type, value)
+}
+
+/// Writes `bytes` to physical memory at `offset` using `data_type` sized accesses
+pub fn write(offset: u64, data_type: &DataType, bytes: Vec<u8>) -> Result<()> {
+    PhysMapper::new()?.write_bytes(offset, data_type, &bytes)
+}
+
+pub struct Devmem {
+    pub inner: Vec<Option<u8>>,
+    pub size: u16,
+    mapper: PhysMapper,
+    memory_map: MemoryMap,
+}
+
+impl Devmem {
+    pub fn new(size: u16) -> Result<Self> {
+        let mut dm = Self {
+            inner: vec![None; size as usize],
+            size,
+            mapper: PhysMapper::new()?,
+            // the viewer works without region names
+            memory_map: MemoryMap::load().unwrap_or_default(),
+        };
+        // the first page is often not accessible, e.g. with CONFIG_STRICT_DEVMEM
+        let _ = dm.update(0, &DataType::Byte);
+        Ok(dm)
+    }
+}
+
+impl MatrixData for Devmem {
+    fn write(&mut self, offset: u64, data_type: &DataType, bytes: Vec<u8>) -> Result<()> {
+        self.mapper.write_bytes(offset, data_type, &bytes)
+    }
+
+    fn update(&mut self, start: u64, data_type: &DataType) -> Result<()> {
+        match self.mapper.read_lossy(start, self.size as usize, data_type) {
+            Ok(data) => {
+                self.inner = data;
+                Ok(())
+            }
+            Err(err) => {
+                self.inner = vec![None; self.size as usize];
+                Err(err)
+            }
         }
     }
 
@@ -99,4 +377,83 @@ impl MatrixData for Devmem {
         }
         None
     }
+
+    fn supports(&self, data_type: &DataType) -> bool {
+        supports(data_type)
+    }
+
+    fn memory_map(&self) -> Option<&MemoryMap> {
+        Some(&self.memory_map)
+    }
+
+    fn scannable(&self, offset: u64, len: u64) -> bool {
+        self.memory_map.is_memory(offset, len)
+    }
+
+    fn read_at(&mut self, offset: u64, data_type: &DataType, len: usize) -> Option<Vec<u8>> {
+        let data = self.mapper.read_lossy(offset, len, data_type).ok()?;
+        data.into_iter().collect()
+    }
+
+    fn write_command(&self, offset: u64, data_type: &DataType, value: u64) -> Option<String> {
+        // the write was confirmed in the TUI already
+        Some(format!(
+            "rw mem write 0x{:x} 0x{:x} --width {} --force",
+            offset,
+            value,
+            data_type.short_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_phys_mapper_cross_page() {
+        let path = std::env::temp_dir().join(format!("rwlinux-devmem-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        let data: Vec<u8> = (0..3 * 4096).map(|i| (i % 251) as u8).collect();
+        file.write_all(&data).unwrap();
+        drop(file);
+
+        let mut mapper = PhysMapper::open(path.to_str().unwrap()).unwrap();
+        let mut buf = vec![0u8; 300];
+        mapper.read(4000, &mut buf).unwrap();
+        assert_eq!(buf, data[4000..4300]);
+
+        mapper
+            .write_bytes(4095, &DataType::Byte, &[0xaa, 0xbb])
+            .unwrap();
+        let mut buf = [0u8; 2];
+        mapper.read(4095, &mut buf).unwrap();
+        assert_eq!(buf, [0xaa, 0xbb]);
+
+        mapper
+            .write_sized(8, &DataType::DoubleWord, 0x12345678)
+            .unwrap();
+        assert_eq!(
+            mapper.read_sized(8, &DataType::DoubleWord).unwrap(),
+            0x12345678
+        );
+        assert_eq!(mapper.read_sized(8, &DataType::Word).unwrap(), 0x5678);
+        assert!(matches!(
+            mapper.read_sized(9, &DataType::Word),
+            Err(Error::Alignment { .. })
+        ));
+        assert!(matches!(
+            mapper.read_sized(0, &DataType::DoubleQuadWord),
+            Err(Error::Unsupported(_))
+        ));
+        // ranges that wrap around the address space are refused up front
+        assert!(matches!(
+            mapper.read_lossy(0xffffffffffffff00, 0x1000, &DataType::Byte),
+            Err(Error::Unmapped { .. })
+        ));
+        assert!(mapper.read(u64::MAX, &mut [0u8; 2]).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
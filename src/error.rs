@@ -0,0 +1,62 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by the data backends of rwlinux
+#[derive(Debug)]
+pub enum Error {
+    /// The caller is not allowed to access the resource
+    PermissionDenied(String),
+    /// The requested object does not exist
+    NotFound(String),
+    /// Nothing that can be mapped backs the address range
+    Unmapped { offset: u64, length: u64 },
+    /// The offset is not aligned to the access width
+    Alignment { offset: u64, width: u64 },
+    /// The backend does not support the requested operation
+    Unsupported(String),
+    /// Invalid input from the user
+    Parse(String),
+    /// A write was refused to protect the system, e.g. in read-only mode
+    Refused(String),
+    /// Any other I/O failure
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PermissionDenied(msg) => write!(f, "permission denied: {}", msg),
+            Error::NotFound(what) => write!(f, "{} not found", what),
+            Error::Unmapped { offset, length } => {
+                write!(f, "unmapped range: 0x{:x}+0x{:x}", offset, length)
+            }
+            Error::Alignment { offset, width } => {
+                write!(f, "offset 0x{:x} is not aligned to {} bytes", offset, width)
+            }
+            Error::Unsupported(msg) => write!(f, "not supported: {}", msg),
+            Error::Parse(msg) => write!(f, "parse error: {}", msg),
+            Error::Refused(msg) => write!(f, "write refused: {}", msg),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(err.to_string()),
+            _ => Error::Io(err),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Protection against harmful writes: a process wide read-only mode and the
+//! classification of physical write targets by the memory map
+
+use super::error::{Error, Result};
+use super::memmap::MemoryMap;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables read-only mode, in which every backend refuses to write
+pub fn set_read_only(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Fails in read-only mode
+pub fn check_writable() -> Result<()> {
+    if read_only() {
+        return Err(Error::Refused(String::from("read-only mode")));
+    }
+    Ok(())
+}
+
+/// What a physical address belongs to, ordered from harmless to dangerous
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum WriteTarget {
+    /// claimed by a device, a bus or the firmware
+    Device,
+    /// not claimed by anything in the memory map
+    Unclaimed,
+    SystemRam,
+    /// code or data of the running kernel
+    Kernel,
+}
+
+impl WriteTarget {
+    fn of(map: &MemoryMap, addr: u64) -> Self {
+        let regions = map.regions_at(addr);
+        if regions.is_empty() {
+            WriteTarget::Unclaimed
+        } else if regions.iter().any(|r| r.name.starts_with("Kernel ")) {
+            WriteTarget::Kernel
+        } else if regions.iter().any(|r| r.name == "System RAM") {
+            WriteTarget::SystemRam
+        } else {
+            WriteTarget::Device
+        }
+    }
+
+    /// Classifies a write of `len` bytes at `addr` by the most dangerous of
+    /// the regions and gaps it overlaps
+    pub fn classify(map: &MemoryMap, addr: u64, len: u64) -> Self {
+        let last = addr.saturating_add(len.max(1) - 1);
+        // the class only changes where a region starts or ends, so checking
+        // the first byte and every such boundary inside the range covers it
+        let boundaries = map
+            .regions
+            .iter()
+            .flat_map(|r| [Some(r.start), r.end.checked_add(1)])
+            .flatten()
+            .filter(|b| addr < *b && *b <= last);
+        std::iter::once(addr)
+            .chain(boundaries)
+            .map(|b| Self::of(map, b))
+            .max()
+            .unwrap_or(WriteTarget::Device)
+    }
+
+    /// Whether the user has to confirm a write to the target
+    pub fn needs_confirmation(&self) -> bool {
+        *self != WriteTarget::Device
+    }
+}
+
+impl fmt::Display for WriteTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteTarget::Device => write!(f, "a device region"),
+            WriteTarget::Unclaimed => write!(f, "an unclaimed range"),
+            WriteTarget::SystemRam => write!(f, "System RAM"),
+            WriteTarget::Kernel => write!(f, "the kernel image"),
+        }
+    }
+}
+
+/// Fails if writing `len` bytes of physical memory at `addr` needs a
+/// confirmation and `force` is not set
+pub fn check_phys_write(addr: u64, len: u64, force: bool) -> Result<()> {
+    check_writable()?;
+    if force {
+        return Ok(());
+    }
+    // without a memory map every address counts as unclaimed
+    let map = MemoryMap::load().unwrap_or_default();
+    let target = WriteTarget::classify(&map, addr, len);
+    if target.needs_confirmation() {
+        return Err(Error::Refused(format!(
+            "0x{:x} is in {}, force the write to proceed",
+            addr, target
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let map = MemoryMap::parse_iomem(
+            "00001000-0009fbff : System RAM\n\
+             00100000-bfffffff : System RAM\n\
+             \x20 01000000-021352a7 : Kernel code\n\
+             fec00000-fec003ff : IOAPIC 0\n",
+        )
+        .unwrap();
+        let classify = |addr, len| WriteTarget::classify(&map, addr, len);
+        assert_eq!(classify(0xfec00000, 4), WriteTarget::Device);
+        assert_eq!(classify(0x2000, 8), WriteTarget::SystemRam);
+        assert_eq!(classify(0x1000010, 1), WriteTarget::Kernel);
+        assert_eq!(classify(0xa0000, 4), WriteTarget::Unclaimed);
+        // the last byte is past the IOAPIC
+        assert_eq!(classify(0xfec003fe, 4), WriteTarget::Unclaimed);
+        // the kernel lies strictly inside the write
+        assert_eq!(classify(0xf00000, 0x2000000), WriteTarget::Kernel);
+
+        // RAM between two device regions
+        let map = MemoryMap::parse_iomem(
+            "fe000000-fe000fff : pci\n\
+             fe001000-fe001fff : System RAM\n\
+             fe002000-fe002fff : pci\n",
+        )
+        .unwrap();
+        let classify = |addr, len| WriteTarget::classify(&map, addr, len);
+        assert_eq!(classify(0xfe000ffc, 0x1008), WriteTarget::SystemRam);
+        assert_eq!(classify(0xfe000000, 0x1000), WriteTarget::Device);
+        assert_eq!(classify(0xfe002000, 0x1000), WriteTarget::Device);
+        assert!(!WriteTarget::Device.needs_confirmation());
+    }
+}
@@ -0,0 +1,363 @@
+use super::error::{Error, Result};
+use super::guard;
+use super::matrix::{data_size, Cell, DataType, MatrixData};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const PORTDEV: &str = "/dev/port";
+
+static PORT_INSTRUCTIONS: AtomicBool = AtomicBool::new(false);
+
+/// Issues word and double word accesses with the `in`/`out` instructions
+/// after raising the I/O privilege level of the whole process, instead of
+/// through `/dev/port`
+pub fn set_port_instructions(enabled: bool) {
+    PORT_INSTRUCTIONS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn port_instructions() -> bool {
+    PORT_INSTRUCTIONS.load(Ordering::Relaxed)
+}
+
+/// Size of the x86 I/O port address space
+pub const IO_SPACE_SIZE: u64 = 0x10000;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod insn {
+    use std::arch::asm;
+
+    pub unsafe fn inw(port: u16) -> u16 {
+        let value: u16;
+        asm!(
+            "in ax, dx",
+            out("ax") value,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags)
+        );
+        value
+    }
+
+    pub unsafe fn inl(port: u16) -> u32 {
+        let value: u32;
+        asm!(
+            "in eax, dx",
+            out("eax") value,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags)
+        );
+        value
+    }
+
+    pub unsafe fn outw(port: u16, value: u16) {
+        asm!(
+            "out dx, ax",
+            in("dx") port,
+            in("ax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+
+    pub unsafe fn outl(port: u16, value: u32) {
+        asm!(
+            "out dx, eax",
+            in("dx") port,
+            in("eax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+/// Accesses I/O ports.
+///
+/// All accesses go through `/dev/port` by default, which splits word and
+/// double word accesses into single bytes. Devices that only accept full
+/// width accesses to their 16 and 32-bit registers need
+/// [`set_port_instructions`]. Both need `CAP_SYS_RAWIO`.
+pub struct PortAccess {
+    file: File,
+    writable: bool,
+    /// whether `iopl(3)` succeeded
+    privileged: bool,
+}
+
+impl PortAccess {
+    /// Opens `/dev/port` for read and write, falling back to read-only access
+    pub fn new() -> Result<Self> {
+        let (file, writable) = match OpenOptions::new().read(true).write(true).open(PORTDEV) {
+            Ok(file) => (file, true),
+            Err(_) => match File::open(PORTDEV) {
+                Ok(file) => (file, false),
+                Err(err) => {
+                    return Err(match err.kind() {
+                        io::ErrorKind::PermissionDenied => {
+                            Error::PermissionDenied(format!("fail to open {}", PORTDEV))
+                        }
+                        io::ErrorKind::NotFound => Error::NotFound(String::from(PORTDEV)),
+                        _ => Error::Io(io::Error::new(err.kind(), format!("{}: {}", PORTDEV, err))),
+                    })
+                }
+            },
+        };
+        Ok(Self {
+            file,
+            writable,
+            privileged: false,
+        })
+    }
+
+    /// Checks that an access of `data_type` at `port` stays within the I/O space
+    fn check_access(&mut self, port: u64, data_type: &DataType) -> Result<()> {
+        let size = data_size(data_type);
+        if !supports(data_type) {
+            return Err(Error::Unsupported(format!(
+                "{}-bit I/O port accesses",
+                size * 8
+            )));
+        }
+        check_range(port, size)?;
+        if size > 1 && port_instructions() && !self.privileged {
+            self.raise_iopl()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn raise_iopl(&mut self) -> Result<()> {
+        if unsafe { libc::iopl(3) } != 0 {
+            return Err(Error::PermissionDenied(format!(
+                "iopl: {}",
+                io::Error::last_os_error()
+            )));
+        }
+        self.privileged = true;
+        Ok(())
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    fn raise_iopl(&mut self) -> Result<()> {
+        Err(Error::Unsupported(String::from(
+            "wide I/O port accesses on this architecture",
+        )))
+    }
+
+    /// Reads a single `data_type` sized value from `port`
+    pub fn read_sized(&mut self, port: u64, data_type: &DataType) -> Result<u64> {
+        self.check_access(port, data_type)?;
+        match data_type {
+            _ if *data_type == DataType::Byte || !port_instructions() => {
+                let mut buf = [0u8; 8];
+                self.file
+                    .read_exact_at(&mut buf[..data_size(data_type) as usize], port)?;
+                Ok(u64::from_le_bytes(buf))
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            DataType::Word => Ok(unsafe { insn::inw(port as u16) } as u64),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            DataType::DoubleWord => Ok(unsafe { insn::inl(port as u16) } as u64),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Writes a single `data_type` sized value to `port`
+    pub fn write_sized(&mut self, port: u64, data_type: &DataType, value: u64) -> Result<()> {
+        guard::check_writable()?;
+        self.check_access(port, data_type)?;
+        if !self.writable {
+            return Err(Error::PermissionDenied(format!(
+                "{} is opened read-only",
+                PORTDEV
+            )));
+        }
+        match data_type {
+            _ if *data_type == DataType::Byte || !port_instructions() => {
+                let size = data_size(data_type) as usize;
+                self.file.write_all_at(&value.to_le_bytes()[..size], port)?
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            DataType::Word => unsafe { insn::outw(port as u16, value as u16) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            DataType::DoubleWord => unsafe { insn::outl(port as u16, value as u32) },
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Reads `len` bytes starting at `port` using `data_type` sized accesses,
+    /// ports that can not be read are returned as `None`
+    pub fn read_lossy(
+        &mut self,
+        port: u64,
+        len: usize,
+        data_type: &DataType,
+    ) -> Result<Vec<Option<u8>>> {
+        check_range(port, len as u64)?;
+        let size = data_size(data_type) as usize;
+        let mut data = Vec::with_capacity(len);
+        let mut error = None;
+        while data.len() < len {
+            let cur = port + data.len() as u64;
+            let chunk = size.min(len - data.len());
+            match self.read_sized(cur, data_type) {
+                Ok(value) => data.extend(value.to_le_bytes()[..chunk].iter().map(|b| Some(*b))),
+                Err(err) => {
+                    data.resize(data.len() + chunk, None);
+                    error = Some(err);
+                }
+            }
+        }
+
+        match error {
+            Some(err) if data.iter().all(|b| b.is_none()) => Err(err),
+            _ => Ok(data),
+        }
+    }
+
+    /// Writes `bytes` starting at `port` as a sequence of `data_type` sized accesses
+    pub fn write_bytes(&mut self, port: u64, data_type: &DataType, bytes: &[u8]) -> Result<()> {
+        let size = data_size(data_type) as usize;
+        if !bytes.len().is_multiple_of(size) {
+            return Err(Error::Parse(format!(
+                "{} bytes can not be written with {:?} accesses",
+                bytes.len(),
+                data_type
+            )));
+        }
+        check_range(port, bytes.len() as u64)?;
+        for (i, chunk) in bytes.chunks(size).enumerate() {
+            let mut value = [0u8; 8];
+            value[..size].copy_from_slice(chunk);
+            self.write_sized(
+                port + (i * size) as u64,
+                data_type,
+                u64::from_le_bytes(value),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fails unless the `len` ports at `port` lie within the I/O space
+fn check_range(port: u64, len: u64) -> Result<()> {
+    if port.checked_add(len).is_none_or(|end| end > IO_SPACE_SIZE) {
+        return Err(Error::Unmapped {
+            offset: port,
+            length: len,
+        });
+    }
+    Ok(())
+}
+
+/// Whether I/O ports can be accessed with accesses of `data_type`
+pub fn supports(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Byte | DataType::Word | DataType::DoubleWord
+    )
+}
+
+/// Reads `length` bytes of I/O space starting at `port`
+pub fn read(port: u64, length: usize, data_type: &DataType) -> Result<Vec<Option<u8>>> {
+    PortAccess::new()?.read_lossy(port, length, data_type)
+}
+
+/// Reads `count` consecutive `data_type` sized values starting at `port`
+pub fn read_values(port: u64, data_type: &DataType, count: u64) -> Result<Vec<u64>> {
+    let size = data_size(data_type);
+    check_range(port, count.saturating_mul(size))?;
+    let mut access = PortAccess::new()?;
+    (0..count)
+        .map(|i| access.read_sized(port + i * size, data_type))
+        .collect()
+}
+
+/// Writes a single `data_type` sized value to `port`
+pub fn write_value(port: u64, data_type: &DataType, value: u64) -> Result<()> {
+    PortAccess::new()?.write_sized(port, data_type, value)
+}
+
+pub struct IoPort {
+    pub inner: Vec<Option<u8>>,
+    pub size: u16,
+    access: PortAccess,
+}
+
+impl IoPort {
+    pub fn new(size: u16) -> Result<Self> {
+        let mut io = Self {
+            inner: vec![None; size as usize],
+            size,
+            access: PortAccess::new()?,
+        };
+        io.update(0, &DataType::Byte)?;
+        Ok(io)
+    }
+}
+
+impl MatrixData for IoPort {
+    fn write(&mut self, offset: u64, data_type: &DataType, bytes: Vec<u8>) -> Result<()> {
+        self.access.write_bytes(offset, data_type, &bytes)
+    }
+
+    fn update(&mut self, start: u64, data_type: &DataType) -> Result<()> {
+        self.inner = vec![None; self.size as usize];
+        if start >= IO_SPACE_SIZE {
+            return Ok(());
+        }
+        let length = (self.size as u64).min(IO_SPACE_SIZE - start) as usize;
+        let data = self.access.read_lossy(start, length, data_type)?;
+        self.inner[..length].copy_from_slice(&data);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cell> {
+        if index < self.size as usize {
+            return Some(Cell {
+                inner: self.inner[index],
+            });
+        }
+        None
+    }
+
+    fn supports(&self, data_type: &DataType) -> bool {
+        supports(data_type)
+    }
+
+    fn limit(&self) -> Option<u64> {
+        Some(IO_SPACE_SIZE)
+    }
+
+    fn scannable(&self, _offset: u64, _len: u64) -> bool {
+        false
+    }
+
+    fn write_command(&self, offset: u64, data_type: &DataType, value: u64) -> Option<String> {
+        let iopl = match port_instructions() {
+            true => " --iopl",
+            false => "",
+        };
+        Some(format!(
+            "rw io{} write 0x{:x} 0x{:x} --width {}",
+            iopl,
+            offset,
+            value,
+            data_type.short_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_range() {
+        assert!(check_range(0, IO_SPACE_SIZE).is_ok());
+        assert!(check_range(0xfffc, 4).is_ok());
+        assert!(check_range(0xfffe, 4).is_err());
+        // would wrap around to port 0
+        assert!(check_range(u64::MAX, 2).is_err());
+        assert!(check_range(0x10, u64::MAX).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Session journal of the writes made in the hex matrix, backs undo and redo
+//! and exports the writes as a shell script
+
+use super::error::{Error, Result};
+use super::matrix::{data_size, DataType};
+use std::fmt;
+
+/// A write of `new` over `old` at `offset` with `data_type` sized accesses
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub offset: u64,
+    pub data_type: DataType,
+    /// bytes before the write, `None` if they could not be read
+    pub old: Option<Vec<u8>>,
+    pub new: Vec<u8>,
+}
+
+/// Values of the `data_type` sized accesses that make up `bytes`
+fn values(bytes: &[u8], data_type: &DataType) -> Vec<u64> {
+    let size = (data_size(data_type) as usize).min(8);
+    bytes
+        .chunks(size)
+        .map(|chunk| {
+            let mut value = [0u8; 8];
+            value[..chunk.len()].copy_from_slice(chunk);
+            u64::from_ne_bytes(value)
+        })
+        .collect()
+}
+
+fn format_values(bytes: &[u8], data_type: &DataType) -> String {
+    let digits = data_size(data_type) as usize * 2;
+    values(bytes, data_type)
+        .iter()
+        .map(|value| format!("{:0digits$X}", value, digits = digits))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let old = match &self.old {
+            Some(old) => format_values(old, &self.data_type),
+            None => String::from("??"),
+        };
+        write!(
+            f,
+            "0x{:X} {}: {} -> {}",
+            self.offset,
+            self.data_type.short_name(),
+            old,
+            format_values(&self.new, &self.data_type)
+        )
+    }
+}
+
+/// Writes in the order they were made, the entries after `applied` have been
+/// undone and are dropped by the next write
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    applied: usize,
+}
+
+impl Journal {
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.entries.truncate(self.applied);
+        self.entries.push(entry);
+        self.applied += 1;
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Number of entries that have not been undone
+    pub fn applied(&self) -> usize {
+        self.applied
+    }
+
+    /// The write the next undo reverts
+    pub fn undo_entry(&self) -> Option<&JournalEntry> {
+        self.entries[..self.applied].last()
+    }
+
+    /// The write the next redo repeats
+    pub fn redo_entry(&self) -> Option<&JournalEntry> {
+        self.entries.get(self.applied)
+    }
+
+    /// Marks the last applied entry as undone
+    pub fn step_back(&mut self) {
+        self.applied = self.applied.saturating_sub(1);
+    }
+
+    /// Marks the first undone entry as applied again
+    pub fn step_forward(&mut self) {
+        self.applied = (self.applied + 1).min(self.entries.len());
+    }
+
+    /// Shell script that replays the applied writes, `command` gives the
+    /// command line of one access and `None` if the target has none, which
+    /// fails the export as the script could not replay that write
+    pub fn script<F>(&self, name: &str, command: F) -> Result<String>
+    where
+        F: Fn(u64, &DataType, u64) -> Option<String>,
+    {
+        let mut script = format!(
+            "#!/bin/sh\n# writes to {} recorded by rw, replay them in order\nset -e\n",
+            name
+        );
+        for entry in &self.entries[..self.applied] {
+            script.push_str(&format!("\n# {}\n", entry));
+            let size = data_size(&entry.data_type);
+            for (i, value) in values(&entry.new, &entry.data_type).iter().enumerate() {
+                let offset = entry.offset + i as u64 * size;
+                let line = command(offset, &entry.data_type, *value).ok_or_else(|| {
+                    Error::Unsupported(format!(
+                        "no command to write 0x{:x} at 0x{:x}",
+                        value, offset
+                    ))
+                })?;
+                script.push_str(&format!("{}\n", line));
+            }
+        }
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(offset: u64, old: u8, new: u8) -> JournalEntry {
+        JournalEntry {
+            offset,
+            data_type: DataType::Byte,
+            old: Some(vec![old]),
+            new: vec![new],
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut journal = Journal::default();
+        journal.record(entry(0x10, 0x00, 0x11));
+        journal.record(entry(0x20, 0x00, 0x22));
+        assert_eq!(journal.undo_entry().unwrap().offset, 0x20);
+        journal.step_back();
+        assert_eq!(journal.redo_entry().unwrap().offset, 0x20);
+        // a new write drops the undone one
+        journal.record(entry(0x30, 0x00, 0x33));
+        assert_eq!(journal.entries().len(), 2);
+        assert!(journal.redo_entry().is_none());
+        journal.step_back();
+        journal.step_back();
+        journal.step_back();
+        assert_eq!(journal.applied(), 0);
+        journal.step_forward();
+
+        journal.record(JournalEntry {
+            offset: 0x40,
+            data_type: DataType::Word,
+            old: None,
+            new: vec![0x34, 0x12, 0x78, 0x56],
+        });
+        assert_eq!(journal.entries()[1].to_string(), "0x40 w: ?? -> 1234 5678");
+        let script = journal
+            .script("test", |offset, data_type, value| {
+                Some(format!(
+                    "write 0x{:x} 0x{:x} {}",
+                    offset,
+                    value,
+                    data_type.short_name()
+                ))
+            })
+            .unwrap();
+        assert!(script
+            .ends_with("# 0x40 w: ?? -> 1234 5678\nwrite 0x40 0x1234 w\nwrite 0x42 0x5678 w\n"));
+        assert!(script.contains("write 0x10 0x11 b\n"));
+        // a write the script cannot replay fails the export
+        assert!(journal.script("test", |_, _, _| None).is_err());
+    }
+}
@@ -1,5 +1,21 @@
+pub mod acpi;
 pub mod api;
+pub mod buffer;
+pub mod cpuid;
 pub mod devmem;
+pub mod error;
+pub mod guard;
+pub mod ioport;
+pub mod journal;
 pub mod matrix;
+pub mod memmap;
+pub mod msr;
 pub mod pci;
+pub mod picker;
+pub mod regmap;
+pub mod search;
+pub mod smbios;
+pub mod textview;
 pub mod utils;
+
+pub use error::Error;
@@ -1,17 +1,28 @@
-use crossterm::event::{self, Event, KeyCode};
+use super::error::{self, Error};
+use super::guard::{self, WriteTarget};
+use super::journal::{Journal, JournalEntry};
+use super::memmap::MemoryMap;
+use super::regmap::{Register, RegisterMap};
+use super::search::{parse_pattern, Scan, ScanState};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use hex;
-use std::error::Error;
+use std::fmt;
+use std::fs;
 use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tui::backend::{Backend, CrosstermBackend};
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Style};
-use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use tui::{Frame, Terminal};
 use unicode_width::UnicodeWidthStr;
 
-pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 struct JumpAddress {
     inner: String,
@@ -67,10 +78,11 @@ impl WriteValue {
         }
     }
 
-    fn parse(&self) -> Option<Vec<u8>> {
+    /// Parses the input into the access width and the bytes to write
+    fn parse(&self) -> Option<(DataType, Vec<u8>)> {
         if !self.inner.contains(":") {
             if let Ok(bytes) = hex::decode(&self.inner) {
-                return Some(bytes);
+                return Some((DataType::Byte, bytes));
             } else {
                 return None;
             }
@@ -82,35 +94,35 @@ impl WriteValue {
             match prefix.as_str() {
                 "B" => {
                     if let Ok(byte) = u8::from_str_radix(&value, 16) {
-                        return Some(vec![byte]);
+                        return Some((DataType::Byte, vec![byte]));
                     } else {
                         return None;
                     }
                 }
                 "W" => {
                     if let Ok(data) = u16::from_str_radix(&value, 16) {
-                        return Some(data.to_ne_bytes().to_vec());
+                        return Some((DataType::Word, data.to_ne_bytes().to_vec()));
                     } else {
                         return None;
                     }
                 }
                 "DW" => {
                     if let Ok(data) = u32::from_str_radix(&value, 16) {
-                        return Some(data.to_ne_bytes().to_vec());
+                        return Some((DataType::DoubleWord, data.to_ne_bytes().to_vec()));
                     } else {
                         return None;
                     }
                 }
                 "QW" => {
                     if let Ok(data) = u64::from_str_radix(&value, 16) {
-                        return Some(data.to_ne_bytes().to_vec());
+                        return Some((DataType::QuadWord, data.to_ne_bytes().to_vec()));
                     } else {
                         return None;
                     }
                 }
                 "DQW" => {
                     if let Ok(data) = u128::from_str_radix(&value, 16) {
-                        return Some(data.to_ne_bytes().to_vec());
+                        return Some((DataType::DoubleQuadWord, data.to_ne_bytes().to_vec()));
                     } else {
                         return None;
                     }
@@ -178,7 +190,8 @@ impl Cell {
     pub fn ascii(&self) -> String {
         match self.inner {
             Some(byte) => {
-                if byte.is_ascii() {
+                // control characters would garble the terminal
+                if byte.is_ascii_graphic() || byte == b' ' {
                     return (byte as char).to_string();
                 } else {
                     return String::from(".");
@@ -189,19 +202,191 @@ impl Cell {
     }
 }
 
+/// Number of bytes shown on one page of the hex matrix
+pub const PAGE_SIZE: u16 = 16 * 16;
+
 pub trait MatrixData {
-    fn new(size: u16) -> Self;
-    fn write(&self, offset: u64, bytes: Vec<u8>);
-    fn update(&mut self, start: u64);
+    /// Writes `bytes` to `offset` as a sequence of `data_type` sized accesses
+    fn write(&mut self, offset: u64, data_type: &DataType, bytes: Vec<u8>) -> error::Result<()>;
+    /// Reloads the page starting at `start` using `data_type` sized accesses
+    fn update(&mut self, start: u64, data_type: &DataType) -> error::Result<()>;
     fn get(&self, index: usize) -> Option<Cell>;
+    /// Whether the backend is able to access data with the width of `data_type`
+    fn supports(&self, _data_type: &DataType) -> bool {
+        true
+    }
+    /// Size of the address space, offsets at or beyond it can not be selected
+    fn limit(&self) -> Option<u64> {
+        None
+    }
+    /// Physical memory map of the address space, if offsets are physical addresses
+    fn memory_map(&self) -> Option<&MemoryMap> {
+        None
+    }
+    /// Reads exactly `len` bytes at `offset` with `data_type` sized accesses
+    /// and nothing around them, `None` if the backend can not or should not
+    /// read them, e.g. because reads have side effects
+    fn read_at(&mut self, _offset: u64, _data_type: &DataType, _len: usize) -> Option<Vec<u8>> {
+        None
+    }
+    /// Whether a search may read the `len` bytes at `offset`, reading device
+    /// registers can have side effects
+    fn scannable(&self, _offset: u64, _len: u64) -> bool {
+        true
+    }
+    /// Command line that writes `value` at `offset` with one `data_type` sized
+    /// access, used to export the write journal
+    fn write_command(&self, _offset: u64, _data_type: &DataType, _value: u64) -> Option<String> {
+        None
+    }
 }
 
 pub enum OpMode {
     Normal,
     Jump,
     Write,
+    /// list of the memory map regions
+    Regions,
+    /// waiting for the user to confirm a write
+    Confirm,
+    /// input of the file the journal is exported to
+    Export,
+    /// input of a search pattern
+    Search,
+    /// a search is scanning the address space
+    Searching,
+    /// input of a new value for the selected bitfield
+    Field,
+    /// bit view of the cell under the cursor
+    Bits,
+}
+
+/// Windows a search scans between two redraws of the matrix
+const SEARCH_STEP: usize = 256;
+
+/// Bounds of the watch interval
+const WATCH_MIN: Duration = Duration::from_millis(50);
+const WATCH_MAX: Duration = Duration::from_secs(10);
+
+/// Samples of the page taken in watch mode, the counters belong to the page
+/// and cell type of the samples
+pub struct Watch {
+    page: u64,
+    cell_type: DataType,
+    sample: Vec<Option<u8>>,
+    /// bytes that differ between the last two samples
+    changed: Vec<bool>,
+    /// number of samples in which the cell at a byte index changed
+    counts: Vec<u32>,
+    /// when the page was last read
+    last_sample: Instant,
+}
+
+impl Watch {
+    fn new(page: u64, cell_type: DataType, sample: Vec<Option<u8>>) -> Self {
+        Self {
+            page,
+            cell_type,
+            changed: vec![false; sample.len()],
+            counts: vec![0; sample.len()],
+            sample,
+            last_sample: Instant::now(),
+        }
+    }
+
+    /// Compares `sample` with the previous one, starts over if the page or
+    /// the cell type is a different one
+    fn update(&mut self, page: u64, cell_type: DataType, sample: Vec<Option<u8>>) {
+        if page != self.page || cell_type != self.cell_type || sample.len() != self.sample.len() {
+            *self = Self::new(page, cell_type, sample);
+            return;
+        }
+        for (i, changed) in self.changed.iter_mut().enumerate() {
+            *changed = sample[i] != self.sample[i];
+        }
+        let cell_size = data_size(&cell_type) as usize;
+        for (i, changed) in self.changed.chunks(cell_size).enumerate() {
+            if changed.iter().any(|c| *c) {
+                self.counts[i * cell_size] += 1;
+            }
+        }
+        self.sample = sample;
+        self.last_sample = Instant::now();
+    }
+
+    /// Whether a byte of the cell at `index` changed in the last sample
+    fn changed(&self, page: u64, index: usize, cell_size: usize) -> bool {
+        page == self.page && self.changed.iter().skip(index).take(cell_size).any(|c| *c)
+    }
+}
+
+/// Height of the inspector panel, one row per interpretation and the borders
+const INSPECTOR_HEIGHT: u16 = 22;
+
+/// Bits shown in one row of the bit view
+const BITS_PER_ROW: u32 = 16;
+
+/// The cell under the cursor, bit by bit, while its bits are toggled
+pub struct BitEdit {
+    pub offset: u64,
+    pub data_type: DataType,
+    /// value read when the bit view was opened
+    pub original: u128,
+    pub value: u128,
+    /// selected bit
+    pub bit: u32,
 }
 
+impl BitEdit {
+    fn new(offset: u64, data_type: DataType, bytes: &[u8]) -> Self {
+        let mut value = [0u8; 16];
+        value[..bytes.len()].copy_from_slice(bytes);
+        let value = u128::from_ne_bytes(value);
+        Self {
+            offset,
+            data_type,
+            original: value,
+            value,
+            bit: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        data_size(&self.data_type) as u32 * 8
+    }
+
+    pub fn is_set(&self, bit: u32) -> bool {
+        self.value >> bit & 1 == 1
+    }
+
+    fn toggle(&mut self) {
+        self.value ^= 1 << self.bit;
+    }
+
+    /// Moves the selection by `delta` bits, bits are shown with the most
+    /// significant one first
+    fn select(&mut self, delta: i64) {
+        let bit = self.bit as i64 + delta;
+        if (0..self.width() as i64).contains(&bit) {
+            self.bit = bit as u32;
+        }
+    }
+
+    /// Bytes of the value in the order `MatrixData::write` takes them
+    fn bytes(&self) -> Vec<u8> {
+        self.value.to_ne_bytes()[..data_size(&self.data_type) as usize].to_vec()
+    }
+}
+
+/// A write into a region that needs the confirmation of the user
+pub struct PendingWrite {
+    pub offset: u64,
+    pub data_type: DataType,
+    pub bytes: Vec<u8>,
+    pub target: WriteTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DataType {
     Byte,
     Word,
@@ -210,7 +395,35 @@ pub enum DataType {
     DoubleQuadWord,
 }
 
-fn data_size(cell_type: &DataType) -> u64 {
+impl FromStr for DataType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "b" | "byte" => Ok(DataType::Byte),
+            "w" | "word" => Ok(DataType::Word),
+            "d" | "dw" | "dword" => Ok(DataType::DoubleWord),
+            "q" | "qw" | "qword" => Ok(DataType::QuadWord),
+            "dq" | "dqw" | "dqword" => Ok(DataType::DoubleQuadWord),
+            _ => Err(Error::Parse(format!("invalid data type: {}", s))),
+        }
+    }
+}
+
+impl DataType {
+    /// Name accepted by `from_str` and the `--width` options of rw
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            DataType::Byte => "b",
+            DataType::Word => "w",
+            DataType::DoubleWord => "d",
+            DataType::QuadWord => "q",
+            DataType::DoubleQuadWord => "dq",
+        }
+    }
+}
+
+pub fn data_size(cell_type: &DataType) -> u64 {
     match cell_type {
         DataType::Byte => 1,
         DataType::Word => 2,
@@ -229,19 +442,70 @@ pub struct Matrix<T> {
     pub op_mode: OpMode,
     pub cell_type: DataType,
     pub input: String,
+    /// result of the last operation, shown in the status panel
+    pub message: Option<String>,
+    /// selected entry of the region list
+    pub region: usize,
+    /// write waiting for confirmation
+    pub pending: Option<PendingWrite>,
+    /// writes of this session, for undo and redo
+    pub journal: Journal,
+    /// whether the history panel is shown
+    pub show_history: bool,
+    /// running search
+    pub search: Option<Scan>,
+    /// pattern of the last search, repeated by `f` and `F`
+    pub pattern: Option<Vec<u8>>,
+    /// direction of the search whose pattern is being entered
+    pub search_forward: bool,
+    /// address and length of the last match, highlighted in the matrix
+    pub matched: Option<(u64, u64)>,
+    /// samples of the page while watch mode is on
+    pub watch: Option<Watch>,
+    /// time between two samples in watch mode
+    pub watch_interval: Duration,
+    /// names of the registers and bitfields of the address space
+    pub registers: Option<RegisterMap>,
+    /// selected bitfield of the register under the cursor
+    pub field: usize,
+    /// cell shown in the bit view
+    pub bits: Option<BitEdit>,
 }
 
 impl<T: MatrixData> Matrix<T> {
-    pub fn new(name: &str) -> Self {
+    /// Creates a matrix over `data`, which holds pages of `PAGE_SIZE` bytes
+    pub fn new(name: &str, data: T) -> Self {
         Self {
             name: String::from(name),
             col_size: 16,
             row_size: 16,
             offset: 0,
-            data: T::new(16 * 16),
+            data,
             op_mode: OpMode::Normal,
             cell_type: DataType::Byte,
             input: String::new(),
+            message: None,
+            region: 0,
+            pending: None,
+            journal: Journal::default(),
+            show_history: false,
+            search: None,
+            pattern: None,
+            search_forward: true,
+            matched: None,
+            watch: None,
+            watch_interval: Duration::from_millis(500),
+            registers: None,
+            field: 0,
+            bits: None,
+        }
+    }
+
+    /// Whether a cell at `offset` lies within the address space of the backend
+    fn in_range(&self, offset: u64) -> bool {
+        match self.data.limit() {
+            Some(limit) => offset < limit,
+            None => true,
         }
     }
 
@@ -258,10 +522,13 @@ impl<T: MatrixData> Matrix<T> {
     }
 
     pub fn next_cell(&mut self) {
+        if !self.in_range(self.offset + data_size(&self.cell_type)) {
+            return;
+        }
         self.offset += data_size(&self.cell_type);
 
         if self.page_offset() == 0 {
-            self.data.update(self.page_start());
+            self.refresh();
         }
     }
 
@@ -273,14 +540,17 @@ impl<T: MatrixData> Matrix<T> {
         }
 
         if self.page_offset() + cell_size == self.page_size() {
-            self.data.update(self.page_start())
+            self.refresh();
         }
     }
 
     pub fn next_line(&mut self) {
+        if !self.in_range(self.offset + self.col_size as u64) {
+            return;
+        }
         self.offset += self.col_size as u64;
         if self.page_offset() <= self.col_size as u64 {
-            self.data.update(self.page_start());
+            self.refresh();
         }
     }
 
@@ -289,43 +559,577 @@ impl<T: MatrixData> Matrix<T> {
             self.offset -= self.col_size as u64;
         }
         if self.page_offset() >= self.page_size() - self.col_size as u64 {
-            self.data.update(self.page_start());
+            self.refresh();
         }
     }
 
     pub fn next_page(&mut self) {
+        if !self.in_range(self.offset + self.page_size()) {
+            return;
+        }
         self.offset += self.page_size();
-        self.data.update(self.page_start());
+        self.refresh();
     }
 
     pub fn prev_page(&mut self) {
         if self.offset >= self.page_size() {
             self.offset -= self.page_size();
-            self.data.update(self.page_start());
+            self.refresh();
+        }
+    }
+
+    /// Reloads the current page with the current cell type
+    fn refresh(&mut self) {
+        let start = self.page_start();
+        if let Err(err) = self.data.update(start, &self.cell_type) {
+            self.message = Some(err.to_string());
         }
     }
 
     pub fn set_cell_type(&mut self, cell_type: DataType) {
+        if !self.data.supports(&cell_type) {
+            self.message =
+                Some(Error::Unsupported(format!("{:?} accesses", cell_type)).to_string());
+            return;
+        }
         self.cell_type = cell_type;
-        self.offset -= self.offset % data_size(&self.cell_type)
+        self.offset -= self.offset % data_size(&self.cell_type);
+        self.refresh();
     }
 
     fn jump(&mut self) {
         if let Some(addr) = JumpAddress::new(&self.input).parse(self.offset) {
-            self.offset = addr;
-            self.data.update(self.page_start());
+            if !self.in_range(addr) {
+                self.message = Some(format!("address out of range: 0x{:x}", addr));
+                self.input.clear();
+                return;
+            }
+            self.offset = addr - addr % data_size(&self.cell_type);
+            self.refresh();
             self.op_mode = OpMode::Normal;
+        } else {
+            self.message = Some(format!("invalid address: {}", self.input));
         }
         self.input.clear();
     }
 
+    /// Opens the region list at the innermost region containing the offset
+    fn open_regions(&mut self) {
+        match self.data.memory_map() {
+            Some(map) if !map.regions.is_empty() => {
+                self.region = map.index_at(self.offset).unwrap_or(0);
+                self.op_mode = OpMode::Regions;
+            }
+            _ => self.message = Some(String::from("no memory map for this address space")),
+        }
+    }
+
+    fn select_region(&mut self, forward: bool) {
+        let count = self.data.memory_map().map_or(0, |map| map.regions.len());
+        if forward {
+            self.region = (self.region + 1).min(count.saturating_sub(1));
+        } else {
+            self.region = self.region.saturating_sub(1);
+        }
+    }
+
+    /// Moves to the start of the selected region
+    fn goto_region(&mut self) {
+        let start = match self
+            .data
+            .memory_map()
+            .and_then(|map| map.regions.get(self.region))
+        {
+            Some(region) => region.start,
+            None => return,
+        };
+        if !self.in_range(start) {
+            self.message = Some(format!("address out of range: 0x{:x}", start));
+            return;
+        }
+        self.offset = start - start % data_size(&self.cell_type);
+        self.refresh();
+        self.op_mode = OpMode::Normal;
+    }
+
+    /// Starts the input of a write, unless writes are disabled
+    fn begin_write(&mut self) {
+        if let Err(err) = guard::check_writable() {
+            self.message = Some(err.to_string());
+            return;
+        }
+        self.input.clear();
+        self.op_mode = OpMode::Write;
+    }
+
+    /// Writes the input at the offset
     fn write(&mut self) {
-        if let Some(bytes) = WriteValue::new(&self.input).parse() {
-            self.data.write(self.offset, bytes);
-            self.data.update(self.page_start());
-            self.op_mode = OpMode::Normal;
+        if let Some((data_type, bytes)) = WriteValue::new(&self.input).parse() {
+            self.request_write(self.offset, data_type, bytes);
+        } else {
+            self.message = Some(format!("invalid value: {}", self.input));
+        }
+        self.input.clear();
+    }
+
+    /// Writes `bytes` at `offset`, writes into System RAM, the kernel image
+    /// or unclaimed ranges wait for a confirmation
+    fn request_write(&mut self, offset: u64, data_type: DataType, bytes: Vec<u8>) {
+        let target = self
+            .data
+            .memory_map()
+            .map(|map| WriteTarget::classify(map, offset, bytes.len() as u64));
+        match target {
+            Some(target) if target.needs_confirmation() => {
+                self.pending = Some(PendingWrite {
+                    offset,
+                    data_type,
+                    bytes,
+                    target,
+                });
+                self.op_mode = OpMode::Confirm;
+            }
+            _ => self.commit_write(offset, data_type, bytes),
+        }
+    }
+
+    /// Writes `bytes` at `offset` and records the write in the journal
+    fn commit_write(&mut self, offset: u64, data_type: DataType, bytes: Vec<u8>) {
+        let old = self.data.read_at(offset, &data_type, bytes.len());
+        match self.data.write(offset, &data_type, bytes.clone()) {
+            Ok(()) => {
+                // say so now rather than when undo is tried
+                if old.is_none() {
+                    self.message = Some(format!(
+                        "written, but can not be undone: the old value at 0x{:X} is unknown",
+                        offset
+                    ));
+                }
+                self.journal.record(JournalEntry {
+                    offset,
+                    data_type,
+                    old,
+                    new: bytes,
+                })
+            }
+            Err(err) => self.message = Some(err.to_string()),
+        }
+        self.op_mode = OpMode::Normal;
+        self.refresh();
+    }
+
+    /// Writes `bytes` of a journal entry and moves to its offset
+    fn replay(&mut self, entry: &JournalEntry, bytes: Vec<u8>) -> bool {
+        let res = self.data.write(entry.offset, &entry.data_type, bytes);
+        self.offset = entry.offset - entry.offset % data_size(&self.cell_type);
+        self.refresh();
+        match res {
+            Ok(()) => true,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Restores the bytes overwritten by the last write
+    fn undo(&mut self) {
+        let entry = match self.journal.undo_entry() {
+            Some(entry) => entry.clone(),
+            None => {
+                self.message = Some(String::from("nothing to undo"));
+                return;
+            }
+        };
+        match entry.old.clone() {
+            Some(old) => {
+                if self.replay(&entry, old) {
+                    self.journal.step_back();
+                    self.message = Some(format!("undone: {}", entry));
+                }
+            }
+            None => {
+                self.message = Some(format!(
+                    "can not undo, the old value at 0x{:X} is unknown",
+                    entry.offset
+                ))
+            }
+        }
+    }
+
+    /// Repeats the last undone write
+    fn redo(&mut self) {
+        let entry = match self.journal.redo_entry() {
+            Some(entry) => entry.clone(),
+            None => {
+                self.message = Some(String::from("nothing to redo"));
+                return;
+            }
+        };
+        if self.replay(&entry, entry.new.clone()) {
+            self.journal.step_forward();
+            self.message = Some(format!("redone: {}", entry));
+        }
+    }
+
+    /// Register that holds the byte under the cursor
+    pub fn register(&self) -> Option<&Register> {
+        self.registers.as_ref()?.register_at(self.offset)
+    }
+
+    /// Value of `register` in the loaded page, `None` if it is not completely
+    /// in the page or not readable
+    fn register_value(&self, register: &Register) -> Option<u64> {
+        let addr = self.registers.as_ref()?.address(register);
+        let index = addr.checked_sub(self.page_start())? as usize;
+        if index as u64 + register.bytes() > self.page_size() {
+            return None;
+        }
+        let mut value = [0u8; 8];
+        for (i, byte) in value.iter_mut().take(register.bytes() as usize).enumerate() {
+            *byte = self.data.get(index + i)?.inner?;
+        }
+        Some(u64::from_ne_bytes(value))
+    }
+
+    fn select_field(&mut self, forward: bool) {
+        let count = self.register().map_or(0, |r| r.fields.len());
+        self.field = match forward {
+            true => (self.field + 1).min(count.saturating_sub(1)),
+            false => self.field.min(count.saturating_sub(1)).saturating_sub(1),
+        };
+    }
+
+    /// Starts the input of a value for the selected bitfield
+    fn begin_field_edit(&mut self) {
+        if let Err(err) = guard::check_writable() {
+            self.message = Some(err.to_string());
+            return;
+        }
+        match self.register() {
+            Some(register) if register.data_type().is_none() => {
+                self.message = Some(format!(
+                    "{}: {} bit registers can not be written",
+                    register.name, register.size
+                ))
+            }
+            Some(register) if !register.fields.is_empty() => {
+                self.input.clear();
+                self.op_mode = OpMode::Field;
+            }
+            Some(register) => self.message = Some(format!("{} has no fields", register.name)),
+            None => self.message = Some(String::from("no register at the cursor")),
+        }
+    }
+
+    /// Reads the register under the cursor, replaces the selected bitfield
+    /// with the input and writes the register back
+    fn write_field(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        self.op_mode = OpMode::Normal;
+        let (addr, register) = match (&self.registers, self.register()) {
+            (Some(map), Some(register)) => (map.address(register), register.clone()),
+            _ => return,
+        };
+        let field = &register.fields[self.field.min(register.fields.len() - 1)];
+        let value = match u64::from_str_radix(input.trim_start_matches("0x"), 16) {
+            Ok(value) => value,
+            Err(_) => {
+                self.message = Some(format!("invalid value: {}", input));
+                return;
+            }
+        };
+        let data_type = match register.data_type() {
+            Some(data_type) => data_type,
+            None => {
+                self.message = Some(format!(
+                    "{}: {} bit registers can not be written",
+                    register.name, register.size
+                ));
+                return;
+            }
+        };
+        let size = register.bytes() as usize;
+        let old = self.data.read_at(addr, &data_type, size);
+        let old = match old {
+            Some(old) => {
+                let mut bytes = [0u8; 8];
+                bytes[..size].copy_from_slice(&old);
+                u64::from_ne_bytes(bytes)
+            }
+            None => {
+                self.message = Some(format!("can not read {}", register.name));
+                return;
+            }
+        };
+        match field.set(old, value) {
+            Ok(new) => {
+                self.message = Some(format!("{}.{} = 0x{:X}", register.name, field.name, value));
+                self.request_write(addr, data_type, new.to_ne_bytes()[..size].to_vec());
+            }
+            Err(err) => self.message = Some(err.to_string()),
+        }
+    }
+
+    /// `len` bytes at the cursor in the loaded page, `None` if they are not
+    /// all readable or run past the end of the page
+    fn cursor_bytes(&self, len: usize) -> Option<Vec<u8>> {
+        let index = self.page_offset() as usize;
+        if index + len > self.page_size() as usize {
+            return None;
+        }
+        (index..index + len)
+            .map(|i| self.data.get(i)?.inner)
+            .collect()
+    }
+
+    /// Moves to the address stored at the cursor in `size` bytes
+    fn follow_pointer(&mut self, size: usize) {
+        let addr = match self.cursor_bytes(size) {
+            Some(bytes) => {
+                let mut value = [0u8; 8];
+                value[..size].copy_from_slice(&bytes);
+                u64::from_le_bytes(value)
+            }
+            None => {
+                self.message = Some(format!("can not read a pointer at 0x{:X}", self.offset));
+                return;
+            }
+        };
+        if !self.in_range(addr) {
+            self.message = Some(format!("address out of range: 0x{:x}", addr));
+            return;
+        }
+        self.message = Some(format!("followed 0x{:X} -> 0x{:X}", self.offset, addr));
+        self.offset = addr - addr % data_size(&self.cell_type);
+        self.refresh();
+    }
+
+    /// Opens the bit view of the cell under the cursor
+    fn open_bits(&mut self) {
+        match self.cursor_bytes(data_size(&self.cell_type) as usize) {
+            Some(bytes) => {
+                self.bits = Some(BitEdit::new(self.offset, self.cell_type, &bytes));
+                self.op_mode = OpMode::Bits;
+            }
+            None => self.message = Some(format!("can not read 0x{:X}", self.offset)),
+        }
+    }
+
+    /// Writes the toggled bits of the bit view, unless nothing changed
+    fn write_bits(&mut self) {
+        let bits = match self.bits.take() {
+            Some(bits) => bits,
+            None => return,
+        };
+        self.op_mode = OpMode::Normal;
+        if bits.value == bits.original {
+            self.message = Some(String::from("no bits changed"));
+            return;
         }
+        if let Err(err) = guard::check_writable() {
+            self.message = Some(err.to_string());
+            return;
+        }
+        self.request_write(bits.offset, bits.data_type, bits.bytes());
+    }
+
+    fn begin_search(&mut self, forward: bool) {
         self.input.clear();
+        self.search_forward = forward;
+        self.op_mode = OpMode::Search;
+    }
+
+    /// Starts searching for the entered pattern from the offset
+    fn search(&mut self) {
+        match parse_pattern(&self.input) {
+            Ok(pattern) => self.start_scan(pattern, self.offset, self.search_forward),
+            Err(err) => self.message = Some(err.to_string()),
+        }
+        self.input.clear();
+    }
+
+    /// Searches the last pattern again, after the match under the cursor
+    /// or before it
+    fn next_match(&mut self, forward: bool) {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern.clone(),
+            None => {
+                self.message = Some(String::from("no previous search"));
+                return;
+            }
+        };
+        let cell_size = data_size(&self.cell_type);
+        let cursor = match self.matched {
+            Some((addr, _)) if self.offset == addr - addr % cell_size => addr,
+            _ => self.offset,
+        };
+        let from = if forward { cursor + 1 } else { cursor };
+        self.start_scan(pattern, from, forward);
+    }
+
+    fn start_scan(&mut self, pattern: Vec<u8>, from: u64, forward: bool) {
+        match Scan::new(
+            pattern.clone(),
+            from,
+            forward,
+            self.page_size(),
+            self.data.limit(),
+        ) {
+            Ok(scan) => {
+                self.pattern = Some(pattern);
+                self.matched = None;
+                self.search = Some(scan);
+                self.op_mode = OpMode::Searching;
+            }
+            Err(err) => {
+                self.message = Some(err.to_string());
+                self.op_mode = OpMode::Normal;
+            }
+        }
+    }
+
+    /// Scans the next windows of the running search with byte accesses
+    fn search_step(&mut self) {
+        let mut scan = match self.search.take() {
+            Some(scan) => scan,
+            None => {
+                self.op_mode = OpMode::Normal;
+                return;
+            }
+        };
+        let page_size = self.page_size() as usize;
+        let data = &mut self.data;
+        let state = scan.step(SEARCH_STEP, |addr| {
+            if !data.scannable(addr, page_size as u64) {
+                return None;
+            }
+            // unreadable windows do not match
+            let _ = data.update(addr, &DataType::Byte);
+            Some(
+                (0..page_size)
+                    .map(|i| data.get(i).and_then(|cell| cell.inner))
+                    .collect(),
+            )
+        });
+        match state {
+            ScanState::Running => {
+                self.search = Some(scan);
+                return;
+            }
+            ScanState::Found(addr) => {
+                self.matched = Some((addr, scan.pattern.len() as u64));
+                self.offset = addr - addr % data_size(&self.cell_type);
+                self.message = Some(format!("found at 0x{:X}", addr));
+            }
+            ScanState::NotFound if scan.skipped > 0 => {
+                self.message = Some(format!(
+                    "not found in 0x{:X}-0x{:X}, skipped 0x{:X} bytes of device registers",
+                    scan.lo, scan.hi, scan.skipped
+                ));
+            }
+            ScanState::NotFound => {
+                self.message = Some(format!("not found in 0x{:X}-0x{:X}", scan.lo, scan.hi));
+            }
+        }
+        self.op_mode = OpMode::Normal;
+        self.refresh();
+    }
+
+    /// Current bytes of the page as loaded by the backend
+    fn page_bytes(&self) -> Vec<Option<u8>> {
+        (0..self.page_size() as usize)
+            .map(|i| self.data.get(i).and_then(|cell| cell.inner))
+            .collect()
+    }
+
+    fn toggle_watch(&mut self) {
+        if self.watch.take().is_some() {
+            self.message = Some(String::from("watch stopped"));
+            return;
+        }
+        self.refresh();
+        self.watch = Some(Watch::new(
+            self.page_start(),
+            self.cell_type,
+            self.page_bytes(),
+        ));
+    }
+
+    /// Re-reads the page and compares it with the previous sample
+    fn sample(&mut self) {
+        self.refresh();
+        let (page, cell_type, sample) = (self.page_start(), self.cell_type, self.page_bytes());
+        if let Some(watch) = &mut self.watch {
+            watch.update(page, cell_type, sample);
+        }
+    }
+
+    /// Doubles or halves the watch interval
+    fn scale_watch_interval(&mut self, slower: bool) {
+        self.watch_interval = match slower {
+            true => (self.watch_interval * 2).min(WATCH_MAX),
+            false => (self.watch_interval / 2).max(WATCH_MIN),
+        };
+        self.message = Some(format!(
+            "watch interval {} ms",
+            self.watch_interval.as_millis()
+        ));
+    }
+
+    fn cancel_search(&mut self) {
+        if let Some(scan) = self.search.take() {
+            self.message = Some(format!("search cancelled at 0x{:X}", scan.position()));
+        }
+        self.op_mode = OpMode::Normal;
+        self.refresh();
+    }
+
+    fn begin_export(&mut self) {
+        if self.journal.applied() == 0 {
+            self.message = Some(String::from("no writes to export"));
+            return;
+        }
+        self.input = String::from("rw-journal.sh");
+        self.op_mode = OpMode::Export;
+    }
+
+    /// Saves the applied writes as an executable shell script
+    fn export(&mut self) {
+        let script = self.journal.script(&self.name, |offset, data_type, value| {
+            self.data.write_command(offset, data_type, value)
+        });
+        self.message = Some(match script {
+            Ok(script) => {
+                let res = fs::write(&self.input, script).and_then(|_| {
+                    fs::set_permissions(&self.input, fs::Permissions::from_mode(0o755))
+                });
+                match res {
+                    Ok(()) => format!(
+                        "exported {} writes to {}",
+                        self.journal.applied(),
+                        self.input
+                    ),
+                    Err(err) => format!("{}: {}", self.input, err),
+                }
+            }
+            Err(err) => format!("not exported: {}", err),
+        });
+        self.input.clear();
+        self.op_mode = OpMode::Normal;
+    }
+
+    /// Performs or drops the pending write
+    fn confirm(&mut self, confirmed: bool) {
+        match self.pending.take() {
+            Some(pending) if confirmed => {
+                self.commit_write(pending.offset, pending.data_type, pending.bytes)
+            }
+            _ => {
+                self.message = Some(String::from("write cancelled"));
+                self.op_mode = OpMode::Normal;
+            }
+        }
     }
 }
 
@@ -336,7 +1140,32 @@ pub fn start<B: Backend, T: MatrixData>(
     loop {
         terminal.draw(|f| ui(f, m))?;
 
+        if let OpMode::Searching = m.op_mode {
+            // poll instead of blocking so the scan goes on without key presses
+            if event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.code == KeyCode::Esc {
+                        m.cancel_search();
+                        continue;
+                    }
+                }
+            }
+            m.search_step();
+            continue;
+        }
+
+        // sample the page whenever an interval has passed, key presses only
+        // shorten the wait for the next event
+        if let (Some(watch), OpMode::Normal) = (&m.watch, &m.op_mode) {
+            let remaining = m.watch_interval.saturating_sub(watch.last_sample.elapsed());
+            if remaining.is_zero() || !event::poll(remaining)? {
+                m.sample();
+                continue;
+            }
+        }
+
         if let Event::Key(key) = event::read()? {
+            m.message = None;
             match m.op_mode {
                 OpMode::Normal => match key.code {
                     // Quit application
@@ -357,12 +1186,71 @@ pub fn start<B: Backend, T: MatrixData>(
                         m.input.clear();
                         m.op_mode = OpMode::Jump;
                     }
-                    KeyCode::Char('e') => {
+                    KeyCode::Char('e') => m.begin_write(),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => m.redo(),
+                    KeyCode::Char('r') => m.open_regions(),
+                    KeyCode::Char('u') => m.undo(),
+                    KeyCode::Char('H') => m.show_history = !m.show_history,
+                    KeyCode::Char('X') => m.begin_export(),
+                    KeyCode::Char('/') => m.begin_search(true),
+                    KeyCode::Char('?') => m.begin_search(false),
+                    KeyCode::Char('f') => m.next_match(true),
+                    KeyCode::Char('F') => m.next_match(false),
+                    KeyCode::Char('w') => m.toggle_watch(),
+                    KeyCode::Char('+') => m.scale_watch_interval(true),
+                    KeyCode::Char('-') => m.scale_watch_interval(false),
+                    KeyCode::Char(']') => m.select_field(true),
+                    KeyCode::Char('[') => m.select_field(false),
+                    KeyCode::Char('b') => m.begin_field_edit(),
+                    KeyCode::Char('v') => m.open_bits(),
+                    KeyCode::Char('g') => m.follow_pointer(8),
+                    KeyCode::Char('G') => m.follow_pointer(4),
+                    _ => {}
+                },
+                OpMode::Bits => {
+                    if let Some(bits) = &mut m.bits {
+                        match key.code {
+                            KeyCode::Char('h') | KeyCode::Left => bits.select(1),
+                            KeyCode::Char('l') | KeyCode::Right => bits.select(-1),
+                            KeyCode::Char('k') | KeyCode::Up => bits.select(BITS_PER_ROW as i64),
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                bits.select(-(BITS_PER_ROW as i64))
+                            }
+                            KeyCode::Char(' ') => bits.toggle(),
+                            KeyCode::Enter => m.write_bits(),
+                            KeyCode::Esc | KeyCode::Char('q') => {
+                                m.bits = None;
+                                m.op_mode = OpMode::Normal;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                OpMode::Field => match key.code {
+                    KeyCode::Char(c) => m.input.push(c),
+                    KeyCode::Backspace => {
+                        m.input.pop();
+                    }
+                    KeyCode::Enter => m.write_field(),
+                    KeyCode::Esc => {
                         m.input.clear();
-                        m.op_mode = OpMode::Write;
+                        m.op_mode = OpMode::Normal;
                     }
-                    _ => {}
+                    _ => (),
+                },
+                OpMode::Search => match key.code {
+                    KeyCode::Char(c) => m.input.push(c),
+                    KeyCode::Backspace => {
+                        m.input.pop();
+                    }
+                    KeyCode::Enter => m.search(),
+                    KeyCode::Esc => {
+                        m.input.clear();
+                        m.op_mode = OpMode::Normal;
+                    }
+                    _ => (),
                 },
+                OpMode::Searching => (),
                 OpMode::Jump => match key.code {
                     KeyCode::Char(c) => m.input.push(c),
                     KeyCode::Backspace => {
@@ -382,6 +1270,30 @@ pub fn start<B: Backend, T: MatrixData>(
 
                     _ => (),
                 },
+                OpMode::Regions => match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => m.select_region(true),
+                    KeyCode::Char('k') | KeyCode::Up => m.select_region(false),
+                    KeyCode::Enter => m.goto_region(),
+                    KeyCode::Char('q') | KeyCode::Esc => m.op_mode = OpMode::Normal,
+                    _ => (),
+                },
+                OpMode::Export => match key.code {
+                    KeyCode::Char(c) => m.input.push(c),
+                    KeyCode::Backspace => {
+                        m.input.pop();
+                    }
+                    KeyCode::Enter => m.export(),
+                    KeyCode::Esc => {
+                        m.input.clear();
+                        m.op_mode = OpMode::Normal;
+                    }
+                    _ => (),
+                },
+                OpMode::Confirm => match key.code {
+                    KeyCode::Char('y') => m.confirm(true),
+                    KeyCode::Char('n') | KeyCode::Esc => m.confirm(false),
+                    _ => (),
+                },
             }
         }
     }
@@ -402,9 +1314,11 @@ fn hex_matrix<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area:
 
     // calculate cell width based on cell size, each byte takes 2 units
     // then add 1 unit as padding
-    let col_constraints = std::iter::repeat(Constraint::Length((cell_size as u16) * 2 + 1))
+    // followed by the ASCII column
+    let mut col_constraints = std::iter::repeat(Constraint::Length((cell_size as u16) * 2 + 1))
         .take((m.col_size / cell_size as u16) as usize)
         .collect::<Vec<_>>();
+    col_constraints.push(Constraint::Length(m.col_size));
 
     // draw hex matrix
     let row_rects = Layout::default()
@@ -418,7 +1332,24 @@ fn hex_matrix<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area:
             .constraints(col_constraints.to_owned())
             .split(row_rect);
 
-        for (c, col_rect) in col_rects.into_iter().enumerate() {
+        let (ascii_rect, col_rects) = col_rects.split_last().unwrap();
+        let row_start = r * m.col_size as usize;
+        let cursor = m.page_offset() as usize;
+        let ascii: Vec<Span> = (row_start..row_start + m.col_size as usize)
+            .map(|i| {
+                let s = match m.data.get(i) {
+                    Some(cell) => cell.ascii(),
+                    None => String::from("."),
+                };
+                match (cursor..cursor + cell_size as usize).contains(&i) {
+                    true => Span::styled(s, Style::default().fg(Color::LightRed)),
+                    false => Span::raw(s),
+                }
+            })
+            .collect();
+        f.render_widget(Paragraph::new(Spans::from(ascii)), *ascii_rect);
+
+        for (c, col_rect) in col_rects.iter().enumerate() {
             let index = r * (m.col_size as usize) + c * cell_size as usize;
             let mut cell_str = String::new();
 
@@ -431,18 +1362,29 @@ fn hex_matrix<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area:
                 cell_str.push_str(s.as_str());
             }
 
-            if m.page_offset() as usize == index {
-                let cb = Paragraph::new(cell_str)
-                    .block(Block::default())
-                    .style(Style::default().fg(Color::LightRed))
-                    .alignment(Alignment::Left);
-                f.render_widget(cb, col_rect);
+            let addr = m.page_start() + index as u64;
+            let matched = match m.matched {
+                Some((start, len)) => addr < start + len && start < addr + cell_size,
+                None => false,
+            };
+            let changed = match &m.watch {
+                Some(watch) => watch.changed(m.page_start(), index, cell_size as usize),
+                None => false,
+            };
+            let style = if m.page_offset() as usize == index {
+                Style::default().fg(Color::LightRed)
+            } else if changed {
+                Style::default().fg(Color::LightGreen)
+            } else if matched {
+                Style::default().fg(Color::Yellow)
             } else {
-                let cb = Paragraph::new(cell_str)
-                    .block(Block::default())
-                    .alignment(Alignment::Left);
-                f.render_widget(cb, col_rect);
-            }
+                Style::default()
+            };
+            let cb = Paragraph::new(cell_str)
+                .block(Block::default())
+                .style(style)
+                .alignment(Alignment::Left);
+            f.render_widget(cb, *col_rect);
         }
     }
 }
@@ -451,8 +1393,79 @@ fn status<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect
     let mut content = String::new();
     content.push_str(format!("Offset:      0x{:X}\n", m.offset).as_str());
     content.push_str(format!("Page Offset: 0x{:02X}\n", m.page_offset()).as_str());
+    if let Some(watch) = &m.watch {
+        let index = m.page_offset() as usize;
+        let count = match watch.page == m.page_start() && watch.cell_type == m.cell_type {
+            true => watch.counts.get(index).copied().unwrap_or(0),
+            false => 0,
+        };
+        content.push_str(
+            format!(
+                "Watch:       every {} ms, cell changed {} times\n",
+                m.watch_interval.as_millis(),
+                count
+            )
+            .as_str(),
+        );
+    }
+    if let Some(scan) = &m.search {
+        content.push_str(
+            format!(
+                "Searching:   0x{:X} {}%, Esc cancels\n",
+                scan.position(),
+                scan.progress()
+            )
+            .as_str(),
+        );
+    }
+    if let Some(register) = m.register() {
+        let value = match m.register_value(register) {
+            Some(value) => format!("0x{:0w$X}", value, w = register.bytes() as usize * 2),
+            None => String::from("??"),
+        };
+        content.push_str(format!("Register:    {} = {}\n", register.name, value).as_str());
+        if let Some(field) = register
+            .fields
+            .get(m.field.min(register.fields.len().max(1) - 1))
+        {
+            let description = field.description.as_deref().unwrap_or("[ ] select, b edit");
+            content.push_str(
+                format!(
+                    "Field:       {} ({}): {}\n",
+                    field.name,
+                    field.bits(),
+                    description
+                )
+                .as_str(),
+            );
+        }
+    }
+    if let Some(map) = m.data.memory_map() {
+        let regions = map.regions_at(m.offset);
+        let names: Vec<&str> = regions.iter().map(|r| r.name.as_str()).collect();
+        match regions.last() {
+            Some(region) => content.push_str(
+                format!(
+                    "Region:      {} [0x{:X}-0x{:X}]\n",
+                    names.join(" > "),
+                    region.start,
+                    region.end
+                )
+                .as_str(),
+            ),
+            None => content.push_str("Region:      -\n"),
+        }
+    }
+
+    let mut text = Text::raw(content);
+    if let Some(message) = &m.message {
+        text.extend(Text::styled(
+            message.to_owned(),
+            Style::default().fg(Color::LightRed),
+        ));
+    }
 
-    let block = Paragraph::new(content)
+    let block = Paragraph::new(text)
         .block(Block::default())
         .alignment(Alignment::Left);
     f.render_widget(block, area);
@@ -500,15 +1513,342 @@ fn draw_edit<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: R
     }
 }
 
+/// Full screen list of the memory map regions, nested regions are indented
+fn draw_regions<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>) {
+    let regions = match m.data.memory_map() {
+        Some(map) => &map.regions[..],
+        None => &[],
+    };
+    let items: Vec<ListItem> = regions
+        .iter()
+        .map(|r| {
+            ListItem::new(format!(
+                "{}{:016X}-{:016X} : {}",
+                "  ".repeat(r.depth),
+                r.start,
+                r.end,
+                r.name
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(m.region));
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.size());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Memory map "))
+        .highlight_style(Style::default().fg(Color::LightRed))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut state);
+    f.render_widget(
+        Paragraph::new("j/k: move  Enter: jump to region  q/Esc: back"),
+        chunks[1],
+    );
+}
+
+fn draw_confirm<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let pending = match &m.pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    let text = format!(
+        "Write {} bytes at 0x{:X} into {}? (y/n)",
+        pending.bytes.len(),
+        pending.offset,
+        pending.target
+    );
+    let dialog = Paragraph::new(text)
+        .style(Style::default().fg(Color::LightRed))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(" Confirm "));
+    let area = centered_rect(area.width.min(40), 4, area);
+    f.render_widget(Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_search<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let title = match m.search_forward {
+        true => " Search forward ",
+        false => " Search backward ",
+    };
+    let input = Paragraph::new(m.input.as_ref())
+        .style(Style::default().fg(Color::Green))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    let area = centered_rect(area.width.min(40), 3, area);
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+    f.set_cursor(area.x + 1 + m.input.width() as u16, area.y + 1);
+}
+
+fn draw_export<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let input = Paragraph::new(m.input.as_ref())
+        .style(Style::default().fg(Color::Green))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Export journal to "),
+        );
+    let area = centered_rect(area.width.min(40), 3, area);
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+    f.set_cursor(area.x + 1 + m.input.width() as u16, area.y + 1);
+}
+
+fn draw_field<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let title = match m.register() {
+        Some(register) if !register.fields.is_empty() => {
+            let field = &register.fields[m.field.min(register.fields.len() - 1)];
+            format!(" {} bits {} ", field.name, field.bits())
+        }
+        _ => String::new(),
+    };
+    let input = Paragraph::new(m.input.as_ref())
+        .style(Style::default().fg(Color::Green))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    let area = centered_rect(area.width.min(40), 3, area);
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+    f.set_cursor(area.x + 1 + m.input.width() as u16, area.y + 1);
+}
+
+/// Formats floats without a thousand digits for tiny or huge values
+fn float<T: Copy + Into<f64> + fmt::Display + fmt::LowerExp>(value: T) -> String {
+    let abs = value.into().abs();
+    if abs == 0.0 || (1e-4..1e9).contains(&abs) {
+        format!("{}", value)
+    } else {
+        format!("{:e}", value)
+    }
+}
+
+/// The bytes at the cursor read as integers, floats and pointers, `--` if
+/// fewer bytes than the type needs are readable
+fn inspect(bytes: &[u8]) -> Vec<(String, String)> {
+    // the first `n` bytes as a little or big endian integer
+    let value = |n: usize, big_endian: bool| -> Option<u64> {
+        let mut value = [0u8; 8];
+        value[..n].copy_from_slice(bytes.get(..n)?);
+        let value = u64::from_le_bytes(value);
+        match big_endian {
+            true => Some(value.swap_bytes() >> (64 - n * 8)),
+            false => Some(value),
+        }
+    };
+    // the value of `n` bytes with the sign bit extended
+    let signed = |value: u64, n: usize| ((value << (64 - n * 8)) as i64) >> (64 - n * 8);
+    let mut rows = vec![];
+    let mut row = |name: String, text: Option<String>| {
+        rows.push((name, text.unwrap_or_else(|| String::from("--"))))
+    };
+    row(String::from("u8"), value(1, false).map(|v| v.to_string()));
+    row(
+        String::from("i8"),
+        value(1, false).map(|v| signed(v, 1).to_string()),
+    );
+    for n in [2, 4, 8] {
+        for (suffix, big_endian) in [("le", false), ("be", true)] {
+            let v = value(n, big_endian);
+            row(format!("u{} {}", n * 8, suffix), v.map(|v| v.to_string()));
+            row(
+                format!("i{} {}", n * 8, suffix),
+                v.map(|v| signed(v, n).to_string()),
+            );
+        }
+    }
+    for (suffix, big_endian) in [("le", false), ("be", true)] {
+        let v = value(4, big_endian);
+        row(
+            format!("f32 {}", suffix),
+            v.map(|v| float(f32::from_bits(v as u32))),
+        );
+    }
+    for (suffix, big_endian) in [("le", false), ("be", true)] {
+        let v = value(8, big_endian);
+        row(
+            format!("f64 {}", suffix),
+            v.map(|v| float(f64::from_bits(v))),
+        );
+    }
+    row(
+        String::from("ptr32 G"),
+        value(4, false).map(|v| format!("0x{:08X}", v)),
+    );
+    row(
+        String::from("ptr64 g"),
+        value(8, false).map(|v| format!("0x{:016X}", v)),
+    );
+    rows
+}
+
+/// Values of the bytes at the cursor
+fn inspector<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    // as many of the next eight bytes as are readable
+    let bytes = (1..=8)
+        .rev()
+        .find_map(|len| m.cursor_bytes(len))
+        .unwrap_or_default();
+    let items: Vec<ListItem> = inspect(&bytes)
+        .into_iter()
+        .map(|(name, value)| ListItem::new(format!("{:<8}{}", name, value)))
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Inspector 0x{:X} ", m.offset)),
+    );
+    f.render_widget(list, area);
+}
+
+/// Bits of the cell under the cursor, the selected bit in red and toggled
+/// ones in green, above the value in hex, decimal and binary
+fn draw_bits<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let bits = match &m.bits {
+        Some(bits) => bits,
+        None => return,
+    };
+    let width = bits.width();
+    let row_bits = BITS_PER_ROW.min(width);
+    let mut lines = vec![];
+    for row in (0..width / row_bits).rev() {
+        let bit_range = (row * row_bits..(row + 1) * row_bits).rev();
+        let numbers: Vec<Span> = bit_range
+            .clone()
+            .map(|bit| Span::styled(format!("{:>3}", bit), Style::default().fg(Color::DarkGray)))
+            .collect();
+        let values: Vec<Span> = bit_range
+            .map(|bit| {
+                let style = if bit == bits.bit {
+                    Style::default().fg(Color::LightRed)
+                } else if (bits.value ^ bits.original) >> bit & 1 == 1 {
+                    Style::default().fg(Color::LightGreen)
+                } else {
+                    Style::default()
+                };
+                Span::styled(format!("{:>3}", bits.is_set(bit) as u8), style)
+            })
+            .collect();
+        lines.push(Spans::from(numbers));
+        lines.push(Spans::from(values));
+    }
+    let digits = width as usize / 4;
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(format!(
+        "Hex: 0x{:0digits$X} (was 0x{:0digits$X})",
+        bits.value,
+        bits.original,
+        digits = digits
+    )));
+    lines.push(Spans::from(format!("Dec: {}", bits.value)));
+    // binary in lines of four bytes, most significant first
+    let bytes: Vec<String> = bits
+        .bytes()
+        .iter()
+        .rev()
+        .map(|byte| format!("{:08b}", byte))
+        .collect();
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let prefix = if i == 0 { "Bin: " } else { "     " };
+        lines.push(Spans::from(format!("{}{}", prefix, chunk.join(" "))));
+    }
+    lines.push(Spans::from(Span::styled(
+        "Space: toggle  Enter: write  Esc: cancel",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let title = format!(
+        " Bits of 0x{:X} {} ",
+        bits.offset,
+        bits.data_type.short_name()
+    );
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let dialog = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    let area = centered_rect(area.width.min(BITS_PER_ROW as u16 * 3 + 4), height, area);
+    f.render_widget(Clear, area);
+    f.render_widget(dialog, area);
+}
+
+/// Bitfields of the register under the cursor with their values
+fn bitfields<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let register = match m.register() {
+        Some(register) => register,
+        None => return,
+    };
+    let value = m.register_value(register);
+    let items: Vec<ListItem> = register
+        .fields
+        .iter()
+        .map(|field| {
+            let field_value = match value {
+                Some(value) => format!("0x{:X}", field.get(value)),
+                None => String::from("??"),
+            };
+            ListItem::new(format!(
+                "{:>5} {} = {}",
+                field.bits(),
+                field.name,
+                field_value
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    if !items.is_empty() {
+        state.select(Some(m.field.min(items.len() - 1)));
+    }
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {} ", register.name)),
+        )
+        .highlight_style(Style::default().fg(Color::LightRed))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Writes of the session, the newest first and undone ones dimmed
+fn history<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>, area: Rect) {
+    let applied = m.journal.applied();
+    let items: Vec<ListItem> = m
+        .journal
+        .entries()
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, entry)| {
+            let item = ListItem::new(entry.to_string());
+            if i < applied {
+                item
+            } else {
+                item.style(Style::default().fg(Color::DarkGray))
+            }
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" History: u undo, C-r redo, X export "),
+    );
+    f.render_widget(list, area);
+}
+
 fn ui<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>) {
+    if let OpMode::Regions = m.op_mode {
+        draw_regions(f, m);
+        return;
+    }
     let size = f.size();
     let cell_size = data_size(&m.cell_type);
-    let matrix_width = ((cell_size * 2 + 1) as u16) * (m.col_size / cell_size as u16) + 3;
+    // cells, the ASCII column and the margins
+    let matrix_width =
+        ((cell_size * 2 + 1) as u16) * (m.col_size / cell_size as u16) + m.col_size + 4;
     let matrix_height = m.row_size;
-    let padding_left = (size.width - matrix_width - 2) / 2;
+    let padding_left = size.width.saturating_sub(matrix_width + 2) / 2;
     let padding_top = 1;
 
-    let chunks = Layout::default()
+    let columns = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
             Constraint::Length(padding_left),
@@ -517,6 +1857,32 @@ fn ui<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>) {
         ])
         .split(size);
 
+    // the inspector needs room for a 64-bit value, the history panel takes
+    // what is left
+    let show_inspector = columns[2].width >= 33;
+    let right = Layout::default()
+        .margin(2)
+        .constraints([
+            Constraint::Length(if show_inspector { INSPECTOR_HEIGHT } else { 0 }),
+            Constraint::Min(0),
+        ])
+        .split(columns[2]);
+    if show_inspector {
+        inspector(f, m, right[0]);
+    }
+    if m.show_history {
+        history(f, m, right[1]);
+    }
+
+    // the bitfield panel needs room for a field name and its value
+    if m.registers.is_some() && padding_left >= 24 {
+        let area = Layout::default()
+            .margin(2)
+            .constraints([Constraint::Min(0)])
+            .split(columns[0])[0];
+        bitfields(f, m, area);
+    }
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
@@ -527,7 +1893,7 @@ fn ui<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>) {
             Constraint::Length(1),
             Constraint::Min(0),
         ])
-        .split(chunks[1]);
+        .split(columns[1]);
     let header_chunk = chunks[1];
     let hex_chunk = chunks[2];
     let status_chunk = chunks[4];
@@ -543,6 +1909,82 @@ fn ui<B: Backend, T: MatrixData>(f: &mut Frame<B>, m: &Matrix<T>) {
         OpMode::Write => {
             draw_edit(f, m, hex_chunk);
         }
+        OpMode::Confirm => {
+            draw_confirm(f, m, hex_chunk);
+        }
+        OpMode::Export => {
+            draw_export(f, m, hex_chunk);
+        }
+        OpMode::Search => {
+            draw_search(f, m, hex_chunk);
+        }
+        OpMode::Field => {
+            draw_field(f, m, hex_chunk);
+        }
+        // up to 128 bits do not fit over the matrix
+        OpMode::Bits => {
+            draw_bits(f, m, size);
+        }
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect() {
+        let rows = inspect(&[0x00, 0x00, 0x80, 0xbf, 0xff, 0xff, 0xff, 0xff]);
+        let row = |name: &str| {
+            rows.iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+                .unwrap()
+        };
+        assert_eq!(rows.len(), 20);
+        assert_eq!(row("u16 be"), "0");
+        assert_eq!(row("i32 le"), "-1082130432");
+        assert_eq!(row("u32 be"), "32959");
+        assert_eq!(row("f32 le"), "-1");
+        assert_eq!(row("i64 le"), "-1082130432");
+        assert_eq!(row("ptr64 g"), "0xFFFFFFFFBF800000");
+
+        let rows = inspect(&[0xfe, 0x01]);
+        assert_eq!(rows[1], (String::from("i8"), String::from("-2")));
+        assert_eq!(rows[2], (String::from("u16 le"), String::from("510")));
+        assert_eq!(rows[6].1, "--");
+    }
+
+    #[test]
+    fn test_bit_edit() {
+        let mut bits = BitEdit::new(0x10, DataType::Word, &[0x40, 0x00]);
+        assert_eq!(bits.width(), 16);
+        bits.select(-1);
+        assert_eq!(bits.bit, 0);
+        bits.toggle();
+        bits.select(15);
+        bits.toggle();
+        // past the most significant bit
+        bits.select(1);
+        assert_eq!(bits.bit, 15);
+        assert_eq!(bits.value, 0x8041);
+        assert!(bits.is_set(6));
+        assert_eq!(bits.bytes(), [0x41, 0x80]);
+    }
+
+    #[test]
+    fn test_watch_counts() {
+        let sample = |bytes: [u8; 4]| bytes.iter().map(|b| Some(*b)).collect::<Vec<_>>();
+        let mut watch = Watch::new(0, DataType::Word, sample([0, 0, 0, 0]));
+        watch.update(0, DataType::Word, sample([1, 1, 0, 0]));
+        watch.update(0, DataType::Word, sample([1, 1, 0, 2]));
+        assert_eq!(watch.counts, [1, 0, 1, 0]);
+        assert!(watch.changed(0, 2, 2));
+        assert!(!watch.changed(0, 0, 2));
+        // another page starts over
+        watch.update(0x100, DataType::Word, sample([1, 1, 0, 3]));
+        assert_eq!(watch.counts, [0, 0, 0, 0]);
+        assert!(!watch.changed(0, 2, 2));
+    }
+}
@@ -0,0 +1,220 @@
+//! Physical memory map from `/proc/iomem` and the firmware provided map in
+//! `/sys/firmware/memmap`
+
+use super::error::{Error, Result};
+use serde::Serialize;
+use std::fs;
+use std::io;
+
+const IOMEM_PATH: &str = "/proc/iomem";
+const FIRMWARE_MEMMAP_DIR: &str = "/sys/firmware/memmap";
+
+/// Top level regions that are memory rather than device registers, reading
+/// them has no side effects
+const MEMORY_REGIONS: &[&str] = &[
+    "System RAM",
+    "ACPI Tables",
+    "ACPI Non-volatile Storage",
+    "System ROM",
+    "Video ROM",
+    "Adapter ROM",
+];
+
+/// A range of physical addresses claimed by `name`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Region {
+    pub start: u64,
+    /// last address of the region
+    pub end: u64,
+    pub name: String,
+    /// nesting level, regions at depth 0 are top level
+    pub depth: usize,
+}
+
+impl Region {
+    pub fn contains(&self, addr: u64) -> bool {
+        self.start <= addr && addr <= self.end
+    }
+}
+
+/// Regions in the order of `/proc/iomem`, every region is followed by the
+/// regions nested in it
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryMap {
+    pub regions: Vec<Region>,
+}
+
+fn read_file(path: &str) -> Result<String> {
+    fs::read_to_string(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(String::from(path)),
+        io::ErrorKind::PermissionDenied => {
+            Error::PermissionDenied(format!("{} requires root", path))
+        }
+        _ => Error::Io(err),
+    })
+}
+
+fn parse_range(range: &str) -> Option<(u64, u64)> {
+    let (start, end) = range.split_once('-')?;
+    Some((
+        u64::from_str_radix(start.trim(), 16).ok()?,
+        u64::from_str_radix(end.trim(), 16).ok()?,
+    ))
+}
+
+impl MemoryMap {
+    /// Parses the content of `/proc/iomem`, nested regions are indented by
+    /// two spaces per level
+    pub fn parse_iomem(content: &str) -> Result<Self> {
+        let mut regions = vec![];
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            let indent = line.len() - line.trim_start().len();
+            let (range, name) = line
+                .trim_start()
+                .split_once(" : ")
+                .ok_or_else(|| Error::Parse(format!("invalid iomem line: {}", line)))?;
+            let (start, end) = parse_range(range)
+                .ok_or_else(|| Error::Parse(format!("invalid iomem range: {}", range)))?;
+            regions.push(Region {
+                start,
+                end,
+                name: String::from(name.trim()),
+                depth: indent / 2,
+            });
+        }
+        Ok(Self { regions })
+    }
+
+    /// Reads `/proc/iomem`, the kernel shows all addresses as zero to
+    /// users without CAP_SYS_ADMIN
+    pub fn iomem() -> Result<Self> {
+        let map = Self::parse_iomem(&read_file(IOMEM_PATH)?)?;
+        if map.regions.iter().all(|r| r.start == 0 && r.end == 0) {
+            return Err(Error::PermissionDenied(format!(
+                "{} addresses require root",
+                IOMEM_PATH
+            )));
+        }
+        Ok(map)
+    }
+
+    /// Reads the map the firmware handed to the kernel at boot, e.g. the
+    /// E820 table, which has no nested regions
+    pub fn firmware() -> Result<Self> {
+        let entries = fs::read_dir(FIRMWARE_MEMMAP_DIR).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(String::from(FIRMWARE_MEMMAP_DIR)),
+            _ => Error::from(err),
+        })?;
+        let mut regions = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            let field = |name: &str| -> Result<String> {
+                Ok(fs::read_to_string(path.join(name))?.trim().to_string())
+            };
+            let addr = |name: &str| -> Result<u64> {
+                let value = field(name)?;
+                u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .map_err(|_| Error::Parse(format!("invalid address: {}", value)))
+            };
+            regions.push(Region {
+                start: addr("start")?,
+                end: addr("end")?,
+                name: field("type")?,
+                depth: 0,
+            });
+        }
+        regions.sort_by_key(|r| r.start);
+        Ok(Self { regions })
+    }
+
+    /// Reads `/proc/iomem`, or the firmware map if the addresses in
+    /// `/proc/iomem` are hidden
+    pub fn load() -> Result<Self> {
+        Self::iomem().or_else(|_| Self::firmware())
+    }
+
+    /// Regions containing `addr`, outermost first
+    pub fn regions_at(&self, addr: u64) -> Vec<&Region> {
+        let mut chain: Vec<&Region> = vec![];
+        for region in &self.regions {
+            if region.depth > chain.len() || !region.contains(addr) {
+                continue;
+            }
+            // a sibling of an enclosing region replaces it and its children
+            chain.truncate(region.depth);
+            chain.push(region);
+        }
+        chain
+    }
+
+    /// Whether every byte of the `len` bytes at `addr` lies in System RAM or
+    /// a firmware range
+    pub fn is_memory(&self, addr: u64, len: u64) -> bool {
+        let last = match addr.checked_add(len.max(1) - 1) {
+            Some(last) => last,
+            None => return false,
+        };
+        let mut cur = addr;
+        loop {
+            let region = self.regions.iter().find(|r| {
+                r.depth == 0 && r.contains(cur) && MEMORY_REGIONS.contains(&r.name.as_str())
+            });
+            match region {
+                Some(region) if region.end >= last => return true,
+                Some(region) => cur = region.end + 1,
+                None => return false,
+            }
+        }
+    }
+
+    /// Index of the innermost region containing `addr`
+    pub fn index_at(&self, addr: u64) -> Option<usize> {
+        let innermost = *self.regions_at(addr).last()?;
+        self.regions.iter().position(|r| std::ptr::eq(r, innermost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_at() {
+        let map = MemoryMap::parse_iomem(
+            "00000000-00000fff : Reserved\n\
+             00100000-bfffffff : System RAM\n\
+             \x20 01000000-021352a7 : Kernel code\n\
+             eec00000-febfffff : Reserved\n\
+             \x20 eec00000-eecfffff : PCI ECAM 0000 [bus 00-00]\n\
+             \x20   eec00000-eecfffff : PCI Bus 0000:00\n\
+             4000000000-7fffffffff : PCI Bus 0000:00\n\
+             \x20 4000000000-400007ffff : 0000:00:01.0\n\
+             \x20   4000000000-400007ffff : virtio-pci-modern\n",
+        )
+        .unwrap();
+        assert_eq!(map.regions.len(), 9);
+        assert_eq!(map.regions[5].depth, 2);
+        assert_eq!(map.regions[4].name, "PCI ECAM 0000 [bus 00-00]");
+
+        let names = |addr| -> Vec<&str> {
+            map.regions_at(addr)
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect()
+        };
+        assert_eq!(names(0x1000000), ["System RAM", "Kernel code"]);
+        assert_eq!(names(0x3000000), ["System RAM"]);
+        assert_eq!(
+            names(0x40_0000_1000),
+            ["PCI Bus 0000:00", "0000:00:01.0", "virtio-pci-modern"]
+        );
+        assert!(names(0x2000).is_empty());
+        assert_eq!(map.index_at(0xeec00010), Some(5));
+
+        assert!(map.is_memory(0x1000000, 0x1000));
+        // the end of System RAM
+        assert!(!map.is_memory(0xbffffff0, 0x20));
+        assert!(!map.is_memory(0xeec00000, 4));
+        assert!(MemoryMap::parse_iomem("00000000 Reserved").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Model-specific register access through the `/dev/cpu/<n>/msr` nodes of the
+//! msr kernel module, plus a TUI table comparing one MSR across all CPUs
+
+use super::error::{Error, Result};
+use super::guard;
+use super::matrix::centered_rect;
+use crossterm::event::{self, Event, KeyCode};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState};
+use tui::Terminal;
+use unicode_width::UnicodeWidthStr;
+
+const CPU_ONLINE: &str = "/sys/devices/system/cpu/online";
+
+/// Parse a kernel cpu list such as `0-3,8,10-11`
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = vec![];
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<usize>().ok()?..=last.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// Online CPUs
+pub fn cpus() -> Result<Vec<usize>> {
+    let list = fs::read_to_string(CPU_ONLINE)?;
+    parse_cpu_list(&list).ok_or_else(|| Error::Parse(format!("{}: {}", CPU_ONLINE, list.trim())))
+}
+
+fn msr_path(cpu: usize) -> String {
+    format!("/dev/cpu/{}/msr", cpu)
+}
+
+/// Maps errors of the msr device nodes to what went wrong from the user's view
+fn msr_error(err: io::Error, cpu: usize, reg: u32) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => Error::Unsupported(format!(
+            "{} is missing, load the msr kernel module with modprobe msr",
+            msr_path(cpu)
+        )),
+        io::ErrorKind::PermissionDenied => {
+            Error::PermissionDenied(format!("{} requires root", msr_path(cpu)))
+        }
+        // the kernel reports a #GP on rdmsr/wrmsr as EIO
+        _ if err.raw_os_error() == Some(libc::EIO) => {
+            Error::Unsupported(format!("MSR 0x{:x} on cpu {}", reg, cpu))
+        }
+        _ => Error::Io(err),
+    }
+}
+
+fn open(cpu: usize, reg: u32, write: bool) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .open(msr_path(cpu))
+        .map_err(|err| msr_error(err, cpu, reg))
+}
+
+/// Reads MSR `reg` on `cpu`
+pub fn read(cpu: usize, reg: u32) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    open(cpu, reg, false)?
+        .read_exact_at(&mut buf, reg as u64)
+        .map_err(|err| msr_error(err, cpu, reg))?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Writes `value` to MSR `reg` on `cpu`
+pub fn write(cpu: usize, reg: u32, value: u64) -> Result<()> {
+    guard::check_writable()?;
+    open(cpu, reg, true)?
+        .write_all_at(&value.to_le_bytes(), reg as u64)
+        .map_err(|err| msr_error(err, cpu, reg))
+}
+
+/// Value of an MSR on one CPU
+#[derive(Debug, Clone, Serialize)]
+pub struct MsrValue {
+    pub cpu: usize,
+    pub value: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Reads MSR `reg` on every online CPU, fails only if the msr nodes are
+/// missing or inaccessible
+pub fn read_all(reg: u32) -> Result<Vec<MsrValue>> {
+    let mut values = vec![];
+    for cpu in cpus()? {
+        values.push(match read(cpu, reg) {
+            Ok(value) => MsrValue {
+                cpu,
+                value: Some(value),
+                error: None,
+            },
+            Err(err @ Error::PermissionDenied(_)) => return Err(err),
+            Err(err) if !Path::new(&msr_path(cpu)).exists() => return Err(err),
+            Err(err) => MsrValue {
+                cpu,
+                value: None,
+                error: Some(err.to_string()),
+            },
+        });
+    }
+    Ok(values)
+}
+
+/// Writes `value` to MSR `reg` on every online CPU
+pub fn write_all(reg: u32, value: u64) -> Result<()> {
+    for cpu in cpus()? {
+        write(cpu, reg, value)?;
+    }
+    Ok(())
+}
+
+enum TableMode {
+    Normal,
+    Jump,
+    Write { all: bool },
+}
+
+/// One MSR across all CPUs
+pub struct MsrTable {
+    pub reg: u32,
+    pub values: Vec<MsrValue>,
+    mode: TableMode,
+    input: String,
+    state: TableState,
+    message: Option<String>,
+}
+
+impl MsrTable {
+    pub fn new(reg: u32) -> Result<Self> {
+        let mut state = TableState::default();
+        state.select(Some(0));
+        Ok(Self {
+            reg,
+            values: read_all(reg)?,
+            mode: TableMode::Normal,
+            input: String::new(),
+            state,
+            message: None,
+        })
+    }
+
+    fn refresh(&mut self) {
+        match read_all(self.reg) {
+            Ok(values) => self.values = values,
+            Err(err) => self.message = Some(err.to_string()),
+        }
+    }
+
+    fn set_reg(&mut self, reg: u32) {
+        self.reg = reg;
+        self.refresh();
+    }
+
+    fn selected_cpu(&self) -> Option<usize> {
+        Some(self.values.get(self.state.selected()?)?.cpu)
+    }
+
+    fn submit(&mut self) {
+        let input = self.input.trim_start_matches("0x");
+        match self.mode {
+            TableMode::Jump => match u32::from_str_radix(input, 16) {
+                Ok(reg) => self.set_reg(reg),
+                Err(_) => self.message = Some(format!("invalid register: {}", self.input)),
+            },
+            TableMode::Write { all } => match u64::from_str_radix(input, 16) {
+                Ok(value) => {
+                    let res = match (all, self.selected_cpu()) {
+                        (true, _) => write_all(self.reg, value),
+                        (false, Some(cpu)) => write(cpu, self.reg, value),
+                        (false, None) => Ok(()),
+                    };
+                    if let Err(err) = res {
+                        self.message = Some(err.to_string());
+                    }
+                    self.refresh();
+                }
+                Err(_) => self.message = Some(format!("invalid value: {}", self.input)),
+            },
+            TableMode::Normal => {}
+        }
+        self.input.clear();
+        self.mode = TableMode::Normal;
+    }
+}
+
+fn draw<B: Backend>(terminal: &mut Terminal<B>, t: &mut MsrTable) -> io::Result<()> {
+    // values that differ from the first CPU stand out
+    let reference = t.values.first().and_then(|v| v.value);
+    let rows: Vec<Row> = t
+        .values
+        .iter()
+        .map(|v| {
+            let (text, style) = match (v.value, &v.error) {
+                (Some(value), _) if Some(value) != reference => (
+                    format!("0x{:016X}", value),
+                    Style::default().fg(Color::LightRed),
+                ),
+                (Some(value), _) => (format!("0x{:016X}", value), Style::default()),
+                (None, error) => (
+                    error.clone().unwrap_or_default(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            };
+            Row::new(vec![
+                Cell::from(format!("CPU {}", v.cpu)),
+                Cell::from(text).style(style),
+            ])
+        })
+        .collect();
+    let title = format!(" MSR 0x{:X} ", t.reg);
+    let help = match &t.message {
+        Some(message) => message.to_owned(),
+        None => String::from(
+            "n/p: next/prev MSR  J: jump  e: write CPU  E: write all  r: refresh  q: quit",
+        ),
+    };
+    let prompt = match t.mode {
+        TableMode::Normal => None,
+        TableMode::Jump => Some("MSR"),
+        TableMode::Write { all: false } => Some("value"),
+        TableMode::Write { all: true } => Some("value (all CPUs)"),
+    };
+    let input = t.input.clone();
+
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let table = Table::new(rows)
+            .header(Row::new(vec!["CPU", "Value"]).style(Style::default().fg(Color::Yellow)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .widths(&[Constraint::Length(10), Constraint::Min(20)])
+            .highlight_symbol("> ");
+        f.render_stateful_widget(table, chunks[0], &mut t.state);
+        f.render_widget(Paragraph::new(help), chunks[1]);
+
+        if let Some(prompt) = prompt {
+            let area = centered_rect(30, 3, chunks[0]);
+            let popup = Paragraph::new(input.as_ref())
+                .style(Style::default().fg(Color::Green))
+                .block(Block::default().borders(Borders::ALL).title(prompt));
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+            f.set_cursor(area.x + 1 + input.width() as u16, area.y + 1);
+        }
+    })?;
+    Ok(())
+}
+
+/// Runs the MSR table until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, t: &mut MsrTable) -> io::Result<()> {
+    loop {
+        draw(terminal, t)?;
+
+        if let Event::Key(key) = event::read()? {
+            t.message = None;
+            match t.mode {
+                TableMode::Normal => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        let i = t.state.selected().unwrap_or(0);
+                        t.state
+                            .select(Some((i + 1).min(t.values.len().saturating_sub(1))));
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        let i = t.state.selected().unwrap_or(0);
+                        t.state.select(Some(i.saturating_sub(1)));
+                    }
+                    KeyCode::Char('n') | KeyCode::PageDown => t.set_reg(t.reg.wrapping_add(1)),
+                    KeyCode::Char('p') | KeyCode::PageUp => t.set_reg(t.reg.wrapping_sub(1)),
+                    KeyCode::Char('r') => t.refresh(),
+                    KeyCode::Char('J') => t.mode = TableMode::Jump,
+                    KeyCode::Char('e') => t.mode = TableMode::Write { all: false },
+                    KeyCode::Char('E') => t.mode = TableMode::Write { all: true },
+                    _ => {}
+                },
+                _ => match key.code {
+                    KeyCode::Char(c) => t.input.push(c),
+                    KeyCode::Backspace => {
+                        t.input.pop();
+                    }
+                    KeyCode::Enter => t.submit(),
+                    KeyCode::Esc => {
+                        t.input.clear();
+                        t.mode = TableMode::Normal;
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0\n"), Some(vec![0]));
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list("0-x"), None);
+    }
+}
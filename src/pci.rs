@@ -1,10 +1,23 @@
+pub mod bar;
+pub mod caps;
+pub mod config;
+pub mod link;
+
+use crate::error::{Error, Result};
+use crate::guard;
+use crate::matrix::{data_size, Cell, DataType, MatrixData};
+use caps::Capability;
+use config::ConfigHeader;
 use log::error;
 use pciid_parser::Database;
-use std::fs;
+use serde::Serialize;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
 
 const SYS_PCI_DEVICE_ROOT: &str = "/sys/bus/pci/devices";
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct PciDevBasicInfo {
     pub vendor: (String, Option<String>),
     pub device: (String, Option<String>),
@@ -14,7 +27,21 @@ pub struct PciDevBasicInfo {
     pub revision: String,
 }
 
-#[derive(Default, Debug)]
+impl PciDevBasicInfo {
+    /// One line description of the device, e.g.
+    /// `Network controller: Intel Corporation Wi-Fi 6 AX201 (rev 20)`
+    pub fn description(&self) -> String {
+        format!(
+            "{}: {} {} (rev {})",
+            self.class.1.as_ref().unwrap_or(&self.class.0),
+            self.vendor.1.as_ref().unwrap_or(&self.vendor.0),
+            self.device.1.as_ref().unwrap_or(&self.device.0),
+            self.revision,
+        )
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct PciDevice {
     pub domain: u8,
     pub bus: u8,
@@ -22,6 +49,16 @@ pub struct PciDevice {
     pub function: u8,
 }
 
+impl fmt::Display for PciDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:02x}:{:02x}.{:01x}",
+            self.domain, self.bus, self.device, self.function
+        )
+    }
+}
+
 impl PciDevice {
     pub fn new(domain: u8, bus: u8, device: u8, function: u8) -> Self {
         Self {
@@ -70,12 +107,22 @@ impl PciDevice {
         Some(pd)
     }
 
+    /// Parse a device address given as `dddd:bb:dd.f` or `bb:dd.f`
+    pub fn from_bdf(bdf: &str) -> Option<Self> {
+        if bdf.len() == 7 {
+            return Self::from_sysfs_dirname(&format!("0000:{}", bdf));
+        }
+        Self::from_sysfs_dirname(bdf)
+    }
+
+    /// Path of the device directory in sysfs
+    pub fn sysfs_dir(&self) -> String {
+        format!("{}/{}", SYS_PCI_DEVICE_ROOT, self)
+    }
+
     /// Get PCI device configuration data by reading the sysfs config node
     pub fn config_data(&self) -> Option<Vec<u8>> {
-        let config_sys_node = format!(
-            "{}/{:04x}:{:02x}:{:02x}.{:01x}/config",
-            SYS_PCI_DEVICE_ROOT, self.domain, self.bus, self.device, self.function
-        );
+        let config_sys_node = self.config_path();
 
         match fs::read(&config_sys_node) {
             Ok(data) => Some(data),
@@ -83,86 +130,325 @@ impl PciDevice {
         }
     }
 
+    fn config_path(&self) -> String {
+        format!("{}/config", self.sysfs_dir())
+    }
+
+    /// Checks a `data_type` sized access at `offset` against the configuration space
+    /// exposed by sysfs, which is 256 bytes for PCI and 4 KiB for PCIe functions
+    fn check_config_access(&self, offset: u64, data_type: &DataType) -> Result<usize> {
+        let size = data_size(data_type);
+        if size > 4 {
+            return Err(Error::Unsupported(format!(
+                "{}-bit configuration space accesses",
+                size * 8
+            )));
+        }
+        if offset & (size - 1) != 0 {
+            return Err(Error::Alignment {
+                offset,
+                width: size,
+            });
+        }
+        let len = fs::metadata(self.config_path())
+            .map_err(|_| Error::NotFound(format!("device {}", self)))?
+            .len();
+        if offset.checked_add(size).is_none_or(|end| end > len) {
+            return Err(Error::Unmapped {
+                offset,
+                length: size,
+            });
+        }
+        Ok(size as usize)
+    }
+
+    /// Reads a single value from configuration space, the kernel performs a
+    /// config cycle of the same width for naturally aligned 1, 2 and 4 byte reads
+    pub fn read_config(&self, offset: u64, data_type: &DataType) -> Result<u64> {
+        let size = self.check_config_access(offset, data_type)?;
+        read_config_at(&File::open(self.config_path())?, offset, size)
+    }
+
+    /// Writes a single value to configuration space with one pwrite of the
+    /// access width, so the device sees a config write of exactly that width
+    pub fn write_config(&self, offset: u64, data_type: &DataType, value: u64) -> Result<()> {
+        guard::check_writable()?;
+        let size = self.check_config_access(offset, data_type)?;
+        if size < 8 && value >> (size * 8) != 0 {
+            return Err(Error::Parse(format!(
+                "0x{:x} does not fit in {} bits",
+                value,
+                size * 8
+            )));
+        }
+        let file = OpenOptions::new().write(true).open(self.config_path())?;
+        if file.write_at(&value.to_le_bytes()[..size], offset)? != size {
+            return Err(Error::Unmapped {
+                offset,
+                length: size as u64,
+            });
+        }
+        Ok(())
+    }
+
+    /// Name of the kernel driver bound to the device
+    pub fn driver(&self) -> Option<String> {
+        let link = fs::read_link(format!("{}/driver", self.sysfs_dir())).ok()?;
+        Some(link.file_name()?.to_str()?.to_string())
+    }
+
+    /// The bridge the device sits behind, derived from the sysfs device hierarchy
+    pub fn parent(&self) -> Option<PciDevice> {
+        let path = fs::canonicalize(self.sysfs_dir()).ok()?;
+        let parent = path.parent()?.file_name()?.to_str()?;
+        Self::from_sysfs_dirname(parent)
+    }
+
+    /// Address ranges the kernel assigned to the BARs and the expansion ROM,
+    /// read from the sysfs resource node as `(start, end, flags)`
+    pub fn resources(&self) -> Vec<(u64, u64, u64)> {
+        let content = match fs::read_to_string(format!("{}/resource", self.sysfs_dir())) {
+            Ok(content) => content,
+            Err(_) => return vec![],
+        };
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line
+                    .split_whitespace()
+                    .map(|f| u64::from_str_radix(f.trim_start_matches("0x"), 16).ok());
+                Some((fields.next()??, fields.next()??, fields.next()??))
+            })
+            .collect()
+    }
+
+    /// Decode the standard configuration header, BAR and expansion ROM sizes
+    /// are taken from the kernel's resource assignment
+    pub fn decoded_config(&self) -> Result<ConfigHeader> {
+        let data = self
+            .config_data()
+            .ok_or_else(|| Error::NotFound(format!("device {}", self)))?;
+        let mut header = ConfigHeader::parse(&data)?;
+        let resources = self.resources();
+        let size = |index: usize| match resources.get(index) {
+            Some((start, end, _)) if *end > *start => Some(end - start + 1),
+            _ => None,
+        };
+        for bar in header.bars.iter_mut() {
+            bar.size = size(bar.index);
+        }
+        if let Some(rom) = header.expansion_rom.as_mut() {
+            rom.size = size(6);
+        }
+        Ok(header)
+    }
+
+    /// Standard and extended capabilities, reading beyond the first 64 bytes
+    /// of configuration space requires root
+    pub fn capabilities(&self) -> Result<Vec<Capability>> {
+        let data = self
+            .config_data()
+            .ok_or_else(|| Error::NotFound(format!("device {}", self)))?;
+        Ok(caps::all_capabilities(&data))
+    }
+
+    /// Secondary and subordinate bus numbers if the device is a PCI bridge
+    pub fn bus_range(&self) -> Option<(u8, u8)> {
+        let config = self.config_data()?;
+        if config.len() < 0x1b || config[0x0e] & 0x7f != 1 {
+            return None;
+        }
+        Some((config[0x19], config[0x1a]))
+    }
+
     /// Read data from sysfs nodes and parse basic PCI device information
     pub fn basic_info(&self) -> Option<PciDevBasicInfo> {
-        let mut info = PciDevBasicInfo::default();
-        let sys_dev_dir = format!(
-            "{}/{:04x}:{:02x}:{:02x}.{:01x}",
-            SYS_PCI_DEVICE_ROOT, self.domain, self.bus, self.device, self.function
-        );
-        let vendor_node = format!("{}/vendor", sys_dev_dir);
-        let device_node = format!("{}/device", sys_dev_dir);
-        let class_node = format!("{}/class", sys_dev_dir);
-        let revision_node = format!("{}/revision", sys_dev_dir);
-
-        // read pci-id database
+        // the pci-ids database is optional, ids are shown without names if it is missing
         let db = Database::read();
         if db.is_err() {
             error!("fail to read pci-ids database");
+        }
+        self.read_basic_info(db.as_ref().ok())
+    }
+
+    fn read_basic_info(&self, db: Option<&Database>) -> Option<PciDevBasicInfo> {
+        let mut info = PciDevBasicInfo::default();
+        let sys_dev_dir = self.sysfs_dir();
+        let read_id = |node: &str| -> Option<String> {
+            let id = fs::read_to_string(format!("{}/{}", sys_dev_dir, node)).ok()?;
+            Some(id.trim().strip_prefix("0x")?.to_string())
+        };
+
+        let vendor_id = read_id("vendor")?;
+        let device_id = read_id("device")?;
+        let class_id = read_id("class")?;
+        if class_id.len() != 6 {
             return None;
         }
-        let db = db.unwrap();
-
-        let vendor_id = fs::read_to_string(vendor_node)
-            .unwrap()
-            .trim()
-            .strip_prefix("0x")
-            .unwrap()
-            .to_string();
-        let device_id = fs::read_to_string(device_node)
-            .unwrap()
-            .trim()
-            .strip_prefix("0x")
-            .unwrap()
-            .to_string();
-        let class_id = fs::read_to_string(class_node)
-            .unwrap()
-            .trim()
-            .strip_prefix("0x")
-            .unwrap()
-            .to_string();
-
-        if let Some(vendor) = db.vendors.get(&vendor_id) {
-            info.vendor = (vendor_id, Some(vendor.name.to_owned()));
-            if let Some(device) = vendor.devices.get(&device_id) {
-                info.device = (device_id, Some(device.name.to_owned()));
-            } else {
-                info.device = (device_id, None);
+
+        info.vendor = (vendor_id.clone(), None);
+        info.device = (device_id.clone(), None);
+        info.class = (class_id[0..2].to_string(), None);
+        info.sub_class = (class_id[2..4].to_string(), None);
+        info.prog_if = (class_id[4..6].to_string(), None);
+
+        if let Some(db) = db {
+            if let Some(vendor) = db.vendors.get(&vendor_id) {
+                info.vendor.1 = Some(vendor.name.to_owned());
+                if let Some(device) = vendor.devices.get(&device_id) {
+                    info.device.1 = Some(device.name.to_owned());
+                }
             }
-        } else {
-            info.vendor = (vendor_id, None);
-        }
-        if let Some(class) = db.classes.get(&class_id[0..2]) {
-            info.class = (class_id[0..2].to_string(), Some(class.name.to_owned()));
-            if let Some(sub_class) = class.subclasses.get(&class_id[2..4]) {
-                info.sub_class = (class_id[2..4].to_string(), Some(sub_class.name.to_owned()));
-                if let Some(prog_if) = sub_class.prog_ifs.get(&class_id[4..6]) {
-                    info.prog_if = (class_id[4..6].to_string(), Some(prog_if.to_owned()));
-                } else {
-                    info.prog_if = (class_id[4..6].to_string(), None);
+            if let Some(class) = db.classes.get(&class_id[0..2]) {
+                info.class.1 = Some(class.name.to_owned());
+                if let Some(sub_class) = class.subclasses.get(&class_id[2..4]) {
+                    info.sub_class.1 = Some(sub_class.name.to_owned());
+                    if let Some(prog_if) = sub_class.prog_ifs.get(&class_id[4..6]) {
+                        info.prog_if.1 = Some(prog_if.to_owned());
+                    }
                 }
-            } else {
-                info.sub_class = (class_id[2..4].to_string(), None);
             }
-        } else {
-            info.class = (class_id[0..2].to_string(), None);
         }
 
-        info.revision = fs::read_to_string(&revision_node)
-            .unwrap()
-            .trim()
-            .strip_prefix("0x")
-            .unwrap()
-            .to_string();
+        info.revision = read_id("revision")?;
 
         Some(info)
     }
 }
 
+fn read_config_at(file: &File, offset: u64, size: usize) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    // unprivileged readers only get the first 64 bytes
+    if file.read_at(&mut buf[..size], offset)? != size {
+        return Err(Error::Unmapped {
+            offset,
+            length: size as u64,
+        });
+    }
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Configuration space of a device as a [`MatrixData`] backend
+pub struct PciConfig {
+    pub device: PciDevice,
+    pub inner: Vec<Option<u8>>,
+    pub size: u16,
+    file: File,
+    /// size of the configuration space exposed by sysfs
+    len: u64,
+}
+
+impl PciConfig {
+    pub fn new(device: PciDevice, size: u16) -> Result<Self> {
+        let file = File::open(device.config_path())
+            .map_err(|_| Error::NotFound(format!("device {}", device)))?;
+        let len = file.metadata()?.len();
+        let mut config = Self {
+            device,
+            inner: vec![None; size as usize],
+            size,
+            file,
+            len,
+        };
+        config.update(0, &DataType::Byte)?;
+        Ok(config)
+    }
+}
+
+impl MatrixData for PciConfig {
+    fn write(&mut self, offset: u64, data_type: &DataType, bytes: Vec<u8>) -> Result<()> {
+        let size = data_size(data_type) as usize;
+        if !bytes.len().is_multiple_of(size) {
+            return Err(Error::Parse(format!(
+                "{} bytes can not be written with {:?} accesses",
+                bytes.len(),
+                data_type
+            )));
+        }
+        for (i, chunk) in bytes.chunks(size).enumerate() {
+            let mut value = [0u8; 8];
+            value[..size].copy_from_slice(chunk);
+            self.device.write_config(
+                offset + (i * size) as u64,
+                data_type,
+                u64::from_ne_bytes(value),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, start: u64, data_type: &DataType) -> Result<()> {
+        let size = data_size(data_type) as usize;
+        self.inner = vec![None; self.size as usize];
+        for index in (0..self.size as usize).step_by(size) {
+            let offset = start + index as u64;
+            if offset + size as u64 > self.len {
+                break;
+            }
+            // bytes an unprivileged reader can not see stay unreadable
+            if let Ok(value) = read_config_at(&self.file, offset, size) {
+                for (i, byte) in value.to_le_bytes()[..size].iter().enumerate() {
+                    self.inner[index + i] = Some(*byte);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cell> {
+        if index < self.size as usize {
+            return Some(Cell {
+                inner: self.inner[index],
+            });
+        }
+        None
+    }
+
+    fn supports(&self, data_type: &DataType) -> bool {
+        data_size(data_type) <= 4
+    }
+
+    fn read_at(&mut self, offset: u64, data_type: &DataType, len: usize) -> Option<Vec<u8>> {
+        let size = data_size(data_type) as usize;
+        let mut bytes = Vec::with_capacity(len);
+        for i in (0..len).step_by(size) {
+            let value = read_config_at(&self.file, offset + i as u64, size).ok()?;
+            bytes.extend_from_slice(&value.to_le_bytes()[..size.min(len - i)]);
+        }
+        Some(bytes)
+    }
+
+    fn limit(&self) -> Option<u64> {
+        Some(self.len)
+    }
+
+    fn write_command(&self, offset: u64, data_type: &DataType, value: u64) -> Option<String> {
+        Some(format!(
+            "rw pci write {} 0x{:x} 0x{:x} --width {}",
+            self.device,
+            offset,
+            value,
+            data_type.short_name()
+        ))
+    }
+}
+
 pub fn devices() -> Vec<(PciDevice, PciDevBasicInfo)> {
     let mut devices: Vec<(PciDevice, PciDevBasicInfo)> = vec![];
 
-    for entry in fs::read_dir(SYS_PCI_DEVICE_ROOT).unwrap() {
+    let entries = match fs::read_dir(SYS_PCI_DEVICE_ROOT) {
+        Ok(entries) => entries,
+        Err(_) => {
+            error!("fail to read {}", SYS_PCI_DEVICE_ROOT);
+            return devices;
+        }
+    };
+    let db = Database::read();
+    if db.is_err() {
+        error!("fail to read pci-ids database");
+    }
+
+    for entry in entries {
         if entry.is_err() {
             continue;
         }
@@ -174,20 +460,73 @@ pub fn devices() -> Vec<(PciDevice, PciDevBasicInfo)> {
         }
         let filename = filename.unwrap();
         let filename = filename.to_str().unwrap();
-        match PciDevice::from_sysfs_dirname(filename) {
-            Some(device) => {
-                let info = device.basic_info();
-                if let Some(info) = info {
-                    devices.push((device, info));
-                }
+        if let Some(device) = PciDevice::from_sysfs_dirname(filename) {
+            if let Some(info) = device.read_basic_info(db.as_ref().ok()) {
+                devices.push((device, info));
             }
-            _ => {}
         }
     }
 
+    devices.sort_by_key(|(d, _)| (d.domain, d.bus, d.device, d.function));
     devices
 }
 
+/// A device in the PCI hierarchy together with the devices behind it
+#[derive(Debug, Serialize)]
+pub struct PciTreeNode {
+    pub device: PciDevice,
+    pub info: PciDevBasicInfo,
+    /// secondary and subordinate bus numbers of a bridge
+    pub bus_range: Option<(u8, u8)>,
+    pub children: Vec<PciTreeNode>,
+}
+
+/// Build the PCI hierarchy, devices directly on a root bus are returned as roots
+pub fn tree() -> Vec<PciTreeNode> {
+    let devices = devices();
+    let bus_ranges: Vec<Option<(u8, u8)>> = devices.iter().map(|(d, _)| d.bus_range()).collect();
+
+    // prefer the sysfs hierarchy, fall back to the bridge whose secondary bus
+    // is the bus the device sits on
+    let parents: Vec<Option<usize>> = devices
+        .iter()
+        .map(|(d, _)| {
+            let by_sysfs = d
+                .parent()
+                .and_then(|p| devices.iter().position(|(other, _)| *other == p));
+            by_sysfs.or_else(|| {
+                devices.iter().enumerate().position(|(i, (other, _))| {
+                    other != d
+                        && other.domain == d.domain
+                        && matches!(bus_ranges[i], Some((secondary, _)) if secondary == d.bus)
+                })
+            })
+        })
+        .collect();
+
+    fn build(
+        index: usize,
+        devices: &[(PciDevice, PciDevBasicInfo)],
+        bus_ranges: &[Option<(u8, u8)>],
+        parents: &[Option<usize>],
+    ) -> PciTreeNode {
+        PciTreeNode {
+            device: devices[index].0.clone(),
+            info: devices[index].1.clone(),
+            bus_range: bus_ranges[index],
+            children: (0..devices.len())
+                .filter(|i| parents[*i] == Some(index))
+                .map(|i| build(i, devices, bus_ranges, parents))
+                .collect(),
+        }
+    }
+
+    (0..devices.len())
+        .filter(|i| parents[*i].is_none())
+        .map(|i| build(i, &devices, &bus_ranges, &parents))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,4 +540,12 @@ mod tests {
         assert_eq!(pd.device, 15);
         assert_eq!(pd.function, 4);
     }
+
+    #[test]
+    fn test_from_bdf() {
+        let pd = PciDevice::from_bdf("3a:00.1").expect("fail to parse short bdf");
+        assert_eq!(pd, PciDevice::new(0, 0x3a, 0, 1));
+        assert_eq!(pd.to_string(), "0000:3a:00.1");
+        assert!(PciDevice::from_bdf("3a:00").is_none());
+    }
 }
@@ -0,0 +1,246 @@
+//! MMIO access to the memory BARs of a device through the sysfs `resourceN`
+//! nodes, which keeps working under `CONFIG_STRICT_DEVMEM`
+
+use super::PciDevice;
+use crate::devmem::{self, PhysMapper};
+use crate::error::{Error, Result};
+use crate::matrix::{Cell, DataType, MatrixData};
+
+/// Resource flag of an I/O port BAR, see `IORESOURCE_IO` in the kernel
+const IORESOURCE_IO: u64 = 0x100;
+
+/// Number of BARs of a type 0 header
+pub const BAR_COUNT: usize = 6;
+
+/// Path of the sysfs node mapping BAR `index`, a 64-bit BAR is named after its lower half
+fn resource_path(device: &PciDevice, index: usize) -> String {
+    format!("{}/resource{}", device.sysfs_dir(), index)
+}
+
+/// Size of memory BAR `index` of `device`
+pub fn bar_len(device: &PciDevice, index: usize) -> Result<u64> {
+    resource_len(device, &device.resources(), index)
+}
+
+/// Size of memory BAR `index` in the `(start, end, flags)` resources of `device`
+fn resource_len(device: &PciDevice, resources: &[(u64, u64, u64)], index: usize) -> Result<u64> {
+    let not_found = || Error::NotFound(format!("BAR{} of {}", index, device));
+    if index >= BAR_COUNT {
+        return Err(not_found());
+    }
+    let (start, end, flags) = *resources.get(index).ok_or_else(not_found)?;
+    if end <= start {
+        return Err(not_found());
+    }
+    if flags & IORESOURCE_IO != 0 {
+        return Err(Error::Unsupported(format!(
+            "BAR{} of {} is an I/O port BAR",
+            index, device
+        )));
+    }
+    Ok(end - start + 1)
+}
+
+/// Checks that `length` bytes at `offset` lie within a BAR of `len` bytes
+fn check_range(offset: u64, length: u64, len: u64) -> Result<()> {
+    match offset.checked_add(length) {
+        Some(end) if end <= len => Ok(()),
+        _ => Err(Error::Unmapped { offset, length }),
+    }
+}
+
+/// Whether the registers of a BAR can be accessed with accesses of `data_type`
+pub fn supports(data_type: &DataType) -> bool {
+    devmem::supports(data_type)
+}
+
+/// Maps memory BAR `index` of `device`
+pub fn open(device: &PciDevice, index: usize) -> Result<(PhysMapper, u64)> {
+    let len = bar_len(device, index)?;
+    let path = resource_path(device, index);
+    let mapper = PhysMapper::open(&path).map_err(|err| match err {
+        Error::Io(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Error::NotFound(path.clone())
+        }
+        err => err,
+    })?;
+    Ok((mapper, len))
+}
+
+/// Reads `length` bytes at `offset` relative to the start of BAR `index`
+pub fn read(
+    device: &PciDevice,
+    index: usize,
+    offset: u64,
+    length: usize,
+    data_type: &DataType,
+) -> Result<Vec<Option<u8>>> {
+    let (mut mapper, len) = open(device, index)?;
+    check_range(offset, length as u64, len)?;
+    mapper.read_lossy(offset, length, data_type)
+}
+
+/// Writes `bytes` at `offset` relative to the start of BAR `index` using `data_type` sized accesses
+pub fn write(
+    device: &PciDevice,
+    index: usize,
+    offset: u64,
+    data_type: &DataType,
+    bytes: &[u8],
+) -> Result<()> {
+    let (mut mapper, len) = open(device, index)?;
+    check_range(offset, bytes.len() as u64, len)?;
+    mapper.write_bytes(offset, data_type, bytes)
+}
+
+/// A memory BAR of a device as a [`MatrixData`] backend, offsets are relative to the BAR start
+pub struct PciBar {
+    pub device: PciDevice,
+    pub index: usize,
+    pub inner: Vec<Option<u8>>,
+    pub size: u16,
+    mapper: PhysMapper,
+    /// size of the BAR
+    len: u64,
+}
+
+impl PciBar {
+    pub fn new(device: PciDevice, index: usize, size: u16) -> Result<Self> {
+        let (mapper, len) = open(&device, index)?;
+        let mut bar = Self {
+            device,
+            index,
+            inner: vec![None; size as usize],
+            size,
+            mapper,
+            len,
+        };
+        bar.update(0, &DataType::Byte)?;
+        Ok(bar)
+    }
+}
+
+impl MatrixData for PciBar {
+    fn write(&mut self, offset: u64, data_type: &DataType, bytes: Vec<u8>) -> Result<()> {
+        check_range(offset, bytes.len() as u64, self.len)?;
+        self.mapper.write_bytes(offset, data_type, &bytes)
+    }
+
+    fn update(&mut self, start: u64, data_type: &DataType) -> Result<()> {
+        self.inner = vec![None; self.size as usize];
+        if start >= self.len {
+            return Ok(());
+        }
+        // the last page of a BAR smaller than a page is only partially backed
+        let length = (self.size as u64).min(self.len - start) as usize;
+        let data = self.mapper.read_lossy(start, length, data_type)?;
+        self.inner[..length].copy_from_slice(&data);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cell> {
+        if index < self.size as usize {
+            return Some(Cell {
+                inner: self.inner[index],
+            });
+        }
+        None
+    }
+
+    fn supports(&self, data_type: &DataType) -> bool {
+        supports(data_type)
+    }
+
+    fn limit(&self) -> Option<u64> {
+        Some(self.len)
+    }
+
+    fn scannable(&self, _offset: u64, _len: u64) -> bool {
+        false
+    }
+
+    fn read_at(&mut self, offset: u64, data_type: &DataType, len: usize) -> Option<Vec<u8>> {
+        check_range(offset, len as u64, self.len).ok()?;
+        let data = self.mapper.read_lossy(offset, len, data_type).ok()?;
+        data.into_iter().collect()
+    }
+
+    fn write_command(&self, offset: u64, data_type: &DataType, value: u64) -> Option<String> {
+        Some(format!(
+            "rw pci bar {} {} write 0x{:x} 0x{:x} --width {}",
+            self.device,
+            self.index,
+            offset,
+            value,
+            data_type.short_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn test_bar_len() {
+        let device = PciDevice::new(0, 0, 2, 0);
+        let resources = [
+            (0xfe000000, 0xfe000fff, 0x40200),
+            (0, 0, 0),
+            (0xe000, 0xe03f, 0x40101),
+        ];
+        assert_eq!(resource_len(&device, &resources, 0).unwrap(), 0x1000);
+        assert!(matches!(
+            resource_len(&device, &resources, 1),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            resource_len(&device, &resources, 2),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(resource_len(&device, &resources, 3).is_err());
+        assert!(resource_len(&device, &resources, BAR_COUNT).is_err());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let path = std::env::temp_dir().join(format!("rwlinux-bar-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&[0x5a; 0x2000]).unwrap();
+        drop(file);
+
+        // a BAR of 0x1800 bytes backed by the first pages of the file
+        let mut bar = PciBar {
+            device: PciDevice::new(0, 0, 2, 0),
+            index: 0,
+            inner: vec![],
+            size: 0x100,
+            mapper: PhysMapper::open(path.to_str().unwrap()).unwrap(),
+            len: 0x1800,
+        };
+        assert!(bar
+            .write(0x17fc, &DataType::DoubleWord, vec![1, 2, 3, 4])
+            .is_ok());
+        assert_eq!(
+            bar.read_at(0x17fc, &DataType::DoubleWord, 4),
+            Some(vec![1, 2, 3, 4])
+        );
+        assert!(matches!(
+            bar.write(0x1800, &DataType::DoubleWord, vec![0; 4]),
+            Err(Error::Unmapped { .. })
+        ));
+        assert!(bar.read_at(0x17fe, &DataType::Byte, 4).is_none());
+        assert!(bar
+            .write(u64::MAX - 1, &DataType::Byte, vec![0; 4])
+            .is_err());
+
+        // the last page is shown up to the end of the BAR
+        bar.update(0x1780, &DataType::Byte).unwrap();
+        assert_eq!(bar.get(0x7f).unwrap().inner, Some(4));
+        assert_eq!(bar.get(0x80).unwrap().inner, None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
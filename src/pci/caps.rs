@@ -0,0 +1,1107 @@
+//! Walker and decoder for the PCI capability list at 0x34 and the PCIe
+//! extended capability list at 0x100
+
+use super::config::{u16_at, u32_at, HEADER_SIZE};
+use serde::Serialize;
+use std::fmt;
+
+/// Offset of the first extended capability
+pub const EXTENDED_CAPS_OFFSET: usize = 0x100;
+
+// upper bounds of list entries, protects against loops in broken lists
+const MAX_CAPS: usize = 48;
+const MAX_EXTENDED_CAPS: usize = 480;
+
+pub const CAP_ID_PM: u16 = 0x01;
+pub const CAP_ID_MSI: u16 = 0x05;
+pub const CAP_ID_VENDOR: u16 = 0x09;
+pub const CAP_ID_EXPRESS: u16 = 0x10;
+pub const CAP_ID_MSIX: u16 = 0x11;
+
+pub const EXT_CAP_ID_AER: u16 = 0x01;
+pub const EXT_CAP_ID_ACS: u16 = 0x0d;
+pub const EXT_CAP_ID_SRIOV: u16 = 0x10;
+pub const EXT_CAP_ID_REBAR: u16 = 0x15;
+pub const EXT_CAP_ID_LTR: u16 = 0x18;
+pub const EXT_CAP_ID_DPC: u16 = 0x1d;
+pub const EXT_CAP_ID_L1SS: u16 = 0x1e;
+pub const EXT_CAP_ID_PTM: u16 = 0x1f;
+pub const EXT_CAP_ID_DVSEC: u16 = 0x23;
+
+fn cap_name(id: u16) -> &'static str {
+    match id {
+        CAP_ID_PM => "Power Management",
+        0x02 => "AGP",
+        0x03 => "Vital Product Data",
+        0x04 => "Slot Identification",
+        CAP_ID_MSI => "MSI",
+        0x06 => "CompactPCI Hot Swap",
+        0x07 => "PCI-X",
+        0x08 => "HyperTransport",
+        CAP_ID_VENDOR => "Vendor Specific",
+        0x0a => "Debug Port",
+        0x0b => "CompactPCI Central Resource Control",
+        0x0c => "PCI Hot-Plug",
+        0x0d => "Bridge Subsystem Vendor ID",
+        0x0e => "AGP 8x",
+        0x0f => "Secure Device",
+        CAP_ID_EXPRESS => "PCI Express",
+        CAP_ID_MSIX => "MSI-X",
+        0x12 => "SATA Data/Index Configuration",
+        0x13 => "Advanced Features",
+        0x14 => "Enhanced Allocation",
+        0x15 => "Flattening Portal Bridge",
+        _ => "Unknown",
+    }
+}
+
+fn ext_cap_name(id: u16) -> &'static str {
+    match id {
+        EXT_CAP_ID_AER => "Advanced Error Reporting",
+        0x02 | 0x09 => "Virtual Channel",
+        0x03 => "Device Serial Number",
+        0x04 => "Power Budgeting",
+        0x05 => "Root Complex Link Declaration",
+        0x06 => "Root Complex Internal Link Control",
+        0x07 => "Root Complex Event Collector Endpoint Association",
+        0x08 => "Multi-Function Virtual Channel",
+        0x0a => "Root Complex Register Block",
+        0x0b => "Vendor Specific",
+        0x0c => "Configuration Access Correlation",
+        EXT_CAP_ID_ACS => "Access Control Services",
+        0x0e => "Alternative Routing-ID Interpretation",
+        0x0f => "Address Translation Services",
+        EXT_CAP_ID_SRIOV => "Single Root I/O Virtualization",
+        0x11 => "Multi-Root I/O Virtualization",
+        0x12 => "Multicast",
+        0x13 => "Page Request Interface",
+        EXT_CAP_ID_REBAR => "Resizable BAR",
+        0x16 => "Dynamic Power Allocation",
+        0x17 => "TPH Requester",
+        EXT_CAP_ID_LTR => "Latency Tolerance Reporting",
+        0x19 => "Secondary PCI Express",
+        0x1a => "Protocol Multiplexing",
+        0x1b => "Process Address Space ID",
+        0x1c => "LN Requester",
+        EXT_CAP_ID_DPC => "Downstream Port Containment",
+        EXT_CAP_ID_L1SS => "L1 PM Substates",
+        EXT_CAP_ID_PTM => "Precision Time Measurement",
+        0x21 => "FRS Queueing",
+        0x22 => "Readiness Time Reporting",
+        EXT_CAP_ID_DVSEC => "Designated Vendor-Specific",
+        0x24 => "VF Resizable BAR",
+        0x25 => "Data Link Feature",
+        0x26 => "Physical Layer 16.0 GT/s",
+        0x27 => "Lane Margining at the Receiver",
+        0x28 => "Hierarchy ID",
+        0x29 => "Native PCIe Enclosure Management",
+        0x2a => "Physical Layer 32.0 GT/s",
+        _ => "Unknown",
+    }
+}
+
+/// Name of a link speed encoding as used by the link capabilities and status registers
+pub fn link_speed_name(speed: u8) -> &'static str {
+    match speed {
+        1 => "2.5GT/s",
+        2 => "5GT/s",
+        3 => "8GT/s",
+        4 => "16GT/s",
+        5 => "32GT/s",
+        6 => "64GT/s",
+        _ => "unknown",
+    }
+}
+
+/// Names of the bits set in `value`, `names` holds `(bit, name)` pairs
+fn bit_names(value: u32, names: &[(u32, &'static str)]) -> Vec<&'static str> {
+    names
+        .iter()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerManagement {
+    pub version: u8,
+    pub pme_clock: bool,
+    pub dsi: bool,
+    /// auxiliary current requirement in mA
+    pub aux_current: u16,
+    pub d1_support: bool,
+    pub d2_support: bool,
+    /// power states PME# can be asserted from, bit 0 is D0 and bit 4 D3cold
+    pub pme_support: u8,
+    /// current power state, 0 to 3 for D0 to D3hot
+    pub power_state: u8,
+    pub no_soft_reset: bool,
+    pub pme_enable: bool,
+    pub pme_status: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Msi {
+    pub enable: bool,
+    pub multiple_message_capable: u8,
+    pub multiple_message_enable: u8,
+    pub address_64bit: bool,
+    pub per_vector_masking: bool,
+    pub address: u64,
+    pub data: u16,
+    pub mask: Option<u32>,
+    pub pending: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MsiX {
+    pub enable: bool,
+    pub function_mask: bool,
+    pub table_size: u16,
+    pub table_bir: u8,
+    pub table_offset: u32,
+    pub pba_bir: u8,
+    pub pba_offset: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Express {
+    pub version: u8,
+    pub device_port_type: u8,
+    pub slot_implemented: bool,
+    pub interrupt_message_number: u8,
+    /// max payload size in bytes
+    pub max_payload_supported: u16,
+    pub max_payload: u16,
+    pub max_read_request: u16,
+    pub relaxed_ordering: bool,
+    pub no_snoop: bool,
+    pub correctable_error_detected: bool,
+    pub non_fatal_error_detected: bool,
+    pub fatal_error_detected: bool,
+    pub unsupported_request_detected: bool,
+    pub transactions_pending: bool,
+    /// link speed encodings, see `link_speed_name`
+    pub max_link_speed: u8,
+    pub max_link_width: u8,
+    pub aspm_support: u8,
+    pub port_number: u8,
+    pub aspm_control: u8,
+    pub link_disable: bool,
+    pub common_clock: bool,
+    pub current_link_speed: u8,
+    pub negotiated_link_width: u8,
+    pub link_training: bool,
+    pub data_link_layer_active: bool,
+    /// speeds in the supported link speeds vector of link capabilities 2
+    pub supported_link_speeds: Vec<u8>,
+}
+
+impl Express {
+    /// Whether the function has a link, i.e. is not a root complex integrated device
+    pub fn has_link(&self) -> bool {
+        !matches!(self.device_port_type, 0x9 | 0xa)
+    }
+}
+
+/// Name of a PCIe device/port type
+pub fn device_port_type_name(port_type: u8) -> &'static str {
+    match port_type {
+        0x0 => "Endpoint",
+        0x1 => "Legacy Endpoint",
+        0x4 => "Root Port",
+        0x5 => "Upstream Port",
+        0x6 => "Downstream Port",
+        0x7 => "PCIe to PCI/PCI-X Bridge",
+        0x8 => "PCI/PCI-X to PCIe Bridge",
+        0x9 => "Root Complex Integrated Endpoint",
+        0xa => "Root Complex Event Collector",
+        _ => "Unknown",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SrIov {
+    pub capabilities: u32,
+    pub vf_enable: bool,
+    pub vf_memory_space: bool,
+    pub ari_capable_hierarchy: bool,
+    pub initial_vfs: u16,
+    pub total_vfs: u16,
+    pub num_vfs: u16,
+    pub function_dependency_link: u8,
+    pub first_vf_offset: u16,
+    pub vf_stride: u16,
+    pub vf_device_id: u16,
+    pub supported_page_sizes: u32,
+    pub system_page_size: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Aer {
+    pub uncorrectable_status: u32,
+    pub uncorrectable_mask: u32,
+    pub uncorrectable_severity: u32,
+    pub correctable_status: u32,
+    pub correctable_mask: u32,
+    pub first_error_pointer: u8,
+    pub header_log: [u32; 4],
+    /// names of the uncorrectable errors flagged in the status register
+    pub uncorrectable_errors: Vec<&'static str>,
+    /// names of the correctable errors flagged in the status register
+    pub correctable_errors: Vec<&'static str>,
+}
+
+const AER_UNCORRECTABLE: &[(u32, &str)] = &[
+    (4, "DLP"),
+    (5, "SDES"),
+    (12, "TLP"),
+    (13, "FCP"),
+    (14, "CmpltTO"),
+    (15, "CmpltAbrt"),
+    (16, "UnxCmplt"),
+    (17, "RxOF"),
+    (18, "MalfTLP"),
+    (19, "ECRC"),
+    (20, "UnsupReq"),
+    (21, "ACSViol"),
+    (22, "UncorrIntErr"),
+    (25, "PoisonTLPBlocked"),
+];
+
+const AER_CORRECTABLE: &[(u32, &str)] = &[
+    (0, "RxErr"),
+    (6, "BadTLP"),
+    (7, "BadDLLP"),
+    (8, "Rollover"),
+    (12, "Timeout"),
+    (13, "AdvNonFatalErr"),
+    (14, "CorrIntErr"),
+    (15, "HeaderOF"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Acs {
+    pub capabilities: Vec<&'static str>,
+    pub control: Vec<&'static str>,
+}
+
+const ACS_BITS: &[(u32, &str)] = &[
+    (0, "SrcValid"),
+    (1, "TransBlk"),
+    (2, "ReqRedir"),
+    (3, "CmpltRedir"),
+    (4, "UpstreamFwd"),
+    (5, "EgressCtrl"),
+    (6, "DirectTrans"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ltr {
+    /// latencies in ns
+    pub max_snoop_latency: u64,
+    pub max_no_snoop_latency: u64,
+}
+
+/// Decode a latency given as a 10-bit value and 3-bit scale, in ns
+fn ltr_latency(value: u32, scale: u32) -> u64 {
+    if scale > 5 {
+        return 0;
+    }
+    (value as u64) << (5 * scale)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct L1Substates {
+    pub supported: Vec<&'static str>,
+    pub enabled: Vec<&'static str>,
+    pub common_mode_restore_time: u8,
+    /// T_POWER_ON in us
+    pub power_on_time: u32,
+    /// LTR L1.2 threshold in ns
+    pub ltr_l1_2_threshold: u64,
+}
+
+const L1SS_BITS: &[(u32, &str)] = &[
+    (0, "PCI-PM_L1.2"),
+    (1, "PCI-PM_L1.1"),
+    (2, "ASPM_L1.2"),
+    (3, "ASPM_L1.1"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dpc {
+    pub interrupt_message_number: u8,
+    pub rp_extensions: bool,
+    pub trigger_enable: u8,
+    pub trigger_status: bool,
+    pub trigger_reason: u8,
+    pub source_id: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ptm {
+    pub requester: bool,
+    pub responder: bool,
+    pub root: bool,
+    pub local_clock_granularity: u8,
+    pub enable: bool,
+    pub root_select: bool,
+    pub effective_granularity: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResizableBar {
+    pub bar: u8,
+    /// supported sizes in bytes
+    pub supported_sizes: Vec<u64>,
+    /// current size in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dvsec {
+    pub vendor_id: u16,
+    pub revision: u8,
+    pub length: u16,
+    pub id: u16,
+}
+
+/// Decoded body of a capability
+#[derive(Debug, Clone, Serialize)]
+pub enum CapabilityData {
+    PowerManagement(PowerManagement),
+    Msi(Msi),
+    MsiX(MsiX),
+    Express(Express),
+    VendorSpecific {
+        length: u8,
+    },
+    SrIov(SrIov),
+    Aer(Aer),
+    Acs(Acs),
+    Ltr(Ltr),
+    L1Substates(L1Substates),
+    Dpc(Dpc),
+    Ptm(Ptm),
+    ResizableBar(Vec<ResizableBar>),
+    Dvsec(Dvsec),
+    /// a capability without decoder or one that extends past the available data
+    Raw,
+}
+
+/// A capability found in the standard or extended list
+#[derive(Debug, Clone, Serialize)]
+pub struct Capability {
+    pub id: u16,
+    pub name: &'static str,
+    pub offset: u16,
+    pub extended: bool,
+    /// capability version, only present in extended capability headers
+    pub version: Option<u8>,
+    pub data: CapabilityData,
+}
+
+fn decode_pm(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 8 > data.len() {
+        return None;
+    }
+    let pmc = u16_at(data, off + 2);
+    let pmcsr = u16_at(data, off + 4);
+    const AUX_CURRENT: [u16; 8] = [0, 55, 100, 160, 220, 270, 320, 375];
+    Some(CapabilityData::PowerManagement(PowerManagement {
+        version: (pmc & 0x7) as u8,
+        pme_clock: pmc & (1 << 3) != 0,
+        dsi: pmc & (1 << 5) != 0,
+        aux_current: AUX_CURRENT[((pmc >> 6) & 0x7) as usize],
+        d1_support: pmc & (1 << 9) != 0,
+        d2_support: pmc & (1 << 10) != 0,
+        pme_support: (pmc >> 11) as u8,
+        power_state: (pmcsr & 0x3) as u8,
+        no_soft_reset: pmcsr & (1 << 3) != 0,
+        pme_enable: pmcsr & (1 << 8) != 0,
+        pme_status: pmcsr & (1 << 15) != 0,
+    }))
+}
+
+fn decode_msi(data: &[u8], off: usize) -> Option<CapabilityData> {
+    let control = u16_at(data.get(..off + 4)?, off + 2);
+    let address_64bit = control & (1 << 7) != 0;
+    let per_vector_masking = control & (1 << 8) != 0;
+    let data_off = if address_64bit { 0xc } else { 0x8 };
+    let len = data_off + if per_vector_masking { 0xc } else { 0x4 };
+    if off + len > data.len() {
+        return None;
+    }
+    let mut address = u32_at(data, off + 4) as u64;
+    if address_64bit {
+        address |= (u32_at(data, off + 8) as u64) << 32;
+    }
+    let (mask, pending) = if per_vector_masking {
+        (
+            Some(u32_at(data, off + data_off + 4)),
+            Some(u32_at(data, off + data_off + 8)),
+        )
+    } else {
+        (None, None)
+    };
+    Some(CapabilityData::Msi(Msi {
+        enable: control & 0x1 != 0,
+        multiple_message_capable: 1 << ((control >> 1) & 0x7).min(5),
+        multiple_message_enable: 1 << ((control >> 4) & 0x7).min(5),
+        address_64bit,
+        per_vector_masking,
+        address,
+        data: u16_at(data, off + data_off),
+        mask,
+        pending,
+    }))
+}
+
+fn decode_msix(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 12 > data.len() {
+        return None;
+    }
+    let control = u16_at(data, off + 2);
+    let table = u32_at(data, off + 4);
+    let pba = u32_at(data, off + 8);
+    Some(CapabilityData::MsiX(MsiX {
+        enable: control & (1 << 15) != 0,
+        function_mask: control & (1 << 14) != 0,
+        table_size: (control & 0x7ff) + 1,
+        table_bir: (table & 0x7) as u8,
+        table_offset: table & !0x7,
+        pba_bir: (pba & 0x7) as u8,
+        pba_offset: pba & !0x7,
+    }))
+}
+
+fn decode_express(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0x14 > data.len() {
+        return None;
+    }
+    let cap = u16_at(data, off + 0x2);
+    let devcap = u32_at(data, off + 0x4);
+    let devctl = u16_at(data, off + 0x8);
+    let devsta = u16_at(data, off + 0xa);
+    let linkcap = u32_at(data, off + 0xc);
+    let linkctl = u16_at(data, off + 0x10);
+    let linksta = u16_at(data, off + 0x12);
+    let version = (cap & 0xf) as u8;
+    // the supported speeds vector only exists in version 2 capabilities
+    let supported_link_speeds = match version >= 2 && off + 0x30 <= data.len() {
+        true => {
+            let linkcap2 = u32_at(data, off + 0x2c);
+            (1..=7).filter(|bit| linkcap2 & (1 << bit) != 0).collect()
+        }
+        false => vec![],
+    };
+    Some(CapabilityData::Express(Express {
+        version,
+        device_port_type: ((cap >> 4) & 0xf) as u8,
+        slot_implemented: cap & (1 << 8) != 0,
+        interrupt_message_number: ((cap >> 9) & 0x1f) as u8,
+        max_payload_supported: 128 << (devcap & 0x7).min(5),
+        max_payload: 128 << ((devctl >> 5) & 0x7).min(5),
+        max_read_request: 128 << ((devctl >> 12) & 0x7).min(5),
+        relaxed_ordering: devctl & (1 << 4) != 0,
+        no_snoop: devctl & (1 << 11) != 0,
+        correctable_error_detected: devsta & (1 << 0) != 0,
+        non_fatal_error_detected: devsta & (1 << 1) != 0,
+        fatal_error_detected: devsta & (1 << 2) != 0,
+        unsupported_request_detected: devsta & (1 << 3) != 0,
+        transactions_pending: devsta & (1 << 5) != 0,
+        max_link_speed: (linkcap & 0xf) as u8,
+        max_link_width: ((linkcap >> 4) & 0x3f) as u8,
+        aspm_support: ((linkcap >> 10) & 0x3) as u8,
+        port_number: (linkcap >> 24) as u8,
+        aspm_control: (linkctl & 0x3) as u8,
+        link_disable: linkctl & (1 << 4) != 0,
+        common_clock: linkctl & (1 << 6) != 0,
+        current_link_speed: (linksta & 0xf) as u8,
+        negotiated_link_width: ((linksta >> 4) & 0x3f) as u8,
+        link_training: linksta & (1 << 11) != 0,
+        data_link_layer_active: linksta & (1 << 13) != 0,
+        supported_link_speeds,
+    }))
+}
+
+fn decode_sriov(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0x24 > data.len() {
+        return None;
+    }
+    let control = u16_at(data, off + 0x8);
+    Some(CapabilityData::SrIov(SrIov {
+        capabilities: u32_at(data, off + 0x4),
+        vf_enable: control & (1 << 0) != 0,
+        vf_memory_space: control & (1 << 3) != 0,
+        ari_capable_hierarchy: control & (1 << 4) != 0,
+        initial_vfs: u16_at(data, off + 0xc),
+        total_vfs: u16_at(data, off + 0xe),
+        num_vfs: u16_at(data, off + 0x10),
+        function_dependency_link: data[off + 0x12],
+        first_vf_offset: u16_at(data, off + 0x14),
+        vf_stride: u16_at(data, off + 0x16),
+        vf_device_id: u16_at(data, off + 0x1a),
+        supported_page_sizes: u32_at(data, off + 0x1c),
+        system_page_size: u32_at(data, off + 0x20),
+    }))
+}
+
+fn decode_aer(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0x2c > data.len() {
+        return None;
+    }
+    let uncorrectable_status = u32_at(data, off + 0x4);
+    let correctable_status = u32_at(data, off + 0x10);
+    Some(CapabilityData::Aer(Aer {
+        uncorrectable_status,
+        uncorrectable_mask: u32_at(data, off + 0x8),
+        uncorrectable_severity: u32_at(data, off + 0xc),
+        correctable_status,
+        correctable_mask: u32_at(data, off + 0x14),
+        first_error_pointer: (u32_at(data, off + 0x18) & 0x1f) as u8,
+        header_log: [
+            u32_at(data, off + 0x1c),
+            u32_at(data, off + 0x20),
+            u32_at(data, off + 0x24),
+            u32_at(data, off + 0x28),
+        ],
+        uncorrectable_errors: bit_names(uncorrectable_status, AER_UNCORRECTABLE),
+        correctable_errors: bit_names(correctable_status, AER_CORRECTABLE),
+    }))
+}
+
+fn decode_acs(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0x8 > data.len() {
+        return None;
+    }
+    Some(CapabilityData::Acs(Acs {
+        capabilities: bit_names(u16_at(data, off + 0x4) as u32, ACS_BITS),
+        control: bit_names(u16_at(data, off + 0x6) as u32, ACS_BITS),
+    }))
+}
+
+fn decode_ltr(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0x8 > data.len() {
+        return None;
+    }
+    let latency = |raw: u16| ltr_latency((raw & 0x3ff) as u32, ((raw >> 10) & 0x7) as u32);
+    Some(CapabilityData::Ltr(Ltr {
+        max_snoop_latency: latency(u16_at(data, off + 0x4)),
+        max_no_snoop_latency: latency(u16_at(data, off + 0x6)),
+    }))
+}
+
+fn decode_l1ss(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0xc > data.len() {
+        return None;
+    }
+    let cap = u32_at(data, off + 0x4);
+    let ctl = u32_at(data, off + 0x8);
+    const POWER_ON_SCALE: [u32; 4] = [2, 10, 100, 0];
+    Some(CapabilityData::L1Substates(L1Substates {
+        supported: bit_names(cap, L1SS_BITS),
+        enabled: bit_names(ctl, L1SS_BITS),
+        common_mode_restore_time: (cap >> 8) as u8,
+        power_on_time: ((cap >> 19) & 0x1f) * POWER_ON_SCALE[((cap >> 16) & 0x3) as usize],
+        ltr_l1_2_threshold: ltr_latency((ctl >> 16) & 0x3ff, (ctl >> 29) & 0x7),
+    }))
+}
+
+fn decode_dpc(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0xc > data.len() {
+        return None;
+    }
+    let cap = u16_at(data, off + 0x4);
+    let status = u16_at(data, off + 0x8);
+    Some(CapabilityData::Dpc(Dpc {
+        interrupt_message_number: (cap & 0x1f) as u8,
+        rp_extensions: cap & (1 << 5) != 0,
+        trigger_enable: (u16_at(data, off + 0x6) & 0x3) as u8,
+        trigger_status: status & 0x1 != 0,
+        trigger_reason: ((status >> 1) & 0x3) as u8,
+        source_id: u16_at(data, off + 0xa),
+    }))
+}
+
+fn decode_ptm(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0xc > data.len() {
+        return None;
+    }
+    let cap = u32_at(data, off + 0x4);
+    let ctl = u32_at(data, off + 0x8);
+    Some(CapabilityData::Ptm(Ptm {
+        requester: cap & (1 << 0) != 0,
+        responder: cap & (1 << 1) != 0,
+        root: cap & (1 << 2) != 0,
+        local_clock_granularity: (cap >> 8) as u8,
+        enable: ctl & (1 << 0) != 0,
+        root_select: ctl & (1 << 1) != 0,
+        effective_granularity: (ctl >> 8) as u8,
+    }))
+}
+
+fn decode_rebar(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0xc > data.len() {
+        return None;
+    }
+    let count = ((u32_at(data, off + 0x8) >> 5) & 0x7) as usize;
+    let mut bars = vec![];
+    for i in 0..count {
+        let entry = off + 0x4 + i * 8;
+        if entry + 8 > data.len() {
+            break;
+        }
+        let cap = u32_at(data, entry);
+        let ctl = u32_at(data, entry + 4);
+        bars.push(ResizableBar {
+            bar: (ctl & 0x7) as u8,
+            // bit 4 of the capability register stands for 1 MiB
+            supported_sizes: (4..32)
+                .filter(|bit| cap & (1 << bit) != 0)
+                .map(|bit| 1u64 << (bit + 16))
+                .collect(),
+            size: 1u64 << (20 + ((ctl >> 8) & 0x3f)).min(63),
+        });
+    }
+    Some(CapabilityData::ResizableBar(bars))
+}
+
+fn decode_dvsec(data: &[u8], off: usize) -> Option<CapabilityData> {
+    if off + 0xa > data.len() {
+        return None;
+    }
+    let header1 = u32_at(data, off + 0x4);
+    Some(CapabilityData::Dvsec(Dvsec {
+        vendor_id: header1 as u16,
+        revision: ((header1 >> 16) & 0xf) as u8,
+        length: (header1 >> 20) as u16,
+        id: u16_at(data, off + 0x8),
+    }))
+}
+
+/// Walk the standard capability list of a configuration space dump
+pub fn capabilities(data: &[u8]) -> Vec<Capability> {
+    let mut caps = vec![];
+    // capabilities list bit in the status register
+    if data.len() < HEADER_SIZE || data[0x06] & 0x10 == 0 {
+        return caps;
+    }
+    let ptr_offset = match data[0x0e] & 0x7f {
+        2 => 0x14,
+        _ => 0x34,
+    };
+    let mut ptr = (data[ptr_offset] & 0xfc) as usize;
+    while ptr >= HEADER_SIZE && ptr + 2 <= data.len() && caps.len() < MAX_CAPS {
+        let id = data[ptr] as u16;
+        let decoded = match id {
+            CAP_ID_PM => decode_pm(data, ptr),
+            CAP_ID_MSI => decode_msi(data, ptr),
+            CAP_ID_MSIX => decode_msix(data, ptr),
+            CAP_ID_EXPRESS => decode_express(data, ptr),
+            CAP_ID_VENDOR => data
+                .get(ptr + 2)
+                .map(|length| CapabilityData::VendorSpecific { length: *length }),
+            _ => None,
+        };
+        caps.push(Capability {
+            id,
+            name: cap_name(id),
+            offset: ptr as u16,
+            extended: false,
+            version: None,
+            data: decoded.unwrap_or(CapabilityData::Raw),
+        });
+        ptr = (data[ptr + 1] & 0xfc) as usize;
+    }
+    caps
+}
+
+/// Walk the extended capability list, present if the dump covers the extended
+/// configuration space of a PCIe function
+pub fn extended_capabilities(data: &[u8]) -> Vec<Capability> {
+    let mut caps = vec![];
+    let mut ptr = EXTENDED_CAPS_OFFSET;
+    while ptr + 4 <= data.len() && caps.len() < MAX_EXTENDED_CAPS {
+        let header = u32_at(data, ptr);
+        if header == 0 || header == 0xffff_ffff {
+            break;
+        }
+        let id = header as u16;
+        let decoded = match id {
+            EXT_CAP_ID_AER => decode_aer(data, ptr),
+            EXT_CAP_ID_ACS => decode_acs(data, ptr),
+            EXT_CAP_ID_SRIOV => decode_sriov(data, ptr),
+            EXT_CAP_ID_REBAR => decode_rebar(data, ptr),
+            EXT_CAP_ID_LTR => decode_ltr(data, ptr),
+            EXT_CAP_ID_DPC => decode_dpc(data, ptr),
+            EXT_CAP_ID_L1SS => decode_l1ss(data, ptr),
+            EXT_CAP_ID_PTM => decode_ptm(data, ptr),
+            EXT_CAP_ID_DVSEC => decode_dvsec(data, ptr),
+            _ => None,
+        };
+        caps.push(Capability {
+            id,
+            name: ext_cap_name(id),
+            offset: ptr as u16,
+            extended: true,
+            version: Some(((header >> 16) & 0xf) as u8),
+            data: decoded.unwrap_or(CapabilityData::Raw),
+        });
+        ptr = ((header >> 20) & 0xffc) as usize;
+        if ptr < EXTENDED_CAPS_OFFSET {
+            break;
+        }
+    }
+    caps
+}
+
+/// Both capability lists, standard capabilities first
+pub fn all_capabilities(data: &[u8]) -> Vec<Capability> {
+    let mut caps = capabilities(data);
+    caps.extend(extended_capabilities(data));
+    caps
+}
+
+fn flag(name: &str, set: bool) -> String {
+    format!("{}{}", name, if set { "+" } else { "-" })
+}
+
+fn names(names: &[&str]) -> String {
+    if names.is_empty() {
+        return "none".to_string();
+    }
+    names.join(" ")
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.extended {
+            write!(
+                f,
+                "Capabilities: [{:03x} v{}] ",
+                self.offset,
+                self.version.unwrap_or(0)
+            )?;
+        } else {
+            write!(f, "Capabilities: [{:02x}] ", self.offset)?;
+        }
+        writeln!(f, "{} ({:02x})", self.name, self.id)?;
+
+        match &self.data {
+            CapabilityData::PowerManagement(pm) => {
+                writeln!(
+                    f,
+                    "\tFlags: PMEClk{} DSI{} D1{} D2{} AuxCurrent={}mA PME(D0{},D1{},D2{},D3hot{},D3cold{}) version {}",
+                    if pm.pme_clock { "+" } else { "-" },
+                    if pm.dsi { "+" } else { "-" },
+                    if pm.d1_support { "+" } else { "-" },
+                    if pm.d2_support { "+" } else { "-" },
+                    pm.aux_current,
+                    if pm.pme_support & 0x01 != 0 { "+" } else { "-" },
+                    if pm.pme_support & 0x02 != 0 { "+" } else { "-" },
+                    if pm.pme_support & 0x04 != 0 { "+" } else { "-" },
+                    if pm.pme_support & 0x08 != 0 { "+" } else { "-" },
+                    if pm.pme_support & 0x10 != 0 { "+" } else { "-" },
+                    pm.version,
+                )?;
+                writeln!(
+                    f,
+                    "\tStatus: D{} {} {} {}",
+                    match pm.power_state {
+                        3 => "3hot".to_string(),
+                        state => state.to_string(),
+                    },
+                    flag("NoSoftRst", pm.no_soft_reset),
+                    flag("PME-Enable", pm.pme_enable),
+                    flag("PME", pm.pme_status),
+                )?;
+            }
+            CapabilityData::Msi(msi) => {
+                writeln!(
+                    f,
+                    "\t{} Count={}/{} {} {}",
+                    flag("Enable", msi.enable),
+                    msi.multiple_message_enable,
+                    msi.multiple_message_capable,
+                    flag("Maskable", msi.per_vector_masking),
+                    flag("64bit", msi.address_64bit),
+                )?;
+                writeln!(f, "\tAddress: {:016x}  Data: {:04x}", msi.address, msi.data)?;
+                if let (Some(mask), Some(pending)) = (msi.mask, msi.pending) {
+                    writeln!(f, "\tMasking: {:08x}  Pending: {:08x}", mask, pending)?;
+                }
+            }
+            CapabilityData::MsiX(msix) => {
+                writeln!(
+                    f,
+                    "\t{} Count={} {}",
+                    flag("Enable", msix.enable),
+                    msix.table_size,
+                    flag("Masked", msix.function_mask),
+                )?;
+                writeln!(
+                    f,
+                    "\tVector table: BAR={} offset={:08x}",
+                    msix.table_bir, msix.table_offset
+                )?;
+                writeln!(
+                    f,
+                    "\tPBA: BAR={} offset={:08x}",
+                    msix.pba_bir, msix.pba_offset
+                )?;
+            }
+            CapabilityData::Express(exp) => {
+                writeln!(
+                    f,
+                    "\t{} v{}, MSI {:02x}{}",
+                    device_port_type_name(exp.device_port_type),
+                    exp.version,
+                    exp.interrupt_message_number,
+                    if exp.slot_implemented { ", slot" } else { "" }
+                )?;
+                writeln!(
+                    f,
+                    "\tDevCtl: MaxPayload {} bytes (max {}), MaxReadReq {} bytes {} {}",
+                    exp.max_payload,
+                    exp.max_payload_supported,
+                    exp.max_read_request,
+                    flag("RlxdOrd", exp.relaxed_ordering),
+                    flag("NoSnoop", exp.no_snoop),
+                )?;
+                writeln!(
+                    f,
+                    "\tDevSta: {} {} {} {} {}",
+                    flag("CorrErr", exp.correctable_error_detected),
+                    flag("NonFatalErr", exp.non_fatal_error_detected),
+                    flag("FatalErr", exp.fatal_error_detected),
+                    flag("UnsupReq", exp.unsupported_request_detected),
+                    flag("TransPend", exp.transactions_pending),
+                )?;
+                if exp.has_link() {
+                    writeln!(
+                        f,
+                        "\tLnkCap: Port #{}, Speed {}, Width x{}, ASPM {}",
+                        exp.port_number,
+                        link_speed_name(exp.max_link_speed),
+                        exp.max_link_width,
+                        aspm_name(exp.aspm_support),
+                    )?;
+                    writeln!(
+                        f,
+                        "\tLnkCtl: ASPM {} {} {}",
+                        aspm_name(exp.aspm_control),
+                        flag("Disabled", exp.link_disable),
+                        flag("CommClk", exp.common_clock),
+                    )?;
+                    writeln!(
+                        f,
+                        "\tLnkSta: Speed {}, Width x{} {} {}",
+                        link_speed_name(exp.current_link_speed),
+                        exp.negotiated_link_width,
+                        flag("Train", exp.link_training),
+                        flag("DLActive", exp.data_link_layer_active),
+                    )?;
+                }
+            }
+            CapabilityData::VendorSpecific { length } => {
+                writeln!(f, "\tLength: {}", length)?;
+            }
+            CapabilityData::SrIov(sriov) => {
+                writeln!(
+                    f,
+                    "\tIOVCtl: {} {} {}",
+                    flag("Enable", sriov.vf_enable),
+                    flag("MSE", sriov.vf_memory_space),
+                    flag("ARIHierarchy", sriov.ari_capable_hierarchy),
+                )?;
+                writeln!(
+                    f,
+                    "\tInitial VFs: {}, Total VFs: {}, Number of VFs: {}, Function Dependency Link: {:02x}",
+                    sriov.initial_vfs, sriov.total_vfs, sriov.num_vfs, sriov.function_dependency_link
+                )?;
+                writeln!(
+                    f,
+                    "\tVF offset: {}, stride: {}, Device ID: {:04x}",
+                    sriov.first_vf_offset, sriov.vf_stride, sriov.vf_device_id
+                )?;
+                writeln!(
+                    f,
+                    "\tSupported Page Size: {:08x}, System Page Size: {:08x}",
+                    sriov.supported_page_sizes, sriov.system_page_size
+                )?;
+            }
+            CapabilityData::Aer(aer) => {
+                writeln!(
+                    f,
+                    "\tUESta: {:08x} {}",
+                    aer.uncorrectable_status,
+                    names(&aer.uncorrectable_errors)
+                )?;
+                writeln!(f, "\tUEMsk: {:08x}", aer.uncorrectable_mask)?;
+                writeln!(f, "\tUESvrt: {:08x}", aer.uncorrectable_severity)?;
+                writeln!(
+                    f,
+                    "\tCESta: {:08x} {}",
+                    aer.correctable_status,
+                    names(&aer.correctable_errors)
+                )?;
+                writeln!(f, "\tCEMsk: {:08x}", aer.correctable_mask)?;
+                writeln!(
+                    f,
+                    "\tFirst Error Pointer: {:02x}, HeaderLog: {:08x} {:08x} {:08x} {:08x}",
+                    aer.first_error_pointer,
+                    aer.header_log[0],
+                    aer.header_log[1],
+                    aer.header_log[2],
+                    aer.header_log[3]
+                )?;
+            }
+            CapabilityData::Acs(acs) => {
+                writeln!(f, "\tACSCap: {}", names(&acs.capabilities))?;
+                writeln!(f, "\tACSCtl: {}", names(&acs.control))?;
+            }
+            CapabilityData::Ltr(ltr) => {
+                writeln!(
+                    f,
+                    "\tMax snoop latency: {}ns, Max no snoop latency: {}ns",
+                    ltr.max_snoop_latency, ltr.max_no_snoop_latency
+                )?;
+            }
+            CapabilityData::L1Substates(l1ss) => {
+                writeln!(f, "\tL1SubCap: {}", names(&l1ss.supported))?;
+                writeln!(f, "\tL1SubCtl: {}", names(&l1ss.enabled))?;
+                writeln!(
+                    f,
+                    "\tPortCommonModeRestoreTime={}us PortTPowerOnTime={}us LTR1.2_Threshold={}ns",
+                    l1ss.common_mode_restore_time, l1ss.power_on_time, l1ss.ltr_l1_2_threshold
+                )?;
+            }
+            CapabilityData::Dpc(dpc) => {
+                writeln!(
+                    f,
+                    "\tDpcCap: INT Msg #{} {}",
+                    dpc.interrupt_message_number,
+                    flag("RPExt", dpc.rp_extensions)
+                )?;
+                writeln!(
+                    f,
+                    "\tDpcCtl: Trigger:{} DpcSta: {} Reason:{:02x} Source:{:04x}",
+                    dpc.trigger_enable,
+                    flag("Trigger", dpc.trigger_status),
+                    dpc.trigger_reason,
+                    dpc.source_id
+                )?;
+            }
+            CapabilityData::Ptm(ptm) => {
+                writeln!(
+                    f,
+                    "\tPTMCap: {} {} {} Granularity={}ns",
+                    flag("Requester", ptm.requester),
+                    flag("Responder", ptm.responder),
+                    flag("Root", ptm.root),
+                    ptm.local_clock_granularity
+                )?;
+                writeln!(
+                    f,
+                    "\tPTMControl: {} {} Granularity={}ns",
+                    flag("Enabled", ptm.enable),
+                    flag("RootSelected", ptm.root_select),
+                    ptm.effective_granularity
+                )?;
+            }
+            CapabilityData::ResizableBar(bars) => {
+                for bar in bars {
+                    let sizes: Vec<String> = bar
+                        .supported_sizes
+                        .iter()
+                        .map(|s| format_size(*s))
+                        .collect();
+                    writeln!(
+                        f,
+                        "\tBAR {}: current size: {}, supported: {}",
+                        bar.bar,
+                        format_size(bar.size),
+                        sizes.join(" ")
+                    )?;
+                }
+            }
+            CapabilityData::Dvsec(dvsec) => {
+                writeln!(
+                    f,
+                    "\tDVSEC Vendor={:04x} ID={:04x} Rev={} Len={}",
+                    dvsec.vendor_id, dvsec.id, dvsec.revision, dvsec.length
+                )?;
+            }
+            CapabilityData::Raw => {}
+        }
+        Ok(())
+    }
+}
+
+fn aspm_name(aspm: u8) -> &'static str {
+    match aspm {
+        0 => "Disabled",
+        1 => "L0s",
+        2 => "L1",
+        _ => "L0s L1",
+    }
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        s if s >= 1 << 30 => format!("{}GB", s >> 30),
+        s => format!("{}MB", s >> 20),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_capabilities() {
+        let mut data = vec![0u8; 0x200];
+        data[0x06] = 0x10;
+        data[0x34] = 0x40;
+        // PM -> PCIe root port, Gen4 x16 capable, trained at Gen1 x4
+        data[0x40..0x48].copy_from_slice(&[0x01, 0x50, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00]);
+        data[0x50..0x54].copy_from_slice(&[0x10, 0x00, 0x42, 0x00]);
+        data[0x5c..0x60].copy_from_slice(&0x0000_0104u32.to_le_bytes());
+        data[0x62..0x64].copy_from_slice(&0x0041u16.to_le_bytes());
+        // AER with a receiver error -> L1SS, terminating the list
+        data[0x100..0x104].copy_from_slice(&0x1401_0001u32.to_le_bytes());
+        data[0x110..0x114].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x140..0x144].copy_from_slice(&0x0001_001eu32.to_le_bytes());
+        data[0x144..0x148].copy_from_slice(&0x0000_000fu32.to_le_bytes());
+
+        let caps = all_capabilities(&data);
+        assert_eq!(caps.len(), 4);
+
+        match &caps[0].data {
+            CapabilityData::PowerManagement(pm) => assert_eq!(pm.power_state, 3),
+            _ => panic!("expected power management"),
+        }
+        match &caps[1].data {
+            CapabilityData::Express(exp) => {
+                assert_eq!(exp.device_port_type, 4);
+                assert_eq!((exp.max_link_speed, exp.max_link_width), (4, 16));
+                assert_eq!((exp.current_link_speed, exp.negotiated_link_width), (1, 4));
+            }
+            _ => panic!("expected pci express"),
+        }
+        assert_eq!(caps[2].offset, 0x100);
+        match &caps[2].data {
+            CapabilityData::Aer(aer) => assert_eq!(aer.correctable_errors, vec!["RxErr"]),
+            _ => panic!("expected aer"),
+        }
+        match &caps[3].data {
+            CapabilityData::L1Substates(l1ss) => assert_eq!(l1ss.supported.len(), 4),
+            _ => panic!("expected l1 pm substates"),
+        }
+
+        // the list of a dump without the device specific area is empty
+        assert!(capabilities(&data[..HEADER_SIZE]).is_empty());
+    }
+}
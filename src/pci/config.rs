@@ -0,0 +1,618 @@
+//! Decoder for the standard PCI configuration space header (type 0 and type 1)
+
+use crate::error::{Error, Result};
+use serde::Serialize;
+use std::fmt;
+
+/// Size of the standard configuration header
+pub const HEADER_SIZE: usize = 0x40;
+
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRegister {
+    pub raw: u16,
+    pub io_space: bool,
+    pub memory_space: bool,
+    pub bus_master: bool,
+    pub special_cycles: bool,
+    pub memory_write_invalidate: bool,
+    pub vga_palette_snoop: bool,
+    pub parity_error_response: bool,
+    pub serr_enable: bool,
+    pub fast_back_to_back: bool,
+    pub interrupt_disable: bool,
+}
+
+impl CommandRegister {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            raw,
+            io_space: raw & (1 << 0) != 0,
+            memory_space: raw & (1 << 1) != 0,
+            bus_master: raw & (1 << 2) != 0,
+            special_cycles: raw & (1 << 3) != 0,
+            memory_write_invalidate: raw & (1 << 4) != 0,
+            vga_palette_snoop: raw & (1 << 5) != 0,
+            parity_error_response: raw & (1 << 6) != 0,
+            serr_enable: raw & (1 << 8) != 0,
+            fast_back_to_back: raw & (1 << 9) != 0,
+            interrupt_disable: raw & (1 << 10) != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusRegister {
+    pub raw: u16,
+    pub interrupt_status: bool,
+    pub capabilities_list: bool,
+    pub mhz66_capable: bool,
+    pub fast_back_to_back_capable: bool,
+    pub master_data_parity_error: bool,
+    /// 0: fast, 1: medium, 2: slow
+    pub devsel_timing: u8,
+    pub signaled_target_abort: bool,
+    pub received_target_abort: bool,
+    pub received_master_abort: bool,
+    pub signaled_system_error: bool,
+    pub detected_parity_error: bool,
+}
+
+impl StatusRegister {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            raw,
+            interrupt_status: raw & (1 << 3) != 0,
+            capabilities_list: raw & (1 << 4) != 0,
+            mhz66_capable: raw & (1 << 5) != 0,
+            fast_back_to_back_capable: raw & (1 << 7) != 0,
+            master_data_parity_error: raw & (1 << 8) != 0,
+            devsel_timing: ((raw >> 9) & 0x3) as u8,
+            signaled_target_abort: raw & (1 << 11) != 0,
+            received_target_abort: raw & (1 << 12) != 0,
+            received_master_abort: raw & (1 << 13) != 0,
+            signaled_system_error: raw & (1 << 14) != 0,
+            detected_parity_error: raw & (1 << 15) != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum BarKind {
+    Io,
+    Memory32,
+    /// 32-bit memory BAR below 1 MiB, legacy type 01b
+    Memory1M,
+    Memory64,
+}
+
+/// A decoded base address register
+#[derive(Debug, Clone, Serialize)]
+pub struct Bar {
+    /// BAR number, a 64-bit BAR uses the following slot as well
+    pub index: usize,
+    pub kind: BarKind,
+    pub prefetchable: bool,
+    pub address: u64,
+    /// size as reported by the kernel in the sysfs `resource` node
+    pub size: Option<u64>,
+}
+
+/// An address window forwarded by a bridge, `limit` is inclusive
+#[derive(Debug, Clone, Serialize)]
+pub struct Window {
+    pub base: u64,
+    pub limit: u64,
+    /// the window supports 32-bit I/O or 64-bit memory addresses
+    pub wide: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpansionRom {
+    pub address: u32,
+    pub enabled: bool,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeControl {
+    pub raw: u16,
+    pub parity_error_response: bool,
+    pub serr_enable: bool,
+    pub isa_enable: bool,
+    pub vga_enable: bool,
+    pub vga_16bit_decode: bool,
+    pub master_abort_mode: bool,
+    pub secondary_bus_reset: bool,
+    pub fast_back_to_back: bool,
+}
+
+impl BridgeControl {
+    pub fn from_raw(raw: u16) -> Self {
+        Self {
+            raw,
+            parity_error_response: raw & (1 << 0) != 0,
+            serr_enable: raw & (1 << 1) != 0,
+            isa_enable: raw & (1 << 2) != 0,
+            vga_enable: raw & (1 << 3) != 0,
+            vga_16bit_decode: raw & (1 << 4) != 0,
+            master_abort_mode: raw & (1 << 5) != 0,
+            secondary_bus_reset: raw & (1 << 6) != 0,
+            fast_back_to_back: raw & (1 << 7) != 0,
+        }
+    }
+}
+
+/// Fields of a type 0 (endpoint) header
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHeader {
+    pub cardbus_cis_pointer: u32,
+    pub subsystem_vendor_id: u16,
+    pub subsystem_id: u16,
+    pub min_grant: u8,
+    pub max_latency: u8,
+}
+
+/// Fields of a type 1 (PCI-to-PCI bridge) header
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeHeader {
+    pub primary_bus: u8,
+    pub secondary_bus: u8,
+    pub subordinate_bus: u8,
+    pub secondary_latency_timer: u8,
+    pub secondary_status: StatusRegister,
+    pub io_window: Option<Window>,
+    pub memory_window: Option<Window>,
+    pub prefetchable_window: Option<Window>,
+    pub bridge_control: BridgeControl,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum HeaderKind {
+    Endpoint(EndpointHeader),
+    Bridge(BridgeHeader),
+    /// header layouts other than type 0 and 1, e.g. CardBus bridges
+    Other(u8),
+}
+
+/// The decoded standard configuration header
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigHeader {
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub command: CommandRegister,
+    pub status: StatusRegister,
+    pub revision_id: u8,
+    pub prog_if: u8,
+    pub sub_class: u8,
+    pub base_class: u8,
+    pub cache_line_size: u8,
+    pub latency_timer: u8,
+    pub header_type: u8,
+    pub multi_function: bool,
+    pub bist: u8,
+    pub bars: Vec<Bar>,
+    pub expansion_rom: Option<ExpansionRom>,
+    pub capabilities_pointer: Option<u8>,
+    pub interrupt_line: u8,
+    pub interrupt_pin: u8,
+    pub kind: HeaderKind,
+}
+
+fn parse_bars(data: &[u8], count: usize) -> Vec<Bar> {
+    let mut bars = vec![];
+    let mut index = 0;
+    while index < count {
+        let raw = u32_at(data, 0x10 + index * 4);
+        let bar = if raw & 0x1 != 0 {
+            Bar {
+                index,
+                kind: BarKind::Io,
+                prefetchable: false,
+                address: (raw & !0x3) as u64,
+                size: None,
+            }
+        } else {
+            let prefetchable = raw & 0x8 != 0;
+            let address = (raw & !0xf) as u64;
+            match (raw >> 1) & 0x3 {
+                0x2 if index + 1 < count => {
+                    let upper = u32_at(data, 0x10 + (index + 1) * 4) as u64;
+                    Bar {
+                        index,
+                        kind: BarKind::Memory64,
+                        prefetchable,
+                        address: (upper << 32) | address,
+                        size: None,
+                    }
+                }
+                0x1 => Bar {
+                    index,
+                    kind: BarKind::Memory1M,
+                    prefetchable,
+                    address,
+                    size: None,
+                },
+                _ => Bar {
+                    index,
+                    kind: BarKind::Memory32,
+                    prefetchable,
+                    address,
+                    size: None,
+                },
+            }
+        };
+        index += if bar.kind == BarKind::Memory64 { 2 } else { 1 };
+        if raw != 0 || bar.kind == BarKind::Memory64 {
+            bars.push(bar);
+        }
+    }
+    bars
+}
+
+fn parse_expansion_rom(raw: u32) -> Option<ExpansionRom> {
+    if raw & 0xffff_f800 == 0 {
+        return None;
+    }
+    Some(ExpansionRom {
+        address: raw & 0xffff_f800,
+        enabled: raw & 0x1 != 0,
+        size: None,
+    })
+}
+
+fn window(base: u64, limit: u64, wide: bool) -> Option<Window> {
+    if base > limit {
+        // a base above the limit disables forwarding
+        return None;
+    }
+    Some(Window { base, limit, wide })
+}
+
+fn parse_bridge(data: &[u8]) -> BridgeHeader {
+    let io_base = data[0x1c];
+    let io_limit = data[0x1d];
+    let io_wide = io_base & 0xf == 0x1;
+    let (io_base_upper, io_limit_upper) = if io_wide {
+        (u16_at(data, 0x30) as u64, u16_at(data, 0x32) as u64)
+    } else {
+        (0, 0)
+    };
+    let io_window = window(
+        (io_base_upper << 16) | (((io_base & 0xf0) as u64) << 8),
+        (io_limit_upper << 16) | (((io_limit & 0xf0) as u64) << 8) | 0xfff,
+        io_wide,
+    );
+
+    let memory_window = window(
+        ((u16_at(data, 0x20) & 0xfff0) as u64) << 16,
+        (((u16_at(data, 0x22) & 0xfff0) as u64) << 16) | 0xf_ffff,
+        false,
+    );
+
+    let pref_base = u16_at(data, 0x24);
+    let pref_limit = u16_at(data, 0x26);
+    let pref_wide = pref_base & 0xf == 0x1;
+    let (pref_base_upper, pref_limit_upper) = if pref_wide {
+        (u32_at(data, 0x28) as u64, u32_at(data, 0x2c) as u64)
+    } else {
+        (0, 0)
+    };
+    let prefetchable_window = window(
+        (pref_base_upper << 32) | (((pref_base & 0xfff0) as u64) << 16),
+        (pref_limit_upper << 32) | (((pref_limit & 0xfff0) as u64) << 16) | 0xf_ffff,
+        pref_wide,
+    );
+
+    BridgeHeader {
+        primary_bus: data[0x18],
+        secondary_bus: data[0x19],
+        subordinate_bus: data[0x1a],
+        secondary_latency_timer: data[0x1b],
+        secondary_status: StatusRegister::from_raw(u16_at(data, 0x1e)),
+        io_window,
+        memory_window,
+        prefetchable_window,
+        bridge_control: BridgeControl::from_raw(u16_at(data, 0x3e)),
+    }
+}
+
+impl ConfigHeader {
+    /// Decode the standard header from raw configuration space data
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::Parse(format!(
+                "configuration header is {} bytes, expected at least {}",
+                data.len(),
+                HEADER_SIZE
+            )));
+        }
+
+        let status = StatusRegister::from_raw(u16_at(data, 0x06));
+        let header_type = data[0x0e] & 0x7f;
+        let (kind, bars, expansion_rom) = match header_type {
+            0 => (
+                HeaderKind::Endpoint(EndpointHeader {
+                    cardbus_cis_pointer: u32_at(data, 0x28),
+                    subsystem_vendor_id: u16_at(data, 0x2c),
+                    subsystem_id: u16_at(data, 0x2e),
+                    min_grant: data[0x3e],
+                    max_latency: data[0x3f],
+                }),
+                parse_bars(data, 6),
+                parse_expansion_rom(u32_at(data, 0x30)),
+            ),
+            1 => (
+                HeaderKind::Bridge(parse_bridge(data)),
+                parse_bars(data, 2),
+                parse_expansion_rom(u32_at(data, 0x38)),
+            ),
+            other => (HeaderKind::Other(other), vec![], None),
+        };
+
+        Ok(Self {
+            vendor_id: u16_at(data, 0x00),
+            device_id: u16_at(data, 0x02),
+            command: CommandRegister::from_raw(u16_at(data, 0x04)),
+            capabilities_pointer: if status.capabilities_list && header_type <= 1 {
+                Some(data[0x34] & 0xfc)
+            } else {
+                None
+            },
+            status,
+            revision_id: data[0x08],
+            prog_if: data[0x09],
+            sub_class: data[0x0a],
+            base_class: data[0x0b],
+            cache_line_size: data[0x0c],
+            latency_timer: data[0x0d],
+            header_type,
+            multi_function: data[0x0e] & 0x80 != 0,
+            bist: data[0x0f],
+            bars,
+            expansion_rom,
+            interrupt_line: data[0x3c],
+            interrupt_pin: data[0x3d],
+            kind,
+        })
+    }
+}
+
+fn flag(name: &str, set: bool) -> String {
+    format!("{}{}", name, if set { "+" } else { "-" })
+}
+
+fn fmt_window(f: &mut fmt::Formatter<'_>, name: &str, window: &Option<Window>) -> fmt::Result {
+    match window {
+        Some(w) => writeln!(f, "{:<24}{:#x}-{:#x}", name, w.base, w.limit),
+        None => writeln!(f, "{:<24}[disabled]", name),
+    }
+}
+
+impl fmt::Display for ConfigHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = &self.command;
+        let s = &self.status;
+        writeln!(f, "{:<24}{:04x}", "Vendor ID:", self.vendor_id)?;
+        writeln!(f, "{:<24}{:04x}", "Device ID:", self.device_id)?;
+        writeln!(
+            f,
+            "{:<24}{} {} {} {} {} {} {} {} {} {}",
+            "Command:",
+            flag("I/O", c.io_space),
+            flag("Mem", c.memory_space),
+            flag("BusMaster", c.bus_master),
+            flag("SpecCycle", c.special_cycles),
+            flag("MemWINV", c.memory_write_invalidate),
+            flag("VGASnoop", c.vga_palette_snoop),
+            flag("ParErr", c.parity_error_response),
+            flag("SERR", c.serr_enable),
+            flag("FastB2B", c.fast_back_to_back),
+            flag("DisINTx", c.interrupt_disable),
+        )?;
+        writeln!(
+            f,
+            "{:<24}{} {} {} {} DEVSEL={} {} {} {} {} {}",
+            "Status:",
+            flag("Cap", s.capabilities_list),
+            flag("66MHz", s.mhz66_capable),
+            flag("FastB2B", s.fast_back_to_back_capable),
+            flag("INTx", s.interrupt_status),
+            match s.devsel_timing {
+                0 => "fast",
+                1 => "medium",
+                2 => "slow",
+                _ => "??",
+            },
+            flag(">TAbort", s.signaled_target_abort),
+            flag("<TAbort", s.received_target_abort),
+            flag("<MAbort", s.received_master_abort),
+            flag(">SERR", s.signaled_system_error),
+            flag("<PERR", s.detected_parity_error),
+        )?;
+        writeln!(f, "{:<24}{:02x}", "Revision:", self.revision_id)?;
+        writeln!(
+            f,
+            "{:<24}{:02x}{:02x}{:02x}",
+            "Class Code:", self.base_class, self.sub_class, self.prog_if
+        )?;
+        writeln!(
+            f,
+            "{:<24}{} bytes",
+            "Cache Line Size:",
+            self.cache_line_size as u32 * 4
+        )?;
+        writeln!(f, "{:<24}{}", "Latency Timer:", self.latency_timer)?;
+        writeln!(
+            f,
+            "{:<24}{:02x}{}",
+            "Header Type:",
+            self.header_type,
+            if self.multi_function {
+                " (multi-function)"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(f, "{:<24}{:02x}", "BIST:", self.bist)?;
+        for bar in &self.bars {
+            let kind = match bar.kind {
+                BarKind::Io => "I/O ports",
+                BarKind::Memory32 => "Memory 32-bit",
+                BarKind::Memory1M => "Memory below 1M",
+                BarKind::Memory64 => "Memory 64-bit",
+            };
+            write!(
+                f,
+                "{:<24}{} at {:#x}{}",
+                format!("BAR{}:", bar.index),
+                kind,
+                bar.address,
+                if bar.prefetchable {
+                    " prefetchable"
+                } else {
+                    ""
+                }
+            )?;
+            match bar.size {
+                Some(size) => writeln!(f, " [size={:#x}]", size)?,
+                None => writeln!(f)?,
+            }
+        }
+        if let Some(rom) = &self.expansion_rom {
+            writeln!(
+                f,
+                "{:<24}{:#x} [{}]",
+                "Expansion ROM:",
+                rom.address,
+                if rom.enabled { "enabled" } else { "disabled" }
+            )?;
+        }
+        if let Some(ptr) = self.capabilities_pointer {
+            writeln!(f, "{:<24}{:02x}", "Capabilities Pointer:", ptr)?;
+        }
+        writeln!(
+            f,
+            "{:<24}line {} pin {}",
+            "Interrupt:",
+            self.interrupt_line,
+            match self.interrupt_pin {
+                0 => "none",
+                1 => "INTA",
+                2 => "INTB",
+                3 => "INTC",
+                4 => "INTD",
+                _ => "??",
+            }
+        )?;
+
+        match &self.kind {
+            HeaderKind::Endpoint(ep) => {
+                writeln!(
+                    f,
+                    "{:<24}{:04x}:{:04x}",
+                    "Subsystem:", ep.subsystem_vendor_id, ep.subsystem_id
+                )?;
+                writeln!(
+                    f,
+                    "{:<24}{:08x}",
+                    "CardBus CIS Pointer:", ep.cardbus_cis_pointer
+                )?;
+                writeln!(f, "{:<24}{}", "Min Grant:", ep.min_grant)?;
+                writeln!(f, "{:<24}{}", "Max Latency:", ep.max_latency)?;
+            }
+            HeaderKind::Bridge(br) => {
+                writeln!(
+                    f,
+                    "{:<24}primary={:02x} secondary={:02x} subordinate={:02x} sec-latency={}",
+                    "Bus:",
+                    br.primary_bus,
+                    br.secondary_bus,
+                    br.subordinate_bus,
+                    br.secondary_latency_timer
+                )?;
+                fmt_window(f, "I/O behind bridge:", &br.io_window)?;
+                fmt_window(f, "Memory behind bridge:", &br.memory_window)?;
+                fmt_window(f, "Prefetchable memory:", &br.prefetchable_window)?;
+                let bc = &br.bridge_control;
+                writeln!(
+                    f,
+                    "{:<24}{} {} {} {} {} {} {} {}",
+                    "Bridge Control:",
+                    flag("Parity", bc.parity_error_response),
+                    flag("SERR", bc.serr_enable),
+                    flag("ISA", bc.isa_enable),
+                    flag("VGA", bc.vga_enable),
+                    flag("VGA16", bc.vga_16bit_decode),
+                    flag("MAbort", bc.master_abort_mode),
+                    flag(">Reset", bc.secondary_bus_reset),
+                    flag("FastB2B", bc.fast_back_to_back),
+                )?;
+            }
+            HeaderKind::Other(header_type) => {
+                writeln!(f, "header type {:02x} is not decoded", header_type)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bridge_header() {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0x00..0x04].copy_from_slice(&[0x86, 0x80, 0x34, 0x12]);
+        data[0x04] = 0x07;
+        data[0x06] = 0x10;
+        data[0x0e] = 0x81;
+        // 64-bit prefetchable BAR0 at 0x1_2345_0000
+        data[0x10..0x14].copy_from_slice(&0x2345_000cu32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+        data[0x18..0x1b].copy_from_slice(&[0, 1, 3]);
+        // I/O window 0x1000-0x1fff, memory window 0xfe00_0000-0xfe1f_ffff
+        data[0x1c] = 0x10;
+        data[0x1d] = 0x10;
+        data[0x20..0x24].copy_from_slice(&[0x00, 0xfe, 0x10, 0xfe]);
+        // prefetchable window disabled
+        data[0x24..0x28].copy_from_slice(&[0xf1, 0xff, 0x01, 0x00]);
+        data[0x34] = 0x40;
+
+        let header = ConfigHeader::parse(&data).unwrap();
+        assert!(header.command.bus_master);
+        assert!(header.multi_function);
+        assert_eq!(header.capabilities_pointer, Some(0x40));
+        assert_eq!(header.bars.len(), 1);
+        assert_eq!(header.bars[0].kind, BarKind::Memory64);
+        assert!(header.bars[0].prefetchable);
+        assert_eq!(header.bars[0].address, 0x1_2345_0000);
+
+        match header.kind {
+            HeaderKind::Bridge(br) => {
+                assert_eq!((br.secondary_bus, br.subordinate_bus), (1, 3));
+                let io = br.io_window.unwrap();
+                assert_eq!((io.base, io.limit), (0x1000, 0x1fff));
+                let mem = br.memory_window.unwrap();
+                assert_eq!((mem.base, mem.limit), (0xfe00_0000, 0xfe1f_ffff));
+                assert!(br.prefetchable_window.is_none());
+            }
+            _ => panic!("expected a bridge header"),
+        }
+
+        assert!(ConfigHeader::parse(&data[..0x20]).is_err());
+    }
+}
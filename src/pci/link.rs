@@ -0,0 +1,158 @@
+//! PCIe link report built from the PCI Express capability of every device
+
+use super::caps::{self, CapabilityData, Express};
+use super::{devices, PciDevice};
+use serde::Serialize;
+
+/// Link speed and width of one end of a PCIe link
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkState {
+    pub device: PciDevice,
+    pub port_type: &'static str,
+    /// speed encodings, see `caps::link_speed_name`
+    pub max_speed: u8,
+    pub max_width: u8,
+    pub speed: u8,
+    pub width: u8,
+    pub active: bool,
+}
+
+impl LinkState {
+    fn new(device: PciDevice, exp: &Express) -> Self {
+        Self {
+            device,
+            port_type: caps::device_port_type_name(exp.device_port_type),
+            max_speed: exp.max_link_speed,
+            max_width: exp.max_link_width,
+            speed: exp.current_link_speed,
+            width: exp.negotiated_link_width,
+            active: exp.data_link_layer_active,
+        }
+    }
+}
+
+/// A device's link together with the port it connects to
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkReport {
+    pub link: LinkState,
+    pub upstream: Option<LinkState>,
+    /// the link trained below the speed both ends are capable of
+    pub speed_degraded: bool,
+    /// the link trained below the width both ends are capable of
+    pub width_degraded: bool,
+}
+
+impl LinkReport {
+    /// Compares the link of a device with what both ends of it support
+    fn new(link: LinkState, upstream: Option<LinkState>) -> Self {
+        // the link is capable of what both ends support
+        let (max_speed, max_width) = match &upstream {
+            Some(port) => (
+                link.max_speed.min(port.max_speed),
+                link.max_width.min(port.max_width),
+            ),
+            None => (link.max_speed, link.max_width),
+        };
+        // a link that is down has neither speed nor width to compare
+        let trained = link.speed != 0 && link.width != 0;
+        Self {
+            speed_degraded: trained && link.speed < max_speed,
+            width_degraded: trained && link.width < max_width,
+            link,
+            upstream,
+        }
+    }
+
+    pub fn degraded(&self) -> bool {
+        self.speed_degraded || self.width_degraded
+    }
+}
+
+/// The PCI Express capability of a device, if it has a link
+pub fn express(device: &PciDevice) -> Option<Express> {
+    device
+        .capabilities()
+        .ok()?
+        .into_iter()
+        .find_map(|cap| match cap.data {
+            CapabilityData::Express(exp) if exp.has_link() => Some(exp),
+            _ => None,
+        })
+}
+
+/// Root and downstream ports are the upstream end of their link
+fn is_downstream_facing(exp: &Express) -> bool {
+    matches!(exp.device_port_type, 0x4 | 0x6 | 0x8)
+}
+
+/// Link report of every device on the downstream end of a PCIe link
+pub fn links() -> Vec<LinkReport> {
+    let mut reports = vec![];
+    for (device, _) in devices() {
+        let exp = match express(&device) {
+            Some(exp) if !is_downstream_facing(&exp) => exp,
+            _ => continue,
+        };
+        let link = LinkState::new(device.clone(), &exp);
+        let upstream = device
+            .parent()
+            .and_then(|port| express(&port).map(|e| LinkState::new(port, &e)));
+        reports.push(LinkReport::new(link, upstream));
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// End of a link decoded from a PCI Express capability with the given
+    /// Link Capabilities and Link Status registers
+    fn state(port_type: u16, linkcap: u32, linksta: u16) -> LinkState {
+        let mut data = vec![0u8; 0x100];
+        data[0x06] = 0x10;
+        data[0x34] = 0x40;
+        data[0x40] = 0x10;
+        data[0x42..0x44].copy_from_slice(&(0x2 | port_type << 4).to_le_bytes());
+        data[0x4c..0x50].copy_from_slice(&linkcap.to_le_bytes());
+        data[0x52..0x54].copy_from_slice(&linksta.to_le_bytes());
+        let exp = caps::capabilities(&data)
+            .into_iter()
+            .find_map(|cap| match cap.data {
+                CapabilityData::Express(exp) => Some(exp),
+                _ => None,
+            })
+            .unwrap();
+        LinkState::new(PciDevice::new(0, 1, 0, 0), &exp)
+    }
+
+    #[test]
+    fn test_degraded() {
+        // Gen4 x16 root port
+        let port = || Some(state(0x4, 0x0000_0104, 0x0104));
+
+        // Gen4 x16 endpoint trained at Gen4 x16
+        let report = LinkReport::new(state(0x0, 0x0000_0104, 0x0104), port());
+        assert!(!report.degraded());
+
+        // trained at Gen1 below the Gen4 both ends support
+        let report = LinkReport::new(state(0x0, 0x0000_0104, 0x0101), port());
+        assert!(report.speed_degraded && !report.width_degraded);
+
+        // trained at x4 below the x16 both ends support
+        let report = LinkReport::new(state(0x0, 0x0000_0104, 0x0044), port());
+        assert!(!report.speed_degraded && report.width_degraded);
+
+        // a Gen5 x16 endpoint behind a Gen3 x8 port runs at what the port allows
+        let narrow = Some(state(0x4, 0x0000_0083, 0x0083));
+        let report = LinkReport::new(state(0x0, 0x0000_0105, 0x0083), narrow);
+        assert!(!report.degraded());
+        // without the port it would count as degraded
+        let report = LinkReport::new(state(0x0, 0x0000_0105, 0x0083), None);
+        assert!(report.speed_degraded && report.width_degraded);
+
+        // a link that is down is not degraded
+        let report = LinkReport::new(state(0x0, 0x0000_0104, 0x0000), port());
+        assert!(!report.degraded());
+    }
+}
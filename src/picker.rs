@@ -0,0 +1,262 @@
+//! PCI device picker screen, opens the decoded configuration of the selected
+//! device and from there its configuration space in the hex matrix
+
+use super::matrix::{self, Matrix, PAGE_SIZE};
+use super::pci::{self, bar::PciBar, PciConfig, PciDevBasicInfo, PciDevice};
+use super::textview::{self, TextView};
+use crossterm::event::{self, Event, KeyCode};
+use std::io;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::Terminal;
+
+/// Whether a device matches every whitespace separated term of `filter`.
+///
+/// `class:<term>` and `vendor:<term>` match the class or vendor id prefix or
+/// name, any other term matches the slot or the description.
+pub fn matches(device: &PciDevice, info: &PciDevBasicInfo, filter: &str) -> bool {
+    let id_or_name = |id: &(String, Option<String>), term: &str| {
+        id.0.to_lowercase().starts_with(term)
+            || id
+                .1
+                .as_ref()
+                .is_some_and(|name| name.to_lowercase().contains(term))
+    };
+    filter.to_lowercase().split_whitespace().all(|term| {
+        if let Some(class) = term.strip_prefix("class:") {
+            id_or_name(&info.class, class) || id_or_name(&info.sub_class, class)
+        } else if let Some(vendor) = term.strip_prefix("vendor:") {
+            id_or_name(&info.vendor, vendor)
+        } else {
+            device.to_string().contains(term) || info.description().to_lowercase().contains(term)
+        }
+    })
+}
+
+enum PickerMode {
+    Browse,
+    Filter,
+}
+
+pub struct Picker {
+    devices: Vec<(PciDevice, PciDevBasicInfo)>,
+    filter: String,
+    mode: PickerMode,
+    state: ListState,
+}
+
+impl Picker {
+    pub fn new() -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            devices: pci::devices(),
+            filter: String::new(),
+            mode: PickerMode::Browse,
+            state,
+        }
+    }
+
+    fn visible(&self) -> Vec<&(PciDevice, PciDevBasicInfo)> {
+        self.devices
+            .iter()
+            .filter(|(device, info)| matches(device, info, &self.filter))
+            .collect()
+    }
+
+    fn select_next(&mut self) {
+        let len = self.visible().len();
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some((i + 1).min(len.saturating_sub(1))));
+    }
+
+    fn select_prev(&mut self) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(i.saturating_sub(1)));
+    }
+
+    fn selected(&self) -> Option<PciDevice> {
+        let visible = self.visible();
+        let (device, _) = visible.get(self.state.selected()?)?;
+        Some(device.clone())
+    }
+}
+
+impl Default for Picker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decoded header and capabilities of a device as text
+fn describe(device: &PciDevice) -> String {
+    let mut text = String::new();
+    if let Some(info) = device.basic_info() {
+        text.push_str(&format!("{} {}\n", device, info.description()));
+    }
+    if let Some(driver) = device.driver() {
+        text.push_str(&format!("Driver: {}\n", driver));
+    }
+    text.push('\n');
+    match device.decoded_config() {
+        Ok(header) => text.push_str(&header.to_string()),
+        Err(err) => text.push_str(&format!("{}\n", err)),
+    }
+    let config_len = device.config_data().map_or(0, |c| c.len());
+    if config_len <= pci::config::HEADER_SIZE {
+        text.push_str("Capabilities: <access denied>\n");
+    }
+    for cap in device.capabilities().unwrap_or_default() {
+        text.push_str(&cap.to_string());
+    }
+    text
+}
+
+fn draw_picker<B: Backend>(terminal: &mut Terminal<B>, p: &mut Picker) -> io::Result<()> {
+    let visible = p.visible();
+    let items: Vec<ListItem> = visible
+        .iter()
+        .map(|(device, info)| ListItem::new(format!("{} {}", device, info.description())))
+        .collect();
+    let title = format!(" PCI devices ({}/{}) ", visible.len(), p.devices.len());
+    let filter = match p.mode {
+        PickerMode::Filter => format!("/{}", p.filter),
+        PickerMode::Browse if p.filter.is_empty() => {
+            String::from("j/k: move  Enter: open  /: filter (class:<id> vendor:<id>)  q: quit")
+        }
+        PickerMode::Browse => format!("filter: {}", p.filter),
+    };
+    let filter_style = match p.mode {
+        PickerMode::Filter => Style::default().fg(Color::Green),
+        PickerMode::Browse => Style::default(),
+    };
+
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, chunks[0], &mut p.state);
+        f.render_widget(Paragraph::new(filter).style(filter_style), chunks[1]);
+    })?;
+    Ok(())
+}
+
+/// Shows the details of `device` until the user goes back to the list
+fn device_screen<B: Backend>(terminal: &mut Terminal<B>, device: &PciDevice) -> io::Result<()> {
+    let mut view = TextView::new(&device.to_string(), &describe(device));
+    let mut message = String::new();
+    loop {
+        let help = format!(
+            "j/k: scroll  x: config hex view  0-5: BAR hex view  q/Esc: back  {}",
+            message
+        );
+        textview::draw(terminal, &view, &help)?;
+
+        if let Event::Key(key) = event::read()? {
+            message.clear();
+            if view.scroll_key(key.code) {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('x') => match PciConfig::new(device.clone(), PAGE_SIZE) {
+                    Ok(config) => {
+                        let mut m = Matrix::new(&format!("{} config", device), config);
+                        matrix::start(terminal, &mut m)?;
+                        // registers may have been edited in the hex view
+                        view.lines = TextView::new("", &describe(device)).lines;
+                    }
+                    Err(err) => message = err.to_string(),
+                },
+                KeyCode::Char(c @ '0'..='5') => {
+                    let index = c as usize - '0' as usize;
+                    match PciBar::new(device.clone(), index, PAGE_SIZE) {
+                        Ok(bar) => {
+                            let mut m = Matrix::new(&format!("{} BAR{}", device, index), bar);
+                            matrix::start(terminal, &mut m)?;
+                        }
+                        Err(err) => message = err.to_string(),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Runs the device picker until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, p: &mut Picker) -> io::Result<()> {
+    loop {
+        draw_picker(terminal, p)?;
+
+        if let Event::Key(key) = event::read()? {
+            match p.mode {
+                PickerMode::Browse => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down => p.select_next(),
+                    KeyCode::Char('k') | KeyCode::Up => p.select_prev(),
+                    KeyCode::Char('/') => p.mode = PickerMode::Filter,
+                    KeyCode::Esc => {
+                        p.filter.clear();
+                        p.state.select(Some(0));
+                    }
+                    KeyCode::Enter => {
+                        if let Some(device) = p.selected() {
+                            device_screen(terminal, &device)?;
+                        }
+                    }
+                    _ => {}
+                },
+                PickerMode::Filter => {
+                    match key.code {
+                        KeyCode::Char(c) => p.filter.push(c),
+                        KeyCode::Backspace => {
+                            p.filter.pop();
+                        }
+                        KeyCode::Enter | KeyCode::Esc => p.mode = PickerMode::Browse,
+                        _ => {}
+                    }
+                    p.state.select(Some(0));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let device = PciDevice::new(0, 0x3a, 0, 1);
+        let info = PciDevBasicInfo {
+            vendor: (
+                String::from("8086"),
+                Some(String::from("Intel Corporation")),
+            ),
+            device: (String::from("a0f0"), None),
+            class: (String::from("02"), Some(String::from("Network controller"))),
+            sub_class: (String::from("80"), None),
+            prog_if: (String::from("00"), None),
+            revision: String::from("20"),
+        };
+        assert!(matches(&device, &info, ""));
+        assert!(matches(&device, &info, "3a:00"));
+        assert!(matches(&device, &info, "class:02 vendor:intel"));
+        assert!(matches(&device, &info, "Network"));
+        assert!(!matches(&device, &info, "class:03"));
+        assert!(!matches(&device, &info, "vendor:10de"));
+    }
+}
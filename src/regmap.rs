@@ -0,0 +1,335 @@
+//! Register maps that name the registers and bitfields of a device at offsets
+//! relative to a base address, loaded from JSON files or imported from
+//! CMSIS-SVD files
+
+pub mod svd;
+
+use super::error::{Error, Result};
+use super::matrix::DataType;
+use super::utils::parse_u64;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A bitfield of `width` bits starting at bit `lsb` of a register
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub lsb: u32,
+    pub width: u32,
+    pub description: Option<String>,
+}
+
+impl Field {
+    pub fn msb(&self) -> u32 {
+        self.lsb + self.width - 1
+    }
+
+    /// Largest value the field holds
+    pub fn max(&self) -> u64 {
+        match self.width {
+            64 => u64::MAX,
+            width => (1 << width) - 1,
+        }
+    }
+
+    /// Bits of the field within the register
+    pub fn mask(&self) -> u64 {
+        self.max() << self.lsb
+    }
+
+    /// Value of the field in the register value `value`
+    pub fn get(&self, value: u64) -> u64 {
+        (value & self.mask()) >> self.lsb
+    }
+
+    /// Replaces the field in the register value `value`, the other bits are
+    /// left as they are
+    pub fn set(&self, value: u64, field: u64) -> Result<u64> {
+        if field > self.max() {
+            return Err(Error::Parse(format!(
+                "0x{:x} does not fit in the {} bits of {}",
+                field, self.width, self.name
+            )));
+        }
+        Ok((value & !self.mask()) | (field << self.lsb))
+    }
+
+    /// Bit range as `msb:lsb`, or the bit number of a one bit field
+    pub fn bits(&self) -> String {
+        match self.width {
+            1 => format!("{}", self.lsb),
+            _ => format!("{}:{}", self.msb(), self.lsb),
+        }
+    }
+}
+
+/// A register of `size` bits at `offset` from the base of its map, fields
+/// are ordered from the most significant one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Register {
+    pub name: String,
+    pub offset: u64,
+    pub size: u32,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+impl Register {
+    /// Size in bytes
+    pub fn bytes(&self) -> u64 {
+        self.size.div_ceil(8) as u64
+    }
+
+    /// Access width of the register, only 8, 16, 32 and 64 bit registers
+    /// can be written field by field
+    pub fn data_type(&self) -> Option<DataType> {
+        match self.size {
+            8 => Some(DataType::Byte),
+            16 => Some(DataType::Word),
+            32 => Some(DataType::DoubleWord),
+            64 => Some(DataType::QuadWord),
+            _ => None,
+        }
+    }
+
+    fn check(&mut self) -> Result<()> {
+        if self.size == 0 || self.size > 64 {
+            return Err(Error::Parse(format!(
+                "{}: size of {} bits is not supported",
+                self.name, self.size
+            )));
+        }
+        for field in &self.fields {
+            let end = field.lsb.checked_add(field.width);
+            if field.width == 0 || end.is_none_or(|end| end > self.size) {
+                return Err(Error::Parse(format!(
+                    "{}.{}: {} bits at bit {} do not fit in {} bits",
+                    self.name, field.name, field.width, field.lsb, self.size
+                )));
+            }
+        }
+        self.fields.sort_by_key(|field| Reverse(field.lsb));
+        Ok(())
+    }
+}
+
+/// Registers at offsets from `base`, sorted by offset
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RegisterMap {
+    pub name: String,
+    pub base: u64,
+    pub registers: Vec<Register>,
+}
+
+/// Integer in a JSON register map, either a number or a string with a
+/// `0x` prefix
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonNumber {
+    Number(u64),
+    Text(String),
+}
+
+impl JsonNumber {
+    fn value(&self) -> Result<u64> {
+        match self {
+            JsonNumber::Number(n) => Ok(*n),
+            JsonNumber::Text(s) => {
+                parse_u64(s.trim()).map_err(|_| Error::Parse(format!("invalid number: {}", s)))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonField {
+    name: String,
+    /// `msb:lsb` or a single bit number
+    bits: Option<String>,
+    lsb: Option<u32>,
+    width: Option<u32>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonRegister {
+    name: String,
+    offset: JsonNumber,
+    size: Option<u32>,
+    description: Option<String>,
+    #[serde(default)]
+    fields: Vec<JsonField>,
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    name: Option<String>,
+    base: Option<JsonNumber>,
+    registers: Vec<JsonRegister>,
+}
+
+/// Parses a bit range as `msb:lsb` or a single bit number into the lsb and
+/// the width
+pub fn parse_bits(s: &str) -> Result<(u32, u32)> {
+    let invalid = || Error::Parse(format!("invalid bit range: {}", s));
+    let s = s.trim().trim_start_matches('[').trim_end_matches(']');
+    let (msb, lsb) = match s.split_once(':') {
+        Some((msb, lsb)) => (msb, lsb),
+        None => (s, s),
+    };
+    let msb: u32 = msb.trim().parse().map_err(|_| invalid())?;
+    let lsb: u32 = lsb.trim().parse().map_err(|_| invalid())?;
+    if msb < lsb {
+        return Err(invalid());
+    }
+    Ok((lsb, msb - lsb + 1))
+}
+
+impl RegisterMap {
+    /// Builds a map from registers in any order and checks that the fields
+    /// fit in their registers
+    pub fn new(name: &str, base: u64, mut registers: Vec<Register>) -> Result<Self> {
+        for register in &mut registers {
+            register.check()?;
+        }
+        registers.sort_by_key(|r| r.offset);
+        Ok(Self {
+            name: String::from(name),
+            base,
+            registers,
+        })
+    }
+
+    /// Parses a JSON register map:
+    ///
+    /// ```json
+    /// { "name": "uart", "base": "0xfe201000", "registers": [
+    ///   { "name": "FR", "offset": "0x18", "size": 32, "fields": [
+    ///     { "name": "TXFF", "bits": "5" }, { "name": "BUSY", "lsb": 3 } ] } ] }
+    /// ```
+    ///
+    /// Registers are 32 bits wide and fields one bit wide unless stated
+    pub fn from_json(name: &str, content: &str) -> Result<Self> {
+        let map: JsonMap = serde_json::from_str(content)
+            .map_err(|err| Error::Parse(format!("{}: {}", name, err)))?;
+        let mut registers = vec![];
+        for register in map.registers {
+            let mut fields = vec![];
+            for field in register.fields {
+                let (lsb, width) = match (&field.bits, field.lsb) {
+                    (Some(bits), _) => parse_bits(bits)?,
+                    (None, Some(lsb)) => (lsb, field.width.unwrap_or(1)),
+                    (None, None) => {
+                        return Err(Error::Parse(format!(
+                            "{}.{}: bits or lsb required",
+                            register.name, field.name
+                        )))
+                    }
+                };
+                fields.push(Field {
+                    name: field.name,
+                    lsb,
+                    width,
+                    description: field.description,
+                });
+            }
+            registers.push(Register {
+                name: register.name,
+                offset: register.offset.value()?,
+                size: register.size.unwrap_or(32),
+                description: register.description,
+                fields,
+            });
+        }
+        let base = match &map.base {
+            Some(base) => base.value()?,
+            None => 0,
+        };
+        Self::new(map.name.as_deref().unwrap_or(name), base, registers)
+    }
+
+    /// Loads a register map, files ending in `.svd` or `.xml` are imported
+    /// as CMSIS-SVD and all others are read as JSON
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(String::from(path)),
+            _ => Error::Io(err),
+        })?;
+        let path = Path::new(path);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svd") | Some("xml") => svd::parse(&content),
+            _ => Self::from_json(&name, &content),
+        }
+    }
+
+    /// Address of `register`
+    pub fn address(&self, register: &Register) -> u64 {
+        self.base + register.offset
+    }
+
+    /// Register that holds the byte at `addr`
+    pub fn register_at(&self, addr: u64) -> Option<&Register> {
+        let offset = addr.checked_sub(self.base)?;
+        self.registers
+            .iter()
+            .find(|r| r.offset <= offset && offset - r.offset < r.bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let map = RegisterMap::from_json(
+            "uart",
+            r#"{ "base": "0x1000", "registers": [
+                { "name": "CR", "offset": 48, "size": 16, "fields": [
+                    { "name": "EN", "bits": "0" },
+                    { "name": "MODE", "bits": "7:4", "description": "mode" } ] },
+                { "name": "DR", "offset": "0x0", "fields": [
+                    { "name": "DATA", "lsb": 0, "width": 8 } ] } ] }"#,
+        )
+        .unwrap();
+        assert_eq!(map.name, "uart");
+        assert_eq!(map.registers[0].name, "DR");
+        let cr = map.register_at(0x1031).unwrap();
+        assert_eq!(cr.name, "CR");
+        assert_eq!(cr.fields[0].name, "MODE");
+        assert_eq!(cr.fields[0].bits(), "7:4");
+        assert!(map.register_at(0x1032).is_none());
+        assert!(map.register_at(0x10).is_none());
+
+        let mode = &cr.fields[0];
+        assert_eq!(mode.get(0xabcd), 0xc);
+        assert_eq!(mode.set(0xabcd, 0x3).unwrap(), 0xab3d);
+        assert!(mode.set(0xabcd, 0x10).is_err());
+
+        // MODE does not fit in 4 bits
+        let err = RegisterMap::from_json(
+            "bad",
+            r#"{ "registers": [ { "name": "R", "offset": 0, "size": 4, "fields": [
+                { "name": "MODE", "bits": "7:4" } ] } ] }"#,
+        );
+        assert!(err.is_err());
+        // empty and overflowing fields are rejected instead of panicking
+        for field in [
+            r#"{ "name": "F", "lsb": 0, "width": 0 }"#,
+            r#"{ "name": "F", "lsb": 4294967295, "width": 2 }"#,
+        ] {
+            let json = format!(
+                r#"{{ "registers": [ {{ "name": "R", "offset": 0, "fields": [ {} ] }} ] }}"#,
+                field
+            );
+            assert!(RegisterMap::from_json("bad", &json).is_err());
+        }
+    }
+}
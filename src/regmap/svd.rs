@@ -0,0 +1,368 @@
+//! Import of CMSIS-SVD device descriptions
+//!
+//! Only the parts of SVD that name registers and fields are read: peripherals
+//! with `derivedFrom`, registers with `dim` arrays and the three ways to give
+//! the bits of a field. Clusters, enumerated values and access rights are
+//! skipped. The base of the map is the lowest peripheral base address.
+
+use super::{parse_bits, Field, Register, RegisterMap};
+use crate::error::{Error, Result};
+use roxmltree::{Document, Node};
+
+/// Largest number of elements of a `dim` array
+const MAX_DIM: u64 = 4096;
+
+/// Child elements of `node` named `name`, SVD elements have no namespace
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |c| c.has_tag_name(name))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+/// Trimmed text of the child `name`
+fn text_of<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).map(|c| c.text().unwrap_or("").trim())
+}
+
+/// Parses an SVD number: decimal, hex with `0x` or binary with `#` or `0b`
+fn number(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let res = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix('#').or_else(|| s.strip_prefix("0b")) {
+        u64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    res.map_err(|_| Error::Parse(format!("invalid SVD number: {}", s)))
+}
+
+/// Parses an SVD number that has to fit in 32 bits, such as a size or a bit
+/// position
+fn number_u32(s: &str) -> Result<u32> {
+    u32::try_from(number(s)?)
+        .map_err(|_| Error::Parse(format!("SVD number out of range: {}", s.trim())))
+}
+
+fn required<'a>(element: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    text_of(element, name).ok_or_else(|| {
+        Error::Parse(format!(
+            "<{}> without <{}>",
+            element.tag_name().name(),
+            name
+        ))
+    })
+}
+
+fn parse_field(field: Node) -> Result<Field> {
+    let name = required(field, "name")?;
+    let (lsb, width) = if let Some(offset) = text_of(field, "bitOffset") {
+        let width = text_of(field, "bitWidth")
+            .map(number_u32)
+            .unwrap_or(Ok(1))?;
+        (number_u32(offset)?, width)
+    } else if let Some(lsb) = text_of(field, "lsb") {
+        let (lsb, msb) = (number_u32(lsb)?, number_u32(required(field, "msb")?)?);
+        if msb < lsb {
+            return Err(Error::Parse(format!(
+                "{}: msb {} is below lsb {}",
+                name, msb, lsb
+            )));
+        }
+        (lsb, msb - lsb + 1)
+    } else {
+        parse_bits(required(field, "bitRange")?)?
+    };
+    Ok(Field {
+        name: String::from(name),
+        lsb,
+        width,
+        description: text_of(field, "description").map(String::from),
+    })
+}
+
+/// Registers of a peripheral with offsets relative to its base address,
+/// `dim` arrays are expanded into one register per element
+fn parse_registers(peripheral: Node, size: u32) -> Result<Vec<Register>> {
+    let mut registers = vec![];
+    let elements = child(peripheral, "registers")
+        .map(|r| children(r, "register").collect::<Vec<_>>())
+        .unwrap_or_default();
+    for register in elements {
+        let name = required(register, "name")?;
+        let offset = number(required(register, "addressOffset")?)?;
+        let size = match text_of(register, "size") {
+            Some(size) => number_u32(size)?,
+            None => size,
+        };
+        let fields = child(register, "fields")
+            .map(|f| children(f, "field").map(parse_field).collect())
+            .unwrap_or_else(|| Ok(vec![]))?;
+        let description = text_of(register, "description").map(String::from);
+
+        let dim = text_of(register, "dim").map(number).transpose()?;
+        let instances = match dim {
+            Some(dim) => {
+                if dim > MAX_DIM {
+                    return Err(Error::Parse(format!(
+                        "{}: dim {} exceeds the maximum of {}",
+                        name, dim, MAX_DIM
+                    )));
+                }
+                let increment = number(required(register, "dimIncrement")?)?;
+                let indices: Vec<String> = match text_of(register, "dimIndex") {
+                    Some(index) => index.split(',').map(|i| i.trim().to_string()).collect(),
+                    None => (0..dim).map(|i| i.to_string()).collect(),
+                };
+                let mut instances = vec![];
+                for (i, index) in indices.into_iter().take(dim as usize).enumerate() {
+                    let offset = (i as u64)
+                        .checked_mul(increment)
+                        .and_then(|o| o.checked_add(offset))
+                        .ok_or_else(|| {
+                            Error::Parse(format!("{}[{}]: offset out of range", name, index))
+                        })?;
+                    let name = name.replace("[%s]", &index).replace("%s", &index);
+                    instances.push((name, offset));
+                }
+                instances
+            }
+            None => vec![(String::from(name), offset)],
+        };
+        for (name, offset) in instances {
+            registers.push(Register {
+                name,
+                offset,
+                size,
+                description: description.clone(),
+                fields: fields.clone(),
+            });
+        }
+    }
+    Ok(registers)
+}
+
+/// Imports the registers of all peripherals of an SVD file, register names
+/// are prefixed with the name of their peripheral
+pub fn parse(content: &str) -> Result<RegisterMap> {
+    let document =
+        Document::parse(content).map_err(|err| Error::Parse(format!("invalid SVD: {}", err)))?;
+    let device = document.root_element();
+    if !device.has_tag_name("device") {
+        return Err(Error::Parse(format!(
+            "SVD root element is <{}>, not <device>",
+            device.tag_name().name()
+        )));
+    }
+    let default_size = match text_of(device, "size") {
+        Some(size) => number_u32(size)?,
+        None => 32,
+    };
+    let peripherals: Vec<Node> = child(device, "peripherals")
+        .map(|p| children(p, "peripheral").collect())
+        .unwrap_or_default();
+
+    let mut parsed = vec![];
+    for peripheral in peripherals.iter().copied() {
+        let name = required(peripheral, "name")?;
+        let base = number(required(peripheral, "baseAddress")?)?;
+        let size = match text_of(peripheral, "size") {
+            Some(size) => number_u32(size)?,
+            None => default_size,
+        };
+        let mut registers = parse_registers(peripheral, size)?;
+        // a derived peripheral without registers of its own copies them
+        if let (true, Some(from)) = (registers.is_empty(), peripheral.attribute("derivedFrom")) {
+            let original = peripherals
+                .iter()
+                .find(|p| text_of(**p, "name") == Some(from))
+                .ok_or_else(|| Error::Parse(format!("{}: unknown peripheral {}", name, from)))?;
+            registers = parse_registers(*original, size)?;
+        }
+        parsed.push((name, base, registers));
+    }
+
+    let base = parsed.iter().map(|(_, base, _)| *base).min().unwrap_or(0);
+    let mut registers = vec![];
+    for (name, peripheral_base, peripheral_registers) in parsed {
+        for mut register in peripheral_registers {
+            register.offset = register
+                .offset
+                .checked_add(peripheral_base - base)
+                .ok_or_else(|| {
+                    Error::Parse(format!("{}.{}: offset out of range", name, register.name))
+                })?;
+            register.name = format!("{}.{}", name, register.name);
+            registers.push(register);
+        }
+    }
+    let name = text_of(device, "name").unwrap_or("svd");
+    RegisterMap::new(name, base, registers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let map = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- test device -->
+            <device schemaVersion="1.1" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance">
+              <name>TEST</name>
+              <size>32</size>
+              <peripherals>
+                <peripheral>
+                  <name>UART0</name>
+                  <baseAddress>0x40001000</baseAddress>
+                  <registers>
+                    <register>
+                      <name>CR</name>
+                      <description><![CDATA[Control & status]]></description>
+                      <addressOffset>0x30</addressOffset>
+                      <fields>
+                        <field><name>EN</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth></field>
+                        <field><name>MODE</name><lsb>4</lsb><msb>7</msb></field>
+                        <field><name>BAUD</name><bitRange>[23:16]</bitRange></field>
+                      </fields>
+                    </register>
+                    <register>
+                      <name>DATA%s</name>
+                      <addressOffset>0x0</addressOffset>
+                      <size>8</size>
+                      <dim>2</dim>
+                      <dimIncrement>4</dimIncrement>
+                    </register>
+                  </registers>
+                </peripheral>
+                <peripheral derivedFrom="UART0">
+                  <name>UART1</name>
+                  <baseAddress>0x40002000</baseAddress>
+                </peripheral>
+              </peripherals>
+            </device>"#,
+        )
+        .unwrap();
+        assert_eq!(map.name, "TEST");
+        assert_eq!(map.base, 0x40001000);
+        assert_eq!(map.registers.len(), 6);
+        assert_eq!(map.register_at(0x40001004).unwrap().name, "UART0.DATA1");
+        assert_eq!(map.register_at(0x40001004).unwrap().size, 8);
+
+        let cr = map.register_at(0x40002030).unwrap();
+        assert_eq!(cr.name, "UART1.CR");
+        assert_eq!(cr.description.as_deref(), Some("Control & status"));
+        let bits: Vec<String> = cr.fields.iter().map(|f| f.bits()).collect();
+        assert_eq!(bits, ["23:16", "7:4", "0"]);
+
+        assert!(parse("<device><name>X</name>").is_err());
+        assert!(parse("<device></peripherals>").is_err());
+        let swapped = "<device><peripherals><peripheral><name>P</name>\
+            <baseAddress>0</baseAddress><registers><register><name>R</name>\
+            <addressOffset>0</addressOffset><fields><field><name>F</name>\
+            <lsb>7</lsb><msb>4</msb></field></fields></register></registers>\
+            </peripheral></peripherals></device>";
+        assert!(parse(swapped).is_err());
+
+        // malformed numbers fail instead of aborting or being truncated
+        let register = |body: &str| {
+            format!(
+                "<device><peripherals><peripheral><name>P</name>\
+                 <baseAddress>0</baseAddress><registers><register><name>R%s</name>\
+                 <addressOffset>0</addressOffset>{}</register></registers>\
+                 </peripheral></peripherals></device>",
+                body
+            )
+        };
+        assert!(parse(&register("<dim>2</dim><dimIncrement>4</dimIncrement>")).is_ok());
+        assert!(parse(&register(
+            "<dim>0xffffffffff</dim><dimIncrement>4</dimIncrement>"
+        ))
+        .is_err());
+        let overflow = "<dim>3</dim><dimIncrement>0x8000000000000000</dimIncrement>";
+        assert!(parse(&register(overflow)).is_err());
+        assert!(parse(&register("<size>0x100000020</size>")).is_err());
+    }
+
+    #[test]
+    fn test_parse_vendor() {
+        // trimmed from the layout of a vendor SVD with entities, enumerated
+        // values and address blocks the import skips
+        let map = parse(
+            r#"<?xml version="1.0" encoding="utf-8" standalone="no"?>
+<device schemaVersion="1.3" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance" xs:noNamespaceSchemaLocation="CMSIS-SVD.xsd">
+  <vendor>STMicroelectronics</vendor>
+  <name>STM32F40x</name>
+  <width>32</width>
+  <size>0x20</size>
+  <resetValue>0x0</resetValue>
+  <peripherals>
+    <peripheral>
+      <name>GPIOA</name>
+      <description>General-purpose I/Os</description>
+      <groupName>GPIO</groupName>
+      <baseAddress>0x40020000</baseAddress>
+      <addressBlock>
+        <offset>0x0</offset>
+        <size>0x400</size>
+        <usage>registers</usage>
+      </addressBlock>
+      <registers>
+        <register>
+          <name>MODER</name>
+          <displayName>MODER</displayName>
+          <description>GPIO port mode register &#x2013; one
+          field per pin &#8220;x&#8221; &amp; &#39;y&#39;</description>
+          <addressOffset>0x0</addressOffset>
+          <size>0x20</size>
+          <access>read-write</access>
+          <resetValue>0xA8000000</resetValue>
+          <fields>
+            <field>
+              <name>MODER15</name>
+              <description>Port x configuration bits (y = 0..15)</description>
+              <bitOffset>30</bitOffset>
+              <bitWidth>2</bitWidth>
+              <enumeratedValues>
+                <enumeratedValue><name>Input</name><value>0</value></enumeratedValue>
+              </enumeratedValues>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <dim>2</dim>
+          <dimIncrement>0x4</dimIncrement>
+          <dimIndex>L,H</dimIndex>
+          <name>AFR%s</name>
+          <addressOffset>0x20</addressOffset>
+          <size>0x20</size>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="GPIOA">
+      <name>GPIOB</name>
+      <baseAddress>0x40020400</baseAddress>
+    </peripheral>
+  </peripherals>
+</device>
+"#,
+        )
+        .unwrap();
+        assert_eq!(map.name, "STM32F40x");
+        assert_eq!(map.base, 0x40020000);
+        let moder = map.register_at(0x40020400).unwrap();
+        assert_eq!(moder.name, "GPIOB.MODER");
+        assert_eq!(
+            moder.description.as_deref(),
+            Some("GPIO port mode register \u{2013} one\n          field per pin \u{201c}x\u{201d} & 'y'")
+        );
+        assert_eq!(moder.fields[0].bits(), "31:30");
+        assert_eq!(map.register_at(0x40020024).unwrap().name, "GPIOA.AFRH");
+    }
+}
@@ -0,0 +1,239 @@
+//! Pattern search over the address space of a [`MatrixData`] backend, the scan
+//! runs in steps so the TUI can show progress and cancel it
+//!
+//! [`MatrixData`]: super::matrix::MatrixData
+
+use super::error::{Error, Result};
+
+/// Bytes scanned before giving up, the address space of `/dev/mem` is too
+/// large to scan completely
+pub const SEARCH_RANGE: u64 = 16 << 20;
+
+/// Parses a search pattern:
+///
+/// - `"RSD PTR "`: ASCII string, the closing quote is optional
+/// - `DW:12345678`: little endian integer of the `B`, `W`, `DW` or `QW` type,
+///   prefixed with `>` for big endian
+/// - `5F534D335F` or `5F 53 4D`: hex bytes
+pub fn parse_pattern(s: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::Parse(format!("invalid search pattern: {}", s));
+    let bytes = if let Some(ascii) = s.strip_prefix('"') {
+        let ascii = ascii.strip_suffix('"').unwrap_or(ascii);
+        ascii.as_bytes().to_vec()
+    } else if let Some((prefix, value)) = s.split_once(':') {
+        let (big_endian, prefix) = match prefix.strip_prefix('>') {
+            Some(prefix) => (true, prefix),
+            None => (false, prefix.strip_prefix('<').unwrap_or(prefix)),
+        };
+        let size = match prefix.to_uppercase().as_str() {
+            "B" => 1,
+            "W" => 2,
+            "DW" => 4,
+            "QW" => 8,
+            _ => return Err(invalid()),
+        };
+        let value = u64::from_str_radix(value.trim(), 16).map_err(|_| invalid())?;
+        if size < 8 && value >> (size * 8) != 0 {
+            return Err(invalid());
+        }
+        let mut bytes = value.to_le_bytes()[..size].to_vec();
+        if big_endian {
+            bytes.reverse();
+        }
+        bytes
+    } else {
+        let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        hex::decode(digits).map_err(|_| invalid())?
+    };
+    if bytes.is_empty() {
+        return Err(invalid());
+    }
+    Ok(bytes)
+}
+
+/// Outcome of a scan step
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScanState {
+    Running,
+    Found(u64),
+    NotFound,
+}
+
+/// Scan for `pattern` in overlapping windows, forward from `from` or
+/// backward from the byte before it
+pub struct Scan {
+    pub pattern: Vec<u8>,
+    pub forward: bool,
+    /// first and one past the last address of the scanned range
+    pub lo: u64,
+    pub hi: u64,
+    /// start of the next window
+    pos: u64,
+    window: u64,
+    done: bool,
+    /// candidate addresses in windows that were not read
+    pub skipped: u64,
+}
+
+impl Scan {
+    /// Creates a scan over at most [`SEARCH_RANGE`] bytes that reads windows
+    /// of `window` bytes, the pattern has to fit in a window
+    pub fn new(
+        pattern: Vec<u8>,
+        from: u64,
+        forward: bool,
+        window: u64,
+        limit: Option<u64>,
+    ) -> Result<Self> {
+        let len = pattern.len() as u64;
+        if len > window {
+            return Err(Error::Parse(format!(
+                "search pattern longer than {} bytes",
+                window
+            )));
+        }
+        let (lo, hi, pos) = if forward {
+            let hi = from.saturating_add(SEARCH_RANGE);
+            (from, limit.map_or(hi, |limit| hi.min(limit)), from)
+        } else {
+            let lo = from.saturating_sub(SEARCH_RANGE);
+            let pos = from.saturating_sub(1).saturating_sub(window - len).max(lo);
+            (lo, from, pos)
+        };
+        Ok(Self {
+            pattern,
+            forward,
+            lo,
+            hi,
+            pos,
+            window,
+            done: if forward { pos + len > hi } else { from == 0 },
+            skipped: 0,
+        })
+    }
+
+    /// Address of the next window
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Scanned part of the range in percent
+    pub fn progress(&self) -> u64 {
+        let total = (self.hi - self.lo).max(1);
+        let scanned = match self.forward {
+            true => self.pos - self.lo,
+            false => self.hi - self.pos.min(self.hi),
+        };
+        (scanned * 100 / total).min(100)
+    }
+
+    fn matches(&self, data: &[Option<u8>], i: usize) -> bool {
+        self.pattern
+            .iter()
+            .enumerate()
+            .all(|(j, b)| data.get(i + j) == Some(&Some(*b)))
+    }
+
+    /// Scans up to `windows` windows, `read` returns the bytes of the window
+    /// at an address with `None` for bytes that can not be read, or `None`
+    /// for a window that must not be read at all
+    pub fn step<F>(&mut self, windows: usize, mut read: F) -> ScanState
+    where
+        F: FnMut(u64) -> Option<Vec<Option<u8>>>,
+    {
+        let len = self.pattern.len() as u64;
+        let stride = self.window - len + 1;
+        for _ in 0..windows {
+            if self.done {
+                return ScanState::NotFound;
+            }
+            // every window starts `stride` bytes after the previous one and
+            // holds that many candidate addresses
+            let data = match read(self.pos) {
+                Some(data) => data,
+                None => {
+                    self.skipped += stride;
+                    vec![]
+                }
+            };
+            if self.forward {
+                for i in 0..stride {
+                    let addr = self.pos + i;
+                    if addr + len > self.hi {
+                        break;
+                    }
+                    if self.matches(&data, i as usize) {
+                        return ScanState::Found(addr);
+                    }
+                }
+                self.pos += stride;
+                self.done = self.pos + len > self.hi;
+            } else {
+                for i in (0..stride).rev() {
+                    let addr = self.pos + i;
+                    if addr < self.hi && self.matches(&data, i as usize) {
+                        return ScanState::Found(addr);
+                    }
+                }
+                self.done = self.pos == self.lo;
+                self.pos = self.pos.saturating_sub(stride).max(self.lo);
+            }
+        }
+        ScanState::Running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        assert_eq!(parse_pattern("\"_SM3_\"").unwrap(), b"_SM3_");
+        assert_eq!(parse_pattern("5F 53 4d").unwrap(), [0x5f, 0x53, 0x4d]);
+        assert_eq!(
+            parse_pattern("DW:12345678").unwrap(),
+            [0x78, 0x56, 0x34, 0x12]
+        );
+        assert_eq!(parse_pattern(">W:1234").unwrap(), [0x12, 0x34]);
+        assert!(parse_pattern("B:100").is_err());
+        assert!(parse_pattern("5F5").is_err());
+
+        let mut memory: Vec<Option<u8>> = vec![Some(0); 1000];
+        memory[300] = None;
+        for (i, b) in b"RSD PTR ".iter().enumerate() {
+            // one copy crosses the boundary of the first two windows
+            memory[14 + i] = Some(*b);
+            memory[500 + i] = Some(*b);
+            // in windows that are skipped
+            memory[481 + i] = Some(*b);
+        }
+        // the window at 480 must not be read
+        let read = |addr: u64| -> Option<Vec<Option<u8>>> {
+            if (addr..addr + 16).contains(&480) {
+                return None;
+            }
+            Some(
+                (addr..addr + 16)
+                    .map(|a| memory.get(a as usize).copied().flatten())
+                    .collect(),
+            )
+        };
+        let pattern = parse_pattern("\"RSD PTR ").unwrap();
+        let find = |from: u64, forward: bool| {
+            let mut scan = Scan::new(pattern.clone(), from, forward, 16, Some(1000)).unwrap();
+            loop {
+                match scan.step(4, read) {
+                    ScanState::Running => continue,
+                    state => return (state, scan.skipped),
+                }
+            }
+        };
+        assert_eq!(find(0, true), (ScanState::Found(14), 0));
+        assert_eq!(find(15, true), (ScanState::Found(500), 18));
+        assert_eq!(find(501, true).0, ScanState::NotFound);
+        assert_eq!(find(999, false).0, ScanState::Found(500));
+        assert_eq!(find(500, false).0, ScanState::Found(14));
+        assert_eq!(find(14, false), (ScanState::NotFound, 0));
+    }
+}
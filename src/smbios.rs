@@ -0,0 +1,1243 @@
+//! SMBIOS structures from the entry point and table the kernel exports under
+//! `/sys/firmware/dmi/tables`, decoded without dmidecode
+
+use super::buffer::Buffer;
+use super::error::{Error, Result};
+use super::matrix::{self, Matrix, PAGE_SIZE};
+use super::textview::{self, TextView};
+use crossterm::event::{self, Event, KeyCode};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui::Terminal;
+
+const ENTRY_POINT_PATH: &str = "/sys/firmware/dmi/tables/smbios_entry_point";
+const TABLE_PATH: &str = "/sys/firmware/dmi/tables/DMI";
+
+/// Type of the structure that ends the table
+const END_OF_TABLE: u8 = 127;
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// SMBIOS 2.1 (`_SM_`) or 3.0 (`_SM3_`) entry point
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPoint {
+    pub major: u8,
+    pub minor: u8,
+    /// docrev of a 3.x entry point, the BCD revision of a 2.x one
+    pub revision: u8,
+    pub table_address: u64,
+    /// exact length for 2.x, maximum length for 3.x
+    pub table_length: u32,
+    /// only 2.x entry points count the structures
+    pub structure_count: Option<u16>,
+}
+
+impl EntryPoint {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let invalid = || Error::Parse(String::from("invalid SMBIOS entry point"));
+        if data.starts_with(b"_SM3_") && data.len() >= 0x18 {
+            Ok(Self {
+                major: data[7],
+                minor: data[8],
+                revision: data[9],
+                table_length: u32_at(data, 0x0c).ok_or_else(invalid)?,
+                table_address: u64_at(data, 0x10).ok_or_else(invalid)?,
+                structure_count: None,
+            })
+        } else if data.starts_with(b"_SM_") && data.len() >= 0x1f {
+            Ok(Self {
+                major: data[6],
+                minor: data[7],
+                revision: data[0x1e],
+                table_length: u16_at(data, 0x16).ok_or_else(invalid)? as u32,
+                table_address: u32_at(data, 0x18).ok_or_else(invalid)? as u64,
+                structure_count: u16_at(data, 0x1c),
+            })
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl fmt::Display for EntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SMBIOS {}.{} present.", self.major, self.minor)?;
+        match self.structure_count {
+            Some(count) => writeln!(
+                f,
+                "{} structures occupying {} bytes.",
+                count, self.table_length
+            )?,
+            None => writeln!(f, "Table of at most {} bytes.", self.table_length)?,
+        }
+        writeln!(f, "Table at 0x{:08X}.", self.table_address)
+    }
+}
+
+/// Formatted area of a structure with its string set
+struct Formatted<'a> {
+    data: &'a [u8],
+    strings: &'a [String],
+}
+
+impl Formatted<'_> {
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    fn word(&self, offset: usize) -> Option<u16> {
+        u16_at(self.data, offset)
+    }
+
+    fn dword(&self, offset: usize) -> Option<u32> {
+        u32_at(self.data, offset)
+    }
+
+    fn qword(&self, offset: usize) -> Option<u64> {
+        u64_at(self.data, offset)
+    }
+
+    /// String referenced by the string number at `offset`, numbers start at 1
+    fn string(&self, offset: usize) -> Option<String> {
+        let index = self.byte(offset)? as usize;
+        self.strings.get(index.checked_sub(1)?).cloned()
+    }
+}
+
+/// BIOS Information, type 0
+#[derive(Debug, Clone, Serialize)]
+pub struct Bios {
+    pub vendor: Option<String>,
+    pub version: Option<String>,
+    pub release_date: Option<String>,
+    pub address: u32,
+    /// size of the BIOS ROM in KiB
+    pub rom_size: u64,
+    pub characteristics: u64,
+    pub release: Option<(u8, u8)>,
+    pub firmware_release: Option<(u8, u8)>,
+}
+
+const BIOS_CHARACTERISTICS: &[(u32, &str)] = &[
+    (4, "ISA is supported"),
+    (7, "PCI is supported"),
+    (9, "Plug and play is supported"),
+    (11, "BIOS is upgradeable"),
+    (12, "BIOS shadowing is allowed"),
+    (15, "Boot from CD is supported"),
+    (16, "Selectable boot is supported"),
+    (19, "EDD is supported"),
+    (26, "Print screen service is supported"),
+    (27, "8042 keyboard services are supported"),
+    (28, "Serial services are supported"),
+    (29, "Printer services are supported"),
+];
+
+impl Bios {
+    fn parse(s: &Formatted) -> Option<Self> {
+        let rom_size = match s.byte(0x09)? {
+            // the extended size gives the unit in bits 15:14
+            0xff => match s.word(0x18) {
+                Some(ext) if ext >> 14 == 0 => (ext & 0x3fff) as u64 * 1024,
+                Some(ext) if ext >> 14 == 1 => (ext & 0x3fff) as u64 * 1024 * 1024,
+                _ => 16 * 1024,
+            },
+            size => (size as u64 + 1) * 64,
+        };
+        Some(Self {
+            vendor: s.string(0x04),
+            version: s.string(0x05),
+            address: (s.word(0x06)? as u32) << 4,
+            release_date: s.string(0x08),
+            rom_size,
+            characteristics: s.qword(0x0a)?,
+            release: s
+                .byte(0x14)
+                .zip(s.byte(0x15))
+                .filter(|r| *r != (0xff, 0xff)),
+            firmware_release: s
+                .byte(0x16)
+                .zip(s.byte(0x17))
+                .filter(|r| *r != (0xff, 0xff)),
+        })
+    }
+}
+
+/// System Information, type 1
+#[derive(Debug, Clone, Serialize)]
+pub struct System {
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub serial: Option<String>,
+    pub uuid: Option<String>,
+    pub sku: Option<String>,
+    pub family: Option<String>,
+}
+
+/// Formats a UUID whose first three fields are little endian, as the
+/// specification requires since 2.6
+fn uuid(bytes: &[u8]) -> Option<String> {
+    let b: [u8; 16] = bytes.try_into().ok()?;
+    if b.iter().all(|x| *x == 0) || b.iter().all(|x| *x == 0xff) {
+        return None;
+    }
+    Some(format!(
+        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13],
+        b[14], b[15]
+    ))
+}
+
+impl System {
+    fn parse(s: &Formatted) -> Option<Self> {
+        Some(Self {
+            manufacturer: s.string(0x04),
+            product: s.string(0x05),
+            version: s.string(0x06),
+            serial: s.string(0x07),
+            uuid: s.data.get(0x08..0x18).and_then(uuid),
+            sku: s.string(0x19),
+            family: s.string(0x1a),
+        })
+    }
+}
+
+/// Baseboard Information, type 2
+#[derive(Debug, Clone, Serialize)]
+pub struct Baseboard {
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub serial: Option<String>,
+    pub asset_tag: Option<String>,
+    pub location: Option<String>,
+    pub board_type: Option<u8>,
+}
+
+impl Baseboard {
+    fn parse(s: &Formatted) -> Option<Self> {
+        Some(Self {
+            manufacturer: s.string(0x04),
+            product: s.string(0x05),
+            version: s.string(0x06),
+            serial: s.string(0x07),
+            asset_tag: s.string(0x08),
+            location: s.string(0x0a),
+            board_type: s.byte(0x0d),
+        })
+    }
+}
+
+fn board_type_name(board_type: u8) -> &'static str {
+    match board_type {
+        0x01 => "Unknown",
+        0x02 => "Other",
+        0x03 => "Server Blade",
+        0x04 => "Connectivity Switch",
+        0x05 => "System Management Module",
+        0x06 => "Processor Module",
+        0x07 => "I/O Module",
+        0x08 => "Memory Module",
+        0x09 => "Daughter Board",
+        0x0a => "Motherboard",
+        0x0b => "Processor+Memory Module",
+        0x0c => "Processor+I/O Module",
+        0x0d => "Interconnect Board",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+/// Chassis Information, type 3
+#[derive(Debug, Clone, Serialize)]
+pub struct Chassis {
+    pub manufacturer: Option<String>,
+    pub chassis_type: u8,
+    pub lock: bool,
+    pub version: Option<String>,
+    pub serial: Option<String>,
+    pub asset_tag: Option<String>,
+}
+
+impl Chassis {
+    fn parse(s: &Formatted) -> Option<Self> {
+        let kind = s.byte(0x05)?;
+        Some(Self {
+            manufacturer: s.string(0x04),
+            chassis_type: kind & 0x7f,
+            lock: kind & 0x80 != 0,
+            version: s.string(0x06),
+            serial: s.string(0x07),
+            asset_tag: s.string(0x08),
+        })
+    }
+}
+
+fn chassis_type_name(chassis_type: u8) -> &'static str {
+    match chassis_type {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "Desktop",
+        0x04 => "Low Profile Desktop",
+        0x05 => "Pizza Box",
+        0x06 => "Mini Tower",
+        0x07 => "Tower",
+        0x08 => "Portable",
+        0x09 => "Laptop",
+        0x0a => "Notebook",
+        0x0b => "Hand Held",
+        0x0c => "Docking Station",
+        0x0d => "All In One",
+        0x0e => "Sub Notebook",
+        0x0f => "Space-saving",
+        0x10 => "Lunch Box",
+        0x11 => "Main Server Chassis",
+        0x12 => "Expansion Chassis",
+        0x13 => "Sub Chassis",
+        0x14 => "Bus Expansion Chassis",
+        0x15 => "Peripheral Chassis",
+        0x16 => "RAID Chassis",
+        0x17 => "Rack Mount Chassis",
+        0x18 => "Sealed-case PC",
+        0x19 => "Multi-system",
+        0x1a => "CompactPCI",
+        0x1b => "AdvancedTCA",
+        0x1c => "Blade",
+        0x1d => "Blade Enclosing",
+        0x1e => "Tablet",
+        0x1f => "Convertible",
+        0x20 => "Detachable",
+        0x21 => "IoT Gateway",
+        0x22 => "Embedded PC",
+        0x23 => "Mini PC",
+        0x24 => "Stick PC",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+/// Processor Information, type 4
+#[derive(Debug, Clone, Serialize)]
+pub struct Processor {
+    pub socket: Option<String>,
+    pub processor_type: u8,
+    pub family: u16,
+    pub manufacturer: Option<String>,
+    /// CPUID leaf 1 eax and edx on x86
+    pub id: u64,
+    pub version: Option<String>,
+    /// MHz, 0 if unknown
+    pub external_clock: u16,
+    pub max_speed: u16,
+    pub current_speed: u16,
+    pub populated: bool,
+    pub status: u8,
+    pub serial: Option<String>,
+    pub asset_tag: Option<String>,
+    pub part_number: Option<String>,
+    pub core_count: Option<u16>,
+    pub core_enabled: Option<u16>,
+    pub thread_count: Option<u16>,
+}
+
+impl Processor {
+    fn parse(s: &Formatted) -> Option<Self> {
+        // counts above 255 are in the 3.0 fields, with 0xff in the old ones
+        let count = |old: usize, new: usize| match s.byte(old)? {
+            0 => None,
+            0xff => s.word(new).or(Some(0xff)),
+            count => Some(count as u16),
+        };
+        let family = match s.byte(0x06)? {
+            0xfe => s.word(0x28).unwrap_or(0xfe),
+            family => family as u16,
+        };
+        let status = s.byte(0x18)?;
+        Some(Self {
+            socket: s.string(0x04),
+            processor_type: s.byte(0x05)?,
+            family,
+            manufacturer: s.string(0x07),
+            id: s.qword(0x08)?,
+            version: s.string(0x10),
+            external_clock: s.word(0x12)?,
+            max_speed: s.word(0x14)?,
+            current_speed: s.word(0x16)?,
+            populated: status & 0x40 != 0,
+            status: status & 0x7,
+            serial: s.string(0x20),
+            asset_tag: s.string(0x21),
+            part_number: s.string(0x22),
+            core_count: count(0x23, 0x2a),
+            core_enabled: count(0x24, 0x2c),
+            thread_count: count(0x25, 0x2e),
+        })
+    }
+}
+
+fn processor_type_name(processor_type: u8) -> &'static str {
+    match processor_type {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "Central Processor",
+        0x04 => "Math Processor",
+        0x05 => "DSP Processor",
+        0x06 => "Video Processor",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+fn processor_status_name(status: u8) -> &'static str {
+    match status {
+        0x0 => "Unknown",
+        0x1 => "Enabled",
+        0x2 => "Disabled By User",
+        0x3 => "Disabled By BIOS",
+        0x4 => "Idle",
+        0x7 => "Other",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+/// System Slots, type 9
+#[derive(Debug, Clone, Serialize)]
+pub struct Slot {
+    pub designation: Option<String>,
+    pub slot_type: u8,
+    pub bus_width: u8,
+    pub usage: u8,
+    pub length: u8,
+    pub id: u16,
+    /// segment, bus and device/function of the device in the slot
+    pub address: Option<(u16, u8, u8)>,
+}
+
+impl Slot {
+    fn parse(s: &Formatted) -> Option<Self> {
+        let address = match (s.word(0x0d), s.byte(0x0f), s.byte(0x10)) {
+            (Some(0xffff), Some(0xff), Some(0xff)) => None,
+            (Some(segment), Some(bus), Some(devfn)) => Some((segment, bus, devfn)),
+            _ => None,
+        };
+        Some(Self {
+            designation: s.string(0x04),
+            slot_type: s.byte(0x05)?,
+            bus_width: s.byte(0x06)?,
+            usage: s.byte(0x07)?,
+            length: s.byte(0x08)?,
+            id: s.word(0x09)?,
+            address,
+        })
+    }
+}
+
+fn slot_type_name(slot_type: u8) -> &'static str {
+    match slot_type {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "ISA",
+        0x04 => "MCA",
+        0x05 => "EISA",
+        0x06 => "PCI",
+        0x07 => "PC Card (PCMCIA)",
+        0x08 => "VLB",
+        0x0e => "PCI-66",
+        0x0f => "AGP",
+        0x12 => "PCI-X",
+        0x1f => "M.2 Socket 1-DP",
+        0x20 => "M.2 Socket 1-SD",
+        0x21 => "M.2 Socket 2",
+        0x22 => "M.2 Socket 3",
+        0x23 => "MXM Type I",
+        0x24 => "MXM Type II",
+        0x25 => "MXM Type III",
+        0x26 => "MXM Type III-HE",
+        0x27 => "MXM Type IV",
+        0x28 => "MXM 3.0 Type A",
+        0x29 => "MXM 3.0 Type B",
+        0x2a => "PCI Express 2 SFF-8639 (U.2)",
+        0x2b => "PCI Express 3 SFF-8639 (U.2)",
+        0x2c => "PCI Express Mini 52-pin with bottom-side keep-outs",
+        0x2d => "PCI Express Mini 52-pin without bottom-side keep-outs",
+        0x2e => "PCI Express Mini 76-pin",
+        0x2f => "PCI Express 4 SFF-8639 (U.2)",
+        0x30 => "PCI Express 5 SFF-8639 (U.2)",
+        0x31 => "OCP NIC 3.0 Small Form Factor (SFF)",
+        0x32 => "OCP NIC 3.0 Large Form Factor (LFF)",
+        0x33 => "OCP NIC Prior to 3.0",
+        0xa5 => "PCI Express",
+        0xa6 => "PCI Express x1",
+        0xa7 => "PCI Express x2",
+        0xa8 => "PCI Express x4",
+        0xa9 => "PCI Express x8",
+        0xaa => "PCI Express x16",
+        0xab => "PCI Express 2",
+        0xac => "PCI Express 2 x1",
+        0xad => "PCI Express 2 x2",
+        0xae => "PCI Express 2 x4",
+        0xaf => "PCI Express 2 x8",
+        0xb0 => "PCI Express 2 x16",
+        0xb1 => "PCI Express 3",
+        0xb2 => "PCI Express 3 x1",
+        0xb3 => "PCI Express 3 x2",
+        0xb4 => "PCI Express 3 x4",
+        0xb5 => "PCI Express 3 x8",
+        0xb6 => "PCI Express 3 x16",
+        0xb8 => "PCI Express 4",
+        0xb9 => "PCI Express 4 x1",
+        0xba => "PCI Express 4 x2",
+        0xbb => "PCI Express 4 x4",
+        0xbc => "PCI Express 4 x8",
+        0xbd => "PCI Express 4 x16",
+        0xbe => "PCI Express 5",
+        0xbf => "PCI Express 5 x1",
+        0xc0 => "PCI Express 5 x2",
+        0xc1 => "PCI Express 5 x4",
+        0xc2 => "PCI Express 5 x8",
+        0xc3 => "PCI Express 5 x16",
+        0xc4 => "PCI Express 6+",
+        0xc5 => "EDSFF E1",
+        0xc6 => "EDSFF E3",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+fn slot_width_name(width: u8) -> &'static str {
+    match width {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "8-bit",
+        0x04 => "16-bit",
+        0x05 => "32-bit",
+        0x06 => "64-bit",
+        0x07 => "128-bit",
+        0x08 => "x1",
+        0x09 => "x2",
+        0x0a => "x4",
+        0x0b => "x8",
+        0x0c => "x12",
+        0x0d => "x16",
+        0x0e => "x32",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+fn slot_usage_name(usage: u8) -> &'static str {
+    match usage {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "Available",
+        0x04 => "In Use",
+        0x05 => "Unavailable",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+fn slot_length_name(length: u8) -> &'static str {
+    match length {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "Short",
+        0x04 => "Long",
+        0x05 => "2.5\" drive form factor",
+        0x06 => "3.5\" drive form factor",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+/// Memory Device, type 17
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryDevice {
+    pub array_handle: u16,
+    /// bits, `None` if unknown
+    pub total_width: Option<u16>,
+    pub data_width: Option<u16>,
+    /// size in MiB, `Some(0)` for an empty slot and `None` if unknown
+    pub size: Option<u64>,
+    pub form_factor: u8,
+    pub locator: Option<String>,
+    pub bank_locator: Option<String>,
+    pub memory_type: u8,
+    /// MT/s
+    pub speed: Option<u32>,
+    pub manufacturer: Option<String>,
+    pub serial: Option<String>,
+    pub asset_tag: Option<String>,
+    pub part_number: Option<String>,
+    pub rank: Option<u8>,
+    pub configured_speed: Option<u32>,
+}
+
+impl MemoryDevice {
+    fn parse(s: &Formatted) -> Option<Self> {
+        let width = |offset: usize| s.word(offset).filter(|w| *w != 0xffff && *w != 0);
+        let size = match s.word(0x0c)? {
+            0xffff => None,
+            0x7fff => s.dword(0x1c).map(|ext| (ext & 0x7fff_ffff) as u64),
+            size if size & 0x8000 != 0 => Some((size & 0x7fff) as u64 / 1024),
+            size => Some(size as u64),
+        };
+        // speeds of 0xffff are in the 32-bit extended fields
+        let speed = |offset: usize, extended: usize| match s.word(offset)? {
+            0 => None,
+            0xffff => s.dword(extended),
+            speed => Some(speed as u32),
+        };
+        Some(Self {
+            array_handle: s.word(0x04)?,
+            total_width: width(0x08),
+            data_width: width(0x0a),
+            size,
+            form_factor: s.byte(0x0e)?,
+            locator: s.string(0x10),
+            bank_locator: s.string(0x11),
+            memory_type: s.byte(0x12)?,
+            speed: speed(0x15, 0x54),
+            manufacturer: s.string(0x17),
+            serial: s.string(0x18),
+            asset_tag: s.string(0x19),
+            part_number: s.string(0x1a),
+            rank: s.byte(0x1b).map(|a| a & 0xf).filter(|r| *r != 0),
+            configured_speed: speed(0x20, 0x58),
+        })
+    }
+}
+
+fn form_factor_name(form_factor: u8) -> &'static str {
+    match form_factor {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "SIMM",
+        0x04 => "SIP",
+        0x05 => "Chip",
+        0x06 => "DIP",
+        0x07 => "ZIP",
+        0x08 => "Proprietary Card",
+        0x09 => "DIMM",
+        0x0a => "TSOP",
+        0x0b => "Row Of Chips",
+        0x0c => "RIMM",
+        0x0d => "SODIMM",
+        0x0e => "SRIMM",
+        0x0f => "FB-DIMM",
+        0x10 => "Die",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+fn memory_type_name(memory_type: u8) -> &'static str {
+    match memory_type {
+        0x01 => "Other",
+        0x02 => "Unknown",
+        0x03 => "DRAM",
+        0x04 => "EDRAM",
+        0x05 => "VRAM",
+        0x06 => "SRAM",
+        0x07 => "RAM",
+        0x08 => "ROM",
+        0x09 => "Flash",
+        0x0a => "EEPROM",
+        0x0b => "FEPROM",
+        0x0c => "EPROM",
+        0x0d => "CDRAM",
+        0x0e => "3DRAM",
+        0x0f => "SDRAM",
+        0x10 => "SGRAM",
+        0x11 => "RDRAM",
+        0x12 => "DDR",
+        0x13 => "DDR2",
+        0x14 => "DDR2 FB-DIMM",
+        0x18 => "DDR3",
+        0x19 => "FBD2",
+        0x1a => "DDR4",
+        0x1b => "LPDDR",
+        0x1c => "LPDDR2",
+        0x1d => "LPDDR3",
+        0x1e => "LPDDR4",
+        0x1f => "Logical non-volatile device",
+        0x20 => "HBM",
+        0x21 => "HBM2",
+        0x22 => "DDR5",
+        0x23 => "LPDDR5",
+        0x24 => "HBM3",
+        _ => "<OUT OF SPEC>",
+    }
+}
+
+/// Decoded formatted area of a structure
+#[derive(Debug, Clone, Serialize)]
+pub enum StructureData {
+    Bios(Bios),
+    System(System),
+    Baseboard(Baseboard),
+    Chassis(Chassis),
+    Processor(Processor),
+    Slot(Slot),
+    MemoryDevice(MemoryDevice),
+    /// a structure without decoder or one that is too short
+    Raw,
+}
+
+/// Name of structure type `kind`
+pub fn type_name(kind: u8) -> &'static str {
+    match kind {
+        0 => "BIOS Information",
+        1 => "System Information",
+        2 => "Base Board Information",
+        3 => "Chassis Information",
+        4 => "Processor Information",
+        5 => "Memory Controller Information",
+        6 => "Memory Module Information",
+        7 => "Cache Information",
+        8 => "Port Connector Information",
+        9 => "System Slot Information",
+        10 => "On Board Devices Information",
+        11 => "OEM Strings",
+        12 => "System Configuration Options",
+        13 => "BIOS Language Information",
+        14 => "Group Associations",
+        15 => "System Event Log",
+        16 => "Physical Memory Array",
+        17 => "Memory Device",
+        18 => "32-bit Memory Error Information",
+        19 => "Memory Array Mapped Address",
+        20 => "Memory Device Mapped Address",
+        21 => "Built-in Pointing Device",
+        22 => "Portable Battery",
+        23 => "System Reset",
+        24 => "Hardware Security",
+        25 => "System Power Controls",
+        26 => "Voltage Probe",
+        27 => "Cooling Device",
+        28 => "Temperature Probe",
+        29 => "Electrical Current Probe",
+        30 => "Out-of-band Remote Access",
+        31 => "Boot Integrity Services Entry Point",
+        32 => "System Boot Information",
+        33 => "64-bit Memory Error Information",
+        34 => "Management Device",
+        35 => "Management Device Component",
+        36 => "Management Device Threshold Data",
+        37 => "Memory Channel",
+        38 => "IPMI Device Information",
+        39 => "System Power Supply",
+        40 => "Additional Information",
+        41 => "Onboard Device",
+        42 => "Management Controller Host Interface",
+        43 => "TPM Device",
+        44 => "Processor Additional Information",
+        45 => "Firmware Inventory Information",
+        46 => "String Property",
+        126 => "Inactive",
+        END_OF_TABLE => "End Of Table",
+        128..=255 => "OEM-specific",
+        _ => "Unknown",
+    }
+}
+
+/// A structure of the SMBIOS table
+#[derive(Debug, Clone, Serialize)]
+pub struct Structure {
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub handle: u16,
+    pub name: &'static str,
+    /// length of the formatted area
+    pub length: u8,
+    pub strings: Vec<String>,
+    pub data: StructureData,
+    /// formatted area and string set as found in the table
+    #[serde(skip)]
+    pub raw: Vec<u8>,
+}
+
+impl Structure {
+    fn decode(kind: u8, formatted: &Formatted) -> StructureData {
+        let decoded = match kind {
+            0 => Bios::parse(formatted).map(StructureData::Bios),
+            1 => System::parse(formatted).map(StructureData::System),
+            2 => Baseboard::parse(formatted).map(StructureData::Baseboard),
+            3 => Chassis::parse(formatted).map(StructureData::Chassis),
+            4 => Processor::parse(formatted).map(StructureData::Processor),
+            9 => Slot::parse(formatted).map(StructureData::Slot),
+            17 => MemoryDevice::parse(formatted).map(StructureData::MemoryDevice),
+            _ => None,
+        };
+        decoded.unwrap_or(StructureData::Raw)
+    }
+}
+
+/// Parses the structures of `table` up to the end-of-table structure
+pub fn parse_table(table: &[u8]) -> Vec<Structure> {
+    let mut structures = vec![];
+    let mut offset = 0;
+    while offset + 4 <= table.len() {
+        let kind = table[offset];
+        let length = table[offset + 1];
+        let handle = u16::from_le_bytes([table[offset + 2], table[offset + 3]]);
+        let strings_start = offset + length as usize;
+        if length < 4 || strings_start > table.len() {
+            break;
+        }
+        // the string set ends with two NUL bytes, also if it is empty
+        let end = table[strings_start..]
+            .windows(2)
+            .position(|w| w == [0, 0])
+            .map_or(table.len(), |p| strings_start + p + 2);
+        let strings: Vec<String> = table[strings_start..end]
+            .split(|b| *b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).trim().to_string())
+            .collect();
+        let formatted = Formatted {
+            data: &table[offset..strings_start],
+            strings: &strings,
+        };
+        let data = Structure::decode(kind, &formatted);
+        structures.push(Structure {
+            kind,
+            handle,
+            name: type_name(kind),
+            length,
+            data,
+            strings,
+            raw: table[offset..end].to_vec(),
+        });
+        offset = end;
+        if kind == END_OF_TABLE {
+            break;
+        }
+    }
+    structures
+}
+
+/// Entry point and structures of the SMBIOS table
+#[derive(Debug, Clone, Serialize)]
+pub struct Smbios {
+    pub entry_point: EntryPoint,
+    pub structures: Vec<Structure>,
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::Unsupported(format!(
+            "{} is missing, the firmware provides no SMBIOS tables",
+            path
+        )),
+        io::ErrorKind::PermissionDenied => {
+            Error::PermissionDenied(format!("{} requires root", path))
+        }
+        _ => Error::Io(err),
+    })
+}
+
+/// Reads and parses the SMBIOS table exported by the kernel
+pub fn read() -> Result<Smbios> {
+    let entry_point = EntryPoint::parse(&read_file(ENTRY_POINT_PATH)?)?;
+    let table = read_file(TABLE_PATH)?;
+    Ok(Smbios {
+        entry_point,
+        structures: parse_table(&table),
+    })
+}
+
+fn field(f: &mut fmt::Formatter<'_>, name: &str, value: &Option<String>) -> fmt::Result {
+    writeln!(
+        f,
+        "\t{}: {}",
+        name,
+        value.as_deref().unwrap_or("Not Specified")
+    )
+}
+
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Handle 0x{:04X}, DMI type {}, {} bytes",
+            self.handle, self.kind, self.length
+        )?;
+        writeln!(f, "{}", self.name)?;
+        match &self.data {
+            StructureData::Bios(b) => {
+                field(f, "Vendor", &b.vendor)?;
+                field(f, "Version", &b.version)?;
+                field(f, "Release Date", &b.release_date)?;
+                writeln!(f, "\tAddress: 0x{:05X}", b.address)?;
+                match b.rom_size {
+                    size if size >= 1024 && size % 1024 == 0 => {
+                        writeln!(f, "\tROM Size: {} MB", size / 1024)?
+                    }
+                    size => writeln!(f, "\tROM Size: {} kB", size)?,
+                }
+                writeln!(f, "\tCharacteristics:")?;
+                for (bit, name) in BIOS_CHARACTERISTICS {
+                    if b.characteristics & (1 << bit) != 0 {
+                        writeln!(f, "\t\t{}", name)?;
+                    }
+                }
+                if let Some((major, minor)) = b.release {
+                    writeln!(f, "\tBIOS Revision: {}.{}", major, minor)?;
+                }
+                if let Some((major, minor)) = b.firmware_release {
+                    writeln!(f, "\tFirmware Revision: {}.{}", major, minor)?;
+                }
+            }
+            StructureData::System(s) => {
+                field(f, "Manufacturer", &s.manufacturer)?;
+                field(f, "Product Name", &s.product)?;
+                field(f, "Version", &s.version)?;
+                field(f, "Serial Number", &s.serial)?;
+                field(f, "UUID", &s.uuid)?;
+                field(f, "SKU Number", &s.sku)?;
+                field(f, "Family", &s.family)?;
+            }
+            StructureData::Baseboard(b) => {
+                field(f, "Manufacturer", &b.manufacturer)?;
+                field(f, "Product Name", &b.product)?;
+                field(f, "Version", &b.version)?;
+                field(f, "Serial Number", &b.serial)?;
+                field(f, "Asset Tag", &b.asset_tag)?;
+                field(f, "Location In Chassis", &b.location)?;
+                if let Some(board_type) = b.board_type {
+                    writeln!(f, "\tType: {}", board_type_name(board_type))?;
+                }
+            }
+            StructureData::Chassis(c) => {
+                field(f, "Manufacturer", &c.manufacturer)?;
+                writeln!(f, "\tType: {}", chassis_type_name(c.chassis_type))?;
+                writeln!(
+                    f,
+                    "\tLock: {}",
+                    if c.lock { "Present" } else { "Not Present" }
+                )?;
+                field(f, "Version", &c.version)?;
+                field(f, "Serial Number", &c.serial)?;
+                field(f, "Asset Tag", &c.asset_tag)?;
+            }
+            StructureData::Processor(p) => {
+                field(f, "Socket Designation", &p.socket)?;
+                writeln!(f, "\tType: {}", processor_type_name(p.processor_type))?;
+                writeln!(f, "\tFamily: 0x{:x}", p.family)?;
+                field(f, "Manufacturer", &p.manufacturer)?;
+                let id = p.id.to_le_bytes();
+                writeln!(
+                    f,
+                    "\tID: {}",
+                    id.iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )?;
+                field(f, "Version", &p.version)?;
+                let mhz = |speed: u16| match speed {
+                    0 => String::from("Unknown"),
+                    speed => format!("{} MHz", speed),
+                };
+                writeln!(f, "\tExternal Clock: {}", mhz(p.external_clock))?;
+                writeln!(f, "\tMax Speed: {}", mhz(p.max_speed))?;
+                writeln!(f, "\tCurrent Speed: {}", mhz(p.current_speed))?;
+                match p.populated {
+                    true => writeln!(
+                        f,
+                        "\tStatus: Populated, {}",
+                        processor_status_name(p.status)
+                    )?,
+                    false => writeln!(f, "\tStatus: Unpopulated")?,
+                }
+                field(f, "Serial Number", &p.serial)?;
+                field(f, "Asset Tag", &p.asset_tag)?;
+                field(f, "Part Number", &p.part_number)?;
+                for (name, count) in [
+                    ("Core Count", p.core_count),
+                    ("Core Enabled", p.core_enabled),
+                    ("Thread Count", p.thread_count),
+                ] {
+                    if let Some(count) = count {
+                        writeln!(f, "\t{}: {}", name, count)?;
+                    }
+                }
+            }
+            StructureData::Slot(s) => {
+                field(f, "Designation", &s.designation)?;
+                writeln!(
+                    f,
+                    "\tType: {} {}",
+                    slot_width_name(s.bus_width),
+                    slot_type_name(s.slot_type)
+                )?;
+                writeln!(f, "\tCurrent Usage: {}", slot_usage_name(s.usage))?;
+                writeln!(f, "\tLength: {}", slot_length_name(s.length))?;
+                writeln!(f, "\tID: {}", s.id)?;
+                if let Some((segment, bus, devfn)) = s.address {
+                    writeln!(
+                        f,
+                        "\tBus Address: {:04x}:{:02x}:{:02x}.{:x}",
+                        segment,
+                        bus,
+                        devfn >> 3,
+                        devfn & 0x7
+                    )?;
+                }
+            }
+            StructureData::MemoryDevice(m) => {
+                writeln!(f, "\tArray Handle: 0x{:04X}", m.array_handle)?;
+                let bits = |width: Option<u16>| match width {
+                    Some(width) => format!("{} bits", width),
+                    None => String::from("Unknown"),
+                };
+                writeln!(f, "\tTotal Width: {}", bits(m.total_width))?;
+                writeln!(f, "\tData Width: {}", bits(m.data_width))?;
+                match m.size {
+                    Some(0) => writeln!(f, "\tSize: No Module Installed")?,
+                    Some(size) if size % 1024 == 0 => writeln!(f, "\tSize: {} GB", size / 1024)?,
+                    Some(size) => writeln!(f, "\tSize: {} MB", size)?,
+                    None => writeln!(f, "\tSize: Unknown")?,
+                }
+                writeln!(f, "\tForm Factor: {}", form_factor_name(m.form_factor))?;
+                field(f, "Locator", &m.locator)?;
+                field(f, "Bank Locator", &m.bank_locator)?;
+                writeln!(f, "\tType: {}", memory_type_name(m.memory_type))?;
+                let speed = |speed: Option<u32>| match speed {
+                    Some(speed) => format!("{} MT/s", speed),
+                    None => String::from("Unknown"),
+                };
+                writeln!(f, "\tSpeed: {}", speed(m.speed))?;
+                field(f, "Manufacturer", &m.manufacturer)?;
+                field(f, "Serial Number", &m.serial)?;
+                field(f, "Asset Tag", &m.asset_tag)?;
+                field(f, "Part Number", &m.part_number)?;
+                if let Some(rank) = m.rank {
+                    writeln!(f, "\tRank: {}", rank)?;
+                }
+                writeln!(
+                    f,
+                    "\tConfigured Memory Speed: {}",
+                    speed(m.configured_speed)
+                )?;
+            }
+            StructureData::Raw => {
+                for (i, string) in self.strings.iter().enumerate() {
+                    writeln!(f, "\tString {}: {}", i + 1, string)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// List of structures, opens the decoded structure or its bytes in the hex matrix
+pub struct SmbiosBrowser {
+    smbios: Smbios,
+    state: ListState,
+}
+
+impl SmbiosBrowser {
+    pub fn new() -> Result<Self> {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Ok(Self {
+            smbios: read()?,
+            state,
+        })
+    }
+
+    fn selected(&self) -> Option<&Structure> {
+        self.smbios.structures.get(self.state.selected()?)
+    }
+}
+
+/// Opens the bytes of `structure` in the hex matrix
+fn hex_view<B: Backend>(terminal: &mut Terminal<B>, structure: &Structure) -> io::Result<()> {
+    let mut m = Matrix::new(
+        &format!("DMI handle 0x{:04X}", structure.handle),
+        Buffer::new(structure.raw.clone(), PAGE_SIZE),
+    );
+    matrix::start(terminal, &mut m)
+}
+
+/// Shows the decoded `structure` until the user goes back to the list
+fn structure_screen<B: Backend>(
+    terminal: &mut Terminal<B>,
+    structure: &Structure,
+) -> io::Result<()> {
+    let title = format!("Handle 0x{:04X}", structure.handle);
+    let mut view = TextView::new(&title, &structure.to_string());
+    loop {
+        textview::draw(terminal, &view, "j/k: scroll  x: hex view  q/Esc: back")?;
+
+        if let Event::Key(key) = event::read()? {
+            if view.scroll_key(key.code) {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('x') => hex_view(terminal, structure)?,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw<B: Backend>(terminal: &mut Terminal<B>, b: &mut SmbiosBrowser) -> io::Result<()> {
+    let items: Vec<ListItem> = b
+        .smbios
+        .structures
+        .iter()
+        .map(|s| ListItem::new(format!("0x{:04X}  {:>3}  {}", s.handle, s.kind, s.name)))
+        .collect();
+    let ep = &b.smbios.entry_point;
+    let title = format!(
+        " SMBIOS {}.{} ({} structures) ",
+        ep.major,
+        ep.minor,
+        b.smbios.structures.len()
+    );
+
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, chunks[0], &mut b.state);
+        f.render_widget(
+            Paragraph::new("j/k: move  Enter: decode  x: hex view  q: quit"),
+            chunks[1],
+        );
+    })?;
+    Ok(())
+}
+
+/// Runs the structure browser until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, b: &mut SmbiosBrowser) -> io::Result<()> {
+    loop {
+        draw(terminal, b)?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('j') | KeyCode::Down => {
+                    let i = b.state.selected().unwrap_or(0);
+                    let last = b.smbios.structures.len().saturating_sub(1);
+                    b.state.select(Some((i + 1).min(last)));
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    let i = b.state.selected().unwrap_or(0);
+                    b.state.select(Some(i.saturating_sub(1)));
+                }
+                KeyCode::Enter => {
+                    if let Some(structure) = b.selected().cloned() {
+                        structure_screen(terminal, &structure)?;
+                    }
+                }
+                KeyCode::Char('x') => {
+                    if let Some(structure) = b.selected().cloned() {
+                        hex_view(terminal, &structure)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        let mut ep = vec![0u8; 0x18];
+        ep[..5].copy_from_slice(b"_SM3_");
+        ep[7] = 3;
+        ep[8] = 4;
+        ep[0x0c..0x10].copy_from_slice(&0x1000u32.to_le_bytes());
+        ep[0x10..0x18].copy_from_slice(&0x000f_0000u64.to_le_bytes());
+        let ep = EntryPoint::parse(&ep).unwrap();
+        assert_eq!((ep.major, ep.minor), (3, 4));
+        assert_eq!(ep.table_address, 0xf0000);
+        assert_eq!(ep.structure_count, None);
+
+        let mut table = vec![];
+        // BIOS with vendor and version strings, date string 0 is unset
+        let mut bios = vec![0u8, 0x18, 0x00, 0x00, 1, 2, 0x00, 0xe8, 0, 0x0f];
+        bios.extend_from_slice(&(1u64 << 7).to_le_bytes());
+        bios.extend_from_slice(&[0, 0, 1, 2, 0xff, 0xff]);
+        table.extend_from_slice(&bios);
+        table.extend_from_slice(b"Vendor\0 1.2.3 \0\0");
+        // memory device of 16 GiB, DDR5 at 4800 MT/s
+        let mut dimm = vec![0u8; 0x22];
+        dimm[0] = 17;
+        dimm[1] = 0x22;
+        dimm[2] = 0x11;
+        dimm[0x0c..0x0e].copy_from_slice(&16384u16.to_le_bytes());
+        dimm[0x0e] = 0x09;
+        dimm[0x10] = 1;
+        dimm[0x12] = 0x22;
+        dimm[0x15..0x17].copy_from_slice(&4800u16.to_le_bytes());
+        table.extend_from_slice(&dimm);
+        table.extend_from_slice(b"DIMM A1\0\0");
+        // structure without strings, then the end of the table
+        table.extend_from_slice(&[32, 0x0b, 0x22, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        table.extend_from_slice(&[127, 4, 0xfe, 0xff, 0, 0]);
+        table.extend_from_slice(&[0xde, 0xad]);
+
+        let structures = parse_table(&table);
+        assert_eq!(structures.len(), 4);
+        match &structures[0].data {
+            StructureData::Bios(b) => {
+                assert_eq!(b.vendor.as_deref(), Some("Vendor"));
+                assert_eq!(b.version.as_deref(), Some("1.2.3"));
+                assert_eq!(b.release_date, None);
+                assert_eq!(b.address, 0xe8000);
+                assert_eq!(b.rom_size, 1024);
+                assert_eq!(b.release, Some((1, 2)));
+                assert_eq!(b.firmware_release, None);
+            }
+            data => panic!("unexpected {:?}", data),
+        }
+        match &structures[1].data {
+            StructureData::MemoryDevice(m) => {
+                assert_eq!(structures[1].handle, 0x11);
+                assert_eq!(m.size, Some(16384));
+                assert_eq!(m.locator.as_deref(), Some("DIMM A1"));
+                assert_eq!(m.bank_locator, None);
+                assert_eq!(memory_type_name(m.memory_type), "DDR5");
+                assert_eq!(m.speed, Some(4800));
+            }
+            data => panic!("unexpected {:?}", data),
+        }
+        assert!(structures[2].strings.is_empty());
+        assert_eq!(structures[2].raw.len(), 0x0b + 2);
+        assert_eq!(structures[3].kind, END_OF_TABLE);
+    }
+}
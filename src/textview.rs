@@ -0,0 +1,82 @@
+//! Scrollable text screen shared by the TUI views that show decoded data
+
+use crossterm::event::{self, Event, KeyCode};
+use std::io;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::text::Spans;
+use tui::widgets::{Block, Borders, Paragraph};
+use tui::Terminal;
+
+/// A scrollable screen of text
+pub struct TextView {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+impl TextView {
+    pub fn new(title: &str, text: &str) -> Self {
+        Self {
+            title: String::from(title),
+            // tabs are not expanded by the terminal backend
+            lines: text.lines().map(|l| l.replace('\t', "    ")).collect(),
+            scroll: 0,
+        }
+    }
+
+    pub fn scroll_down(&mut self, lines: u16) {
+        let max = self.lines.len().saturating_sub(1) as u16;
+        self.scroll = (self.scroll + lines).min(max);
+    }
+
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Handles the scroll keys, returns whether `code` was one of them
+    pub fn scroll_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_down(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_up(1),
+            KeyCode::Char('n') | KeyCode::PageDown => self.scroll_down(16),
+            KeyCode::Char('p') | KeyCode::PageUp => self.scroll_up(16),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Draws `view` with a line of `help` below it
+pub fn draw<B: Backend>(terminal: &mut Terminal<B>, view: &TextView, help: &str) -> io::Result<()> {
+    let lines: Vec<Spans> = view.lines.iter().map(|l| Spans::from(l.as_str())).collect();
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(f.size());
+        let text = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" {} ", view.title)),
+            )
+            .scroll((view.scroll, 0));
+        f.render_widget(text, chunks[0]);
+        f.render_widget(Paragraph::new(help), chunks[1]);
+    })?;
+    Ok(())
+}
+
+/// Shows `view` until the user quits
+pub fn start<B: Backend>(terminal: &mut Terminal<B>, view: &mut TextView) -> io::Result<()> {
+    loop {
+        draw(terminal, view, "j/k: scroll  n/p: page  q/Esc: back")?;
+
+        if let Event::Key(key) = event::read()? {
+            if !view.scroll_key(key.code) && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(());
+            }
+        }
+    }
+}
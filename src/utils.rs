@@ -27,6 +27,41 @@ pub fn encode_hex(bytes: &[u8]) -> String {
     s
 }
 
+/// parse an integer given either in hex with a `0x` prefix or in decimal
+pub fn parse_u64(s: &str) -> Result<u64, ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse::<u64>(),
+    }
+}
+
+/// format bytes as `hexdump -C` style lines, unreadable bytes are shown as `XX`
+pub fn hexdump(offset: u64, bytes: &[Option<u8>]) -> String {
+    let mut s = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        write!(&mut s, "{:016x}  ", offset + (i * 16) as u64).unwrap();
+        for j in 0..16 {
+            match line.get(j) {
+                Some(Some(b)) => write!(&mut s, "{:02x} ", b).unwrap(),
+                Some(None) => s.push_str("XX "),
+                None => s.push_str("   "),
+            }
+            if j == 7 {
+                s.push(' ');
+            }
+        }
+        s.push_str(" |");
+        for b in line {
+            match b {
+                Some(b) if b.is_ascii_graphic() || *b == b' ' => s.push(*b as char),
+                _ => s.push('.'),
+            }
+        }
+        s.push_str("|\n");
+    }
+    s
+}
+
 /// Initialize logging
 /// refer to: https://github.com/estk/log4rs/blob/master/examples/log_to_file.rs
 pub fn init_logging() {